@@ -2,4 +2,171 @@ pub mod info;
 pub mod params;
 mod prelude;
 pub mod residents;
+use std::collections::BTreeSet;
+
 pub use prelude::*;
+use roead::{aamp::ParameterIO, byml::Map, sarc::Sarc};
+use smartstring::alias::String;
+
+use self::params::{
+    aiprog::AIProgram, aischedule::AISchedule, aslist::ASList, atcllist::AttClientList,
+    aware::Awareness, bonectrl::BoneControl, chemical::Chemical, damage::DamageParam,
+    drop::DropTable, general::GeneralParamList, life::LifeCondition, link::ActorLink, lod::Lod,
+    modellist::ModelList, physics::Physics, recipe::Recipe, rgbw::RagdollBlendWeight,
+    rgconfiglist::RagdollConfigList, shop::ShopData, umii::UMii,
+};
+use crate::{prelude::Resource, Result, UKError};
+
+type TargetPath = fn(&str) -> std::string::String;
+
+/// Actor link targets which name a file in the actor pack
+static LINK_TARGETS: &[(&str, TargetPath)] = &[
+    ("AIProgramUser", AIProgram::path),
+    ("AIScheduleUser", AISchedule::path),
+    ("ASUser", ASList::path),
+    ("AttentionUser", AttClientList::path),
+    ("AwarenessUser", Awareness::path),
+    ("BoneControlUser", BoneControl::path),
+    ("ChemicalUser", Chemical::path),
+    ("DamageParamUser", DamageParam::path),
+    ("DropTableUser", DropTable::path),
+    ("GParamUser", GeneralParamList::path),
+    ("LifeConditionUser", LifeCondition::path),
+    ("LODUser", Lod::path),
+    ("ModelUser", ModelList::path),
+    ("PhysicsUser", Physics::path),
+    ("RecipeUser", Recipe::path),
+    ("RgBlendWeightUser", RagdollBlendWeight::path),
+    ("RgConfigListUser", RagdollConfigList::path),
+    ("ShopDataUser", ShopData::path),
+    ("UMiiUser", UMii::path),
+];
+
+/// An actor pack taken as a whole. The files in a pack are merged one by one,
+/// so this is used on the merged pack to check that they still fit together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Actor {
+    pub name:  String,
+    pub link:  ActorLink,
+    /// Paths of every file in the pack
+    pub files: BTreeSet<String>,
+}
+
+/// A link target naming a file which is missing from the actor pack
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanglingLink {
+    pub user: &'static str,
+    pub path: std::string::String,
+}
+
+impl Actor {
+    pub fn from_sarc(sarc: &Sarc) -> Result<Self> {
+        let (name, link) = sarc
+            .files()
+            .find_map(|file| {
+                let name = file
+                    .name?
+                    .strip_prefix("Actor/ActorLink/")?
+                    .strip_suffix(".bxml")?;
+                Some((name, file.data))
+            })
+            .ok_or(UKError::MissingSarcFile("Actor/ActorLink"))?;
+        Ok(Self {
+            name:  name.into(),
+            link:  ActorLink::try_from(ParameterIO::from_binary(link)?)?,
+            files: sarc
+                .files()
+                .filter_map(|f| f.name.map(Into::into))
+                .collect(),
+        })
+    }
+
+    /// The file name set for a link target, if any
+    fn target(&self, user: &str) -> Option<&str> {
+        self.link
+            .targets
+            .get(user)
+            .and_then(|t| t.as_str().ok())
+            .filter(|t| !t.is_empty() && *t != "Dummy")
+    }
+
+    /// Finds link targets naming files which are not in the pack. The game
+    /// crashes loading an actor like this.
+    pub fn dangling_links(&self) -> Vec<DanglingLink> {
+        LINK_TARGETS
+            .iter()
+            .filter_map(|(user, path)| {
+                let path = path(self.target(user)?);
+                (!self.files.contains(path.as_str())).then_some(DanglingLink { user, path })
+            })
+            .collect()
+    }
+
+    /// Sets the actor info fields which can be derived from the files in the
+    /// pack, such as the model, profile, and general parameters.
+    pub fn update_info(&self, sarc: &Sarc, info: &mut Map) -> Result<()> {
+        self.link.update_info(info)?;
+        self.update_info_from::<GeneralParamList>(sarc, "GParamUser", info)?;
+        self.update_info_from::<ModelList>(sarc, "ModelUser", info)?;
+        self.update_info_from::<Physics>(sarc, "PhysicsUser", info)?;
+        self.update_info_from::<Chemical>(sarc, "ChemicalUser", info)?;
+        self.update_info_from::<LifeCondition>(sarc, "LifeConditionUser", info)?;
+        self.update_info_from::<Recipe>(sarc, "RecipeUser", info)?;
+        self.update_info_from::<DropTable>(sarc, "DropTableUser", info)?;
+        Ok(())
+    }
+
+    fn update_info_from<T: InfoSource + ParameterResource>(
+        &self,
+        sarc: &Sarc,
+        user: &str,
+        info: &mut Map,
+    ) -> Result<()> {
+        if let Some(data) = self
+            .target(user)
+            .and_then(|target| sarc.get_data(T::path(target).as_str()))
+        {
+            T::from_binary(data)?.update_info(info)?;
+        }
+        Ok(())
+    }
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod tests {
+    use roead::{
+        aamp::{Parameter, ParameterIO, ParameterObject},
+        sarc::{Sarc, SarcWriter},
+    };
+
+    use super::{params::link::ActorLink, Actor, DanglingLink};
+
+    #[test]
+    fn dangling_links() {
+        let link = ActorLink {
+            targets: [
+                ("ModelUser", Parameter::StringRef("Lynel".into())),
+                ("PhysicsUser", Parameter::StringRef("Lynel_New".into())),
+                ("GParamUser", Parameter::StringRef("Dummy".into())),
+                ("ProfileUser", Parameter::StringRef("Enemy".into())),
+            ]
+            .into_iter()
+            .collect::<ParameterObject>(),
+            ..Default::default()
+        };
+        let mut writer = SarcWriter::new(roead::Endian::Big);
+        writer.add_file(
+            "Actor/ActorLink/Enemy_Lynel.bxml",
+            ParameterIO::from(link).to_binary(),
+        );
+        writer.add_file("Actor/ModelList/Lynel.bmodellist", b"AAMP".to_vec());
+        let sarc = Sarc::new(writer.to_binary()).unwrap();
+        let actor = Actor::from_sarc(&sarc).unwrap();
+        assert_eq!(actor.name.as_str(), "Enemy_Lynel");
+        assert_eq!(actor.dangling_links(), vec![DanglingLink {
+            user: "PhysicsUser",
+            path: "Actor/Physics/Lynel_New.bphysics".into(),
+        }]);
+    }
+}
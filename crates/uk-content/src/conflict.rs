@@ -0,0 +1,382 @@
+use std::collections::BTreeMap;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use smartstring::alias::String;
+use uk_localization::string_ext::LocString;
+
+/// Conflicts and other problems found while merging, by canonical resource
+/// path.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConflictReport {
+    /// Fields which more than one mod set to different values
+    #[serde(default)]
+    pub fields:   BTreeMap<String, Vec<FieldConflict>>,
+    /// Problems with merged resources as a whole
+    #[serde(default)]
+    pub warnings: BTreeMap<String, Vec<MergeWarning>>,
+}
+
+impl ConflictReport {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.warnings.is_empty()
+    }
+
+    pub fn remove(&mut self, resource: &str) {
+        self.fields.remove(resource);
+        self.warnings.remove(resource);
+    }
+
+    /// Updates the report with the results for rebuilt resources. Resources
+    /// with nothing left to report are dropped.
+    pub fn update(&mut self, rebuilt: ConflictReport) {
+        fn update<T>(report: &mut BTreeMap<String, Vec<T>>, rebuilt: BTreeMap<String, Vec<T>>) {
+            for (resource, found) in rebuilt {
+                if found.is_empty() {
+                    report.remove(&resource);
+                } else {
+                    report.insert(resource, found);
+                }
+            }
+        }
+        update(&mut self.fields, rebuilt.fields);
+        update(&mut self.warnings, rebuilt.warnings);
+    }
+}
+
+/// A field in a resource which two mods set to different values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldConflict {
+    pub resource: String,
+    /// Path to the field in the resource, separated by slashes
+    pub path:     std::string::String,
+    pub mod_a:    String,
+    pub value_a:  std::string::String,
+    pub mod_b:    String,
+    pub value_b:  std::string::String,
+    /// The mod whose value ended up in the merged resource
+    pub winner:   String,
+}
+
+/// A problem with a merged resource which isn't down to a single field, such
+/// as a link to a file missing from a merged actor pack.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeWarning {
+    pub resource: String,
+    /// Mods which change the resource, in load order
+    pub mods:     Vec<String>,
+    pub message:  std::string::String,
+}
+
+/// A choice for a conflicting field, applied over a resource after merging.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resolution {
+    pub resource: String,
+    /// Path to the field, as in [`FieldConflict`]
+    pub path:     std::string::String,
+    #[serde(flatten)]
+    pub choice:   ResolutionChoice,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResolutionChoice {
+    /// Take the value set by the named mod
+    Mod(String),
+    /// Use this value
+    Value(Value),
+}
+
+impl ResolutionChoice {
+    fn winner(&self) -> String {
+        match self {
+            Self::Mod(name) => name.clone(),
+            Self::Value(_) => "Conflicts_CustomValue".localize().as_ref().into(),
+        }
+    }
+}
+
+/// Tracks which mod last set each field of a resource while its diffs are
+/// merged in load order, recording each time one mod overwrites another.
+#[derive(Debug, Default)]
+pub struct ConflictTracker {
+    resource:  String,
+    fields:    BTreeMap<std::string::String, (String, Value)>,
+    conflicts: Vec<FieldConflict>,
+}
+
+impl ConflictTracker {
+    pub fn new(resource: impl Into<String>) -> Self {
+        Self {
+            resource: resource.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Records the fields set by a mod's diff. Later mods win, so any field
+    /// an earlier mod set to a different value is a conflict.
+    pub fn record(&mut self, mod_name: &str, diff: &impl Serialize) {
+        let value = match serde_json::to_value(diff) {
+            Ok(value) => value,
+            Err(e) => {
+                log::debug!("Cannot track conflicts in {}: {e}", self.resource);
+                return;
+            }
+        };
+        let mut leaves = vec![];
+        flatten(std::string::String::new(), value, &mut leaves);
+        for (path, value) in leaves {
+            match self.fields.get(&path) {
+                Some((prev_mod, prev_value))
+                    if prev_mod.as_str() != mod_name && *prev_value != value =>
+                {
+                    log::debug!(
+                        "{} overwrites {} from {} in {}",
+                        mod_name,
+                        path,
+                        prev_mod,
+                        self.resource
+                    );
+                    self.conflicts.push(FieldConflict {
+                        resource: self.resource.clone(),
+                        path:     path.clone(),
+                        mod_a:    prev_mod.clone(),
+                        value_a:  prev_value.to_string(),
+                        mod_b:    mod_name.into(),
+                        value_b:  value.to_string(),
+                        winner:   mod_name.into(),
+                    });
+                }
+                _ => (),
+            }
+            self.fields.insert(path, (mod_name.into(), value));
+        }
+    }
+
+    pub fn into_conflicts(self) -> Vec<FieldConflict> {
+        self.conflicts
+    }
+
+    /// Returns the conflicts, given the merged resource. Conflicts in a list
+    /// where the earlier value is still in the merged list are dropped, since
+    /// the mods added separate entries rather than overwriting one.
+    pub fn finish(self, merged: &impl Serialize) -> Vec<FieldConflict> {
+        let merged = match serde_json::to_value(merged) {
+            Ok(merged) => merged,
+            Err(_) => return self.conflicts,
+        };
+        self.conflicts
+            .into_iter()
+            .filter(|conflict| !in_list(&merged, &conflict.path, &conflict.value_a))
+            .collect()
+    }
+}
+
+/// Whether any entry of the innermost list on a field path has the given
+/// value at the same field
+fn in_list(merged: &Value, path: &str, value: &str) -> bool {
+    let keys: Vec<&str> = path.split('/').collect();
+    let Some(index) = keys.iter().rposition(|key| key.parse::<usize>().is_ok()) else {
+        return false;
+    };
+    let parent = if index == 0 {
+        Some(merged)
+    } else {
+        lookup(merged, &keys[..index].join("/"))
+    };
+    let Some(Value::Array(list)) = parent else {
+        return false;
+    };
+    let field = keys[index + 1..].join("/");
+    list.iter().any(|entry| {
+        let found = if field.is_empty() {
+            Some(entry)
+        } else {
+            lookup(entry, &field)
+        };
+        found.is_some_and(|found| found.to_string() == value)
+    })
+}
+
+/// Overlays chosen values on a merged resource. `diffs` are the diffs that
+/// were merged, with the mod each came from, so values can be taken from a
+/// particular mod. The winners of any resolved conflicts are updated to match.
+pub fn apply_resolutions<T: Serialize + DeserializeOwned>(
+    merged: T,
+    resolutions: &[Resolution],
+    diffs: &[(&str, &T)],
+    conflicts: &mut [FieldConflict],
+) -> T {
+    let mut value = match serde_json::to_value(&merged) {
+        Ok(value) => value,
+        Err(e) => {
+            log::warn!("Cannot apply conflict resolutions: {e}");
+            return merged;
+        }
+    };
+    let diffs: Vec<(&str, Value)> = diffs
+        .iter()
+        .filter_map(|(mod_name, diff)| Some((*mod_name, serde_json::to_value(diff).ok()?)))
+        .collect();
+    let mut changed = false;
+    for resolution in resolutions {
+        let new_value = match &resolution.choice {
+            ResolutionChoice::Value(value) => Some(value.clone()),
+            ResolutionChoice::Mod(name) => {
+                diffs
+                    .iter()
+                    .rev()
+                    .filter(|(mod_name, _)| *mod_name == name.as_str())
+                    .find_map(|(_, diff)| lookup(diff, &resolution.path).cloned())
+            }
+        };
+        let (Some(new_value), Some(field)) = (new_value, lookup_mut(&mut value, &resolution.path))
+        else {
+            log::warn!(
+                "Could not resolve {} in {}, the field or chosen value is missing",
+                resolution.path,
+                resolution.resource
+            );
+            continue;
+        };
+        log::info!(
+            "Using {} for {} in {}",
+            resolution.choice.winner(),
+            resolution.path,
+            resolution.resource
+        );
+        *field = new_value;
+        changed = true;
+        for conflict in conflicts.iter_mut().filter(|c| c.path == resolution.path) {
+            conflict.winner = resolution.choice.winner();
+        }
+    }
+    if !changed {
+        return merged;
+    }
+    serde_json::from_value(value).unwrap_or_else(|e| {
+        log::warn!("Cannot apply conflict resolutions: {e}");
+        merged
+    })
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('/').try_fold(value, |value, key| {
+        match value {
+            Value::Object(map) => map.get(key),
+            Value::Array(list) => list.get(key.parse::<usize>().ok()?),
+            _ => None,
+        }
+    })
+}
+
+fn lookup_mut<'a>(value: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    path.split('/').try_fold(value, |value, key| {
+        match value {
+            Value::Object(map) => map.get_mut(key),
+            Value::Array(list) => list.get_mut(key.parse::<usize>().ok()?),
+            _ => None,
+        }
+    })
+}
+
+fn flatten(path: std::string::String, value: Value, out: &mut Vec<(std::string::String, Value)>) {
+    let join = |key: &dyn std::fmt::Display| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}/{key}")
+        }
+    };
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                flatten(join(&key), value, out);
+            }
+        }
+        Value::Array(list) if !list.is_empty() => {
+            for (i, value) in list.into_iter().enumerate() {
+                flatten(join(&i), value, out);
+            }
+        }
+        value => out.push((path, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn track_conflicts() {
+        let mut tracker = super::ConflictTracker::new("Actor/Pack/Enemy_Lynel_Junior.sbactorpack");
+        let diff = |health: i32, drop: &str| {
+            BTreeMap::from([
+                ("General", BTreeMap::from([("Life", health.to_string())])),
+                ("Drop", BTreeMap::from([("Item", drop.to_owned())])),
+            ])
+        };
+        tracker.record("Mod A", &diff(2000, "Item_Apple"));
+        tracker.record("Mod A", &diff(3000, "Item_Apple"));
+        tracker.record("Mod B", &diff(3000, "Item_Banana"));
+        let conflicts = tracker.into_conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, "Drop/Item");
+        assert_eq!(conflicts[0].mod_a.as_str(), "Mod A");
+        assert_eq!(conflicts[0].value_a, "\"Item_Apple\"");
+        assert_eq!(conflicts[0].winner.as_str(), "Mod B");
+    }
+
+    #[test]
+    fn appended_entries() {
+        let mut tracker = super::ConflictTracker::new("Actor/ActorLink/Enemy_Lynel_Junior.bxml");
+        let diff = |tags: &[&str]| BTreeMap::from([("Tags", tags.to_vec())]);
+        tracker.record("Mod A", &diff(&["Lynel", "Fire"]));
+        tracker.record("Mod B", &diff(&["Lynel", "Ice"]));
+        tracker.record("Mod C", &diff(&["Lynel", "Shock"]));
+        // Mod B added its tag alongside Mod A's, but Mod C replaced Mod B's
+        let conflicts = tracker.finish(&diff(&["Lynel", "Fire", "Shock"]));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, "Tags/1");
+        assert_eq!(conflicts[0].mod_a.as_str(), "Mod B");
+    }
+
+    #[test]
+    fn resolve_conflicts() {
+        use super::{Resolution, ResolutionChoice};
+
+        type Diff = BTreeMap<std::string::String, BTreeMap<std::string::String, i32>>;
+        let diff = |life: i32, drop: i32| -> Diff {
+            BTreeMap::from([
+                ("General".into(), BTreeMap::from([("Life".into(), life)])),
+                ("Drop".into(), BTreeMap::from([("Count".into(), drop)])),
+            ])
+        };
+        let (mod_a, mod_b) = (diff(100, 1), diff(200, 5));
+        let mut tracker = super::ConflictTracker::new("Lynel");
+        tracker.record("Mod A", &mod_a);
+        tracker.record("Mod B", &mod_b);
+        let mut conflicts = tracker.into_conflicts();
+        let resolutions = [
+            Resolution {
+                resource: "Lynel".into(),
+                path:     "General/Life".into(),
+                choice:   ResolutionChoice::Mod("Mod A".into()),
+            },
+            Resolution {
+                resource: "Lynel".into(),
+                path:     "Drop/Count".into(),
+                choice:   ResolutionChoice::Value(3.into()),
+            },
+        ];
+        let merged = super::apply_resolutions(
+            mod_b.clone(),
+            &resolutions,
+            &[("Mod A", &mod_a), ("Mod B", &mod_b)],
+            &mut conflicts,
+        );
+        assert_eq!(merged, diff(100, 3));
+        assert_eq!(conflicts[0].winner.as_str(), "(custom value)");
+        assert_eq!(conflicts[1].winner.as_str(), "Mod A");
+    }
+}
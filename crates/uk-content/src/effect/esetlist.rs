@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+
+use crate::{
+    prelude::*,
+    util::{BinReader, DeleteMap},
+    Result, UKError,
+};
+
+const HEADER_SIZE: usize = 0x10;
+const ENTRY_SIZE: usize = 0x10;
+/// PTCL data is uploaded to the GPU as-is, so each section is aligned
+const PTCL_ALIGNMENT: usize = 0x100;
+
+/// An emitter set and the PTCL section holding its emitters, textures, and
+/// shaders. The PTCL data is kept whole, since emitters refer to the rest of
+/// their section by offset.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmitterSet {
+    pub user_data: u32,
+    pub ptcl: Vec<u8>,
+}
+
+/// A particle effect list (`.esetlist`). Emitter sets are keyed by name, so
+/// mods changing different effects on the same actor merge cleanly.
+///
+/// The file has a 0x10 byte header (`SESL`, byte order mark, version, set
+/// count, string table offset), then a 0x10 byte entry for each set (name
+/// offset into the string table, PTCL offset and size, user data), then the
+/// PTCL sections, then the set names.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EffectSetList {
+    pub version: u16,
+    /// Emitter sets by name, in list order
+    pub sets:    DeleteMap<String, EmitterSet>,
+}
+
+impl Mergeable for EffectSetList {
+    fn diff(&self, other: &Self) -> Self {
+        Self {
+            version: other.version,
+            sets:    self.sets.diff(&other.sets),
+        }
+    }
+
+    fn merge(&self, diff: &Self) -> Self {
+        Self {
+            version: diff.version,
+            sets:    self.sets.merge(&diff.sets),
+        }
+    }
+}
+
+impl Resource for EffectSetList {
+    fn from_binary(data: impl AsRef<[u8]>) -> Result<Self> {
+        let data = data.as_ref();
+        if data.get(..4) != Some(b"SESL") {
+            return Err(UKError::Other("Not an ESET list"));
+        }
+        let endian = match data.get(4..6) {
+            Some([0xFE, 0xFF]) => Endian::Big,
+            Some([0xFF, 0xFE]) => Endian::Little,
+            _ => return Err(UKError::Other("Invalid ESET list byte order mark")),
+        };
+        let reader = BinReader::new(data, endian, "ESET list");
+        let version = reader.u16(0x06)?;
+        let count = reader.u32(0x08)? as usize;
+        let strings = reader.u32(0x0C)? as usize;
+        let mut sets = DeleteMap::with_capacity(count);
+        for i in 0..count {
+            let pos = HEADER_SIZE + i * ENTRY_SIZE;
+            let name = reader.string(strings + reader.u32(pos)? as usize)?;
+            let offset = reader.u32(pos + 0x4)? as usize;
+            let size = reader.u32(pos + 0x8)? as usize;
+            sets.insert(name, EmitterSet {
+                user_data: reader.u32(pos + 0xC)?,
+                ptcl: reader.bytes(offset, size)?.to_vec(),
+            });
+        }
+        Ok(Self { version, sets })
+    }
+
+    fn into_binary(self, endian: Endian) -> Vec<u8> {
+        let half = |value: u16| {
+            match endian {
+                Endian::Big => value.to_be_bytes(),
+                Endian::Little => value.to_le_bytes(),
+            }
+        };
+        let word = |value: u32| {
+            match endian {
+                Endian::Big => value.to_be_bytes(),
+                Endian::Little => value.to_le_bytes(),
+            }
+        };
+        let mut data = Vec::with_capacity(
+            HEADER_SIZE
+                + self
+                    .sets
+                    .values()
+                    .map(|set| set.ptcl.len() + PTCL_ALIGNMENT)
+                    .sum::<usize>(),
+        );
+        data.extend(b"SESL");
+        data.extend(half(0xFEFF));
+        data.extend(half(self.version));
+        data.extend(word(self.sets.len() as u32));
+        data.extend(word(0)); // String table offset, set below
+        data.resize(HEADER_SIZE + self.sets.len() * ENTRY_SIZE, 0);
+
+        let mut names = Vec::new();
+        for (i, (name, set)) in self.sets.iter().enumerate() {
+            data.resize(data.len().next_multiple_of(PTCL_ALIGNMENT), 0);
+            let pos = HEADER_SIZE + i * ENTRY_SIZE;
+            let entry = [
+                names.len() as u32,
+                data.len() as u32,
+                set.ptcl.len() as u32,
+                set.user_data,
+            ];
+            for (j, value) in entry.into_iter().enumerate() {
+                data[pos + j * 4..pos + j * 4 + 4].copy_from_slice(&word(value));
+            }
+            data.extend(&set.ptcl);
+            names.extend(name.as_bytes());
+            names.push(0);
+        }
+
+        let strings = data.len().next_multiple_of(4);
+        data.resize(strings, 0);
+        data[0x0C..0x10].copy_from_slice(&word(strings as u32));
+        data.extend(names);
+        data
+    }
+
+    fn path_matches(path: impl AsRef<std::path::Path>) -> bool {
+        path.as_ref()
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(|x| x.ends_with("esetlist"))
+            .unwrap_or(false)
+    }
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(data: &[u8]) -> EmitterSet {
+        EmitterSet {
+            user_data: 0,
+            ptcl: [b"VFXB".as_slice(), data].concat(),
+        }
+    }
+
+    fn list() -> EffectSetList {
+        EffectSetList {
+            version: 1,
+            sets:    [
+                ("Lynel_Fire".into(), set(&[1, 2, 3])),
+                ("Lynel_Dead".into(), EmitterSet {
+                    user_data: 7,
+                    ..set(&[4; 0x120])
+                }),
+                ("Lynel_Roar".into(), set(&[])),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    #[test]
+    fn ptcl_alignment() {
+        let list = list();
+        for endian in [Endian::Big, Endian::Little] {
+            let data = list.clone().into_binary(endian);
+            let reader = BinReader::new(&data, endian, "ESET list");
+            for i in 0..list.sets.len() {
+                let offset = reader.u32(HEADER_SIZE + i * ENTRY_SIZE + 0x4).unwrap() as usize;
+                assert_eq!(offset % PTCL_ALIGNMENT, 0);
+            }
+            assert_eq!(EffectSetList::from_binary(data).unwrap(), list);
+        }
+    }
+
+    #[test]
+    fn merge_edit_and_removal() {
+        let base = list();
+        let mut mod_a = base.clone();
+        mod_a.sets.insert(String::from("Lynel_Fire"), set(&[9, 9]));
+        let mut mod_b = EffectSetList {
+            sets: base
+                .sets
+                .iter()
+                .filter(|(name, _)| name.as_str() != "Lynel_Roar")
+                .map(|(name, set)| (name.clone(), set.clone()))
+                .collect(),
+            ..base.clone()
+        };
+        mod_b.sets.insert(String::from("Lynel_Ice"), set(&[5]));
+        let merged = base.merge(&base.diff(&mod_a)).merge(&base.diff(&mod_b));
+        assert_eq!(
+            merged.sets.get(String::from("Lynel_Fire")).unwrap(),
+            &set(&[9, 9])
+        );
+        assert!(!merged.sets.contains_key(String::from("Lynel_Roar")));
+        let names: Vec<&str> = merged.sets.keys().map(|name| name.as_str()).collect();
+        assert_eq!(names, ["Lynel_Fire", "Lynel_Dead", "Lynel_Ice"]);
+    }
+
+    #[test]
+    fn ptcl_out_of_bounds() {
+        let mut data = list().into_binary(Endian::Little);
+        data[HEADER_SIZE + 0x4..HEADER_SIZE + 0x8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(EffectSetList::from_binary(&data).is_err());
+    }
+
+    #[test]
+    #[ignore = "needs an effect list from Wii U and Switch game dumps"]
+    fn stock_list() {
+        for (path, endian) in [
+            ("test/Effect/Lynel.esetlist", Endian::Big),
+            ("test/Effect/Lynel.nx.esetlist", Endian::Little),
+        ] {
+            let data = std::fs::read(path).unwrap();
+            let list = EffectSetList::from_binary(&data).unwrap();
+            assert!(!list.sets.is_empty());
+            assert_eq!(list.into_binary(endian), data);
+        }
+    }
+
+    #[test]
+    fn compressed_name() {
+        assert!(EffectSetList::path_matches("Effect/Lynel.esetlist"));
+        assert!(EffectSetList::path_matches("Effect/Lynel.sesetlist"));
+        assert!(!EffectSetList::path_matches("Effect/Lynel.sesetlist.yml"));
+    }
+}
@@ -7,11 +7,13 @@ use thiserror::Error;
 
 pub mod actor;
 pub mod chemical;
+pub mod conflict;
 pub mod constants;
 pub mod cooking;
 pub mod data;
 pub mod demo;
 pub mod eco;
+pub mod effect;
 pub mod event;
 pub mod font;
 pub mod layout;
@@ -20,6 +22,7 @@ pub mod message;
 pub mod quest;
 pub mod resource;
 pub mod sound;
+pub mod terrain;
 pub mod tips;
 pub mod util;
 pub mod worldmgr;
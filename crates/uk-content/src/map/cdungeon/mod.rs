@@ -1 +1,282 @@
+use anyhow::Context;
+use roead::byml::Byml;
+use serde::{de::Error as _, Deserialize, Deserializer};
+use smartstring::alias::String;
 
+use crate::{
+    prelude::Mergeable,
+    util::{parsers::try_get_vecf, DeleteMap, HashMap},
+};
+
+/// A named position in a shrine, such as an entrance or a warp destination.
+/// Entries are keyed by shrine map and position name in the CDungeon static.
+/// Only these are merged: shrine map units go through `MapUnit`, and the
+/// `DungeonInfo` links in them aren't checked against these entries.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct EntryPos {
+    pub player_state:   Option<String>,
+    pub rotate:         DeleteMap<char, f32>,
+    pub translate:      DeleteMap<char, f32>,
+}
+
+impl TryFrom<&Byml> for EntryPos {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Byml) -> anyhow::Result<Self> {
+        let map = value.as_map()
+            .context("CDungeon StartPos node must be HashMap")?;
+        Ok(Self {
+            player_state: map.get("PlayerState")
+                .map(|b| b.as_string().context("CDungeon StartPos PlayerState must be String"))
+                .transpose()?
+                .cloned(),
+            rotate: try_get_vecf(map.get("Rotate")
+                .context("CDungeon StartPos must have Rotate")?)
+                .context("Invalid CDungeon StartPos Rotate")?,
+            translate: try_get_vecf(map.get("Translate")
+                .context("CDungeon StartPos must have Translate")?)
+                .context("Invalid CDungeon StartPos Translate")?,
+        })
+    }
+}
+
+/// How entries were stored before their axes were typed. Installed mods
+/// may still have diffs in this form.
+#[derive(Deserialize)]
+struct LegacyEntryPos {
+    rotate:       Byml,
+    translate:    Byml,
+    player_state: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CurrentEntryPos {
+    player_state: Option<String>,
+    rotate:       DeleteMap<char, f32>,
+    translate:    DeleteMap<char, f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EncodedEntryPos {
+    Current(CurrentEntryPos),
+    Legacy(LegacyEntryPos),
+}
+
+impl<'de> Deserialize<'de> for EntryPos {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match EncodedEntryPos::deserialize(deserializer)? {
+            EncodedEntryPos::Current(pos) => Ok(Self {
+                player_state: pos.player_state,
+                rotate:       pos.rotate,
+                translate:    pos.translate,
+            }),
+            EncodedEntryPos::Legacy(pos) => Ok(Self {
+                player_state: pos.player_state,
+                rotate:       try_get_vecf(&pos.rotate).map_err(D::Error::custom)?,
+                translate:    try_get_vecf(&pos.translate).map_err(D::Error::custom)?,
+            }),
+        }
+    }
+}
+
+impl EntryPos {
+    /// Whether the rotation and translation both have every axis
+    pub fn is_complete(&self) -> bool {
+        ['X', 'Y', 'Z']
+            .into_iter()
+            .all(|c| self.rotate.get(c).is_some() && self.translate.get(c).is_some())
+    }
+
+    /// Writes the entry with the map and position name it's keyed by
+    pub fn into_byml(self, map_name: &str, pos_name: &str) -> Byml {
+        let mut map: HashMap<String, Byml> = Default::default();
+        map.insert("Map".into(), Byml::String(map_name.into()));
+        map.insert("PosName".into(), Byml::String(pos_name.into()));
+        if let Some(p) = &self.player_state {
+            map.insert("PlayerState".into(), Byml::String(p.clone()));
+        }
+        map.insert("Rotate".into(), Byml::Map(self.rotate
+            .iter()
+            .map(|(k, v)| (k.to_string().into(), Byml::Float(*v)))
+            .collect::<HashMap<String, Byml>>()));
+        map.insert("Translate".into(), Byml::Map(self.translate
+            .iter()
+            .map(|(k, v)| (k.to_string().into(), Byml::Float(*v)))
+            .collect::<HashMap<String, Byml>>()));
+        Byml::Map(map)
+    }
+}
+
+impl Mergeable for EntryPos {
+    #[allow(clippy::obfuscated_if_else)]
+    fn diff(&self, other: &Self) -> Self {
+        Self {
+            player_state: other.player_state
+                .ne(&self.player_state)
+                .then(|| other.player_state.clone())
+                .unwrap_or_default(),
+            rotate: self.rotate.diff(&other.rotate),
+            translate: self.translate.diff(&other.translate),
+        }
+    }
+
+    fn merge(&self, diff: &Self) -> Self {
+        Self {
+            player_state: diff.player_state
+                .clone()
+                .or_else(|| self.player_state.clone()),
+            rotate: self.rotate.merge(&diff.rotate),
+            translate: self.translate.merge(&diff.translate),
+        }
+    }
+}
+
+/// The positions of one shrine map, by position name
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(transparent)]
+pub struct DungeonEntries(pub DeleteMap<String, EntryPos>);
+
+impl Mergeable for DungeonEntries {
+    fn diff(&self, other: &Self) -> Self {
+        Self(self.0.deep_diff(&other.0))
+    }
+
+    fn merge(&self, diff: &Self) -> Self {
+        Self(self.0.deep_merge(&diff.0))
+    }
+}
+
+impl DungeonEntries {
+    pub fn get(&self, pos_name: impl AsRef<str>) -> Option<&EntryPos> {
+        self.0.get(String::from(pos_name.as_ref()))
+    }
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use roead::byml::{map, Byml};
+    use smartstring::alias::String;
+
+    use crate::{
+        map::static_::Static,
+        prelude::Mergeable,
+        util::{DeleteMap, DeleteVec},
+    };
+
+    fn entry(map_name: &str, pos_name: &str, x: f32, y: f32) -> Byml {
+        map!(
+            "Map" => map_name.into(),
+            "PosName" => pos_name.into(),
+            "Rotate" => map!("X" => 0.0f32.into(), "Y" => 0.0f32.into(), "Z" => 0.0f32.into()),
+            "Translate" => map!("X" => x.into(), "Y" => y.into(), "Z" => 0.0f32.into()),
+        )
+    }
+
+    fn static_(entries: Vec<Byml>) -> Static {
+        (&map!("StartPos" => Byml::Array(entries)))
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn merge_entries() {
+        let base = static_(vec![
+            entry("Dungeon000", "Entrance_1", 0.0, 0.0),
+            entry("Dungeon000", "Warp", 10.0, 0.0),
+            entry("Dungeon001", "Entrance_1", 0.0, 0.0),
+        ]);
+        let mod_a = static_(vec![
+            entry("Dungeon000", "Entrance_1", 5.0, 0.0),
+            entry("Dungeon000", "Warp", 10.0, 0.0),
+        ]);
+        let mod_b = static_(vec![
+            entry("Dungeon000", "Entrance_1", 0.0, 3.0),
+            entry("Dungeon000", "Warp", 10.0, 0.0),
+            entry("Dungeon001", "Entrance_1", 0.0, 0.0),
+            entry("Dungeon200", "Entrance_1", 1.0, 1.0),
+        ]);
+        let merged = base.merge(&base.diff(&mod_a)).merge(&base.diff(&mod_b));
+        let entrance = merged
+            .start_pos
+            .get(String::from("Dungeon000"))
+            .unwrap()
+            .get("Entrance_1")
+            .unwrap();
+        assert_eq!(entrance.translate.get('X'), Some(&5.0));
+        assert_eq!(entrance.translate.get('Y'), Some(&3.0));
+        assert!(!merged.start_pos.contains_key(String::from("Dungeon001")));
+        assert!(merged.start_pos.contains_key(String::from("Dungeon200")));
+        let byml = Byml::from(merged);
+        assert_eq!(
+            byml.as_map().unwrap()["StartPos"].as_array().unwrap().len(),
+            3
+        );
+    }
+
+    #[test]
+    fn player_state_and_axes() {
+        let mut warp = entry("Dungeon050", "Warp", 2.0, 0.0);
+        if let Byml::Map(hash) = &mut warp {
+            hash.insert("PlayerState".into(), "Swim".into());
+        }
+        let parsed = static_(vec![warp]);
+        let pos = parsed
+            .start_pos
+            .get(String::from("Dungeon050"))
+            .unwrap()
+            .get("Warp")
+            .unwrap();
+        assert_eq!(pos.player_state.as_deref(), Some("Swim"));
+        assert!(pos.is_complete());
+        let mut partial = pos.clone();
+        partial.translate.set_delete('Z');
+        assert!(!partial.is_complete());
+    }
+
+    /// A Static diff as it was stored before entries had typed axes
+    #[derive(serde::Serialize)]
+    struct OldStatic {
+        general:   BTreeMap<String, DeleteVec<Byml>>,
+        start_pos: DeleteMap<String, DeleteMap<String, OldEntryPos>>,
+    }
+
+    #[derive(serde::Serialize)]
+    struct OldEntryPos {
+        rotate:       Byml,
+        translate:    Byml,
+        player_state: Option<String>,
+    }
+
+    #[test]
+    fn legacy_diff() {
+        let mut entries = DeleteMap::new();
+        entries.insert("Entrance_1", OldEntryPos {
+            rotate:       map!("X" => 0.0f32.into(), "Y" => 90.0f32.into(), "Z" => 0.0f32.into()),
+            translate:    map!("X" => 5.0f32.into(), "Y" => 1.0f32.into(), "Z" => 0.0f32.into()),
+            player_state: Some("Wait".into()),
+        });
+        let mut start_pos = DeleteMap::new();
+        start_pos.insert("Dungeon000", entries);
+        let old = minicbor_ser::to_vec(&OldStatic {
+            general: Default::default(),
+            start_pos,
+        })
+        .unwrap();
+        let diff: Static = minicbor_ser::from_slice(&old).unwrap();
+        let pos = diff
+            .start_pos
+            .get(String::from("Dungeon000"))
+            .unwrap()
+            .get("Entrance_1")
+            .unwrap();
+        assert_eq!(pos.rotate.get('Y'), Some(&90.0));
+        assert_eq!(pos.translate.get('X'), Some(&5.0));
+        assert_eq!(pos.player_state.as_deref(), Some("Wait"));
+        let current = minicbor_ser::to_vec(&diff).unwrap();
+        assert_eq!(minicbor_ser::from_slice::<Static>(&current).unwrap(), diff);
+    }
+}
@@ -0,0 +1,337 @@
+use smartstring::alias::String;
+
+use crate::{
+    prelude::Endian,
+    util::{BinReader, HashMap},
+    Result, UKError,
+};
+
+const MAGIC: [u8; 8] = [0x57, 0xE0, 0xE0, 0x57, 0x10, 0xC0, 0xC0, 0x10];
+const HEADER_SIZE: usize = 0x40;
+const ACTOR_SIZE: usize = 0x10;
+const SHAPE_SIZE: usize = 0xC;
+
+/// The header of a Havok packfile, such as a static compound (`.hksc`) or a
+/// navmesh (`.hknm2`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HavokHeader {
+    pub user_tag:         u32,
+    pub file_version:     u32,
+    pub pointer_size:     u8,
+    pub endian:           Endian,
+    /// Havok SDK version, e.g. `hk_2014.2.0-r1`
+    pub contents_version: String,
+    /// Name of the class of the root object
+    pub contents_class:   String,
+    pub sections:         Vec<HavokSection>,
+    contents_section:     usize,
+    contents_offset:      usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HavokSection {
+    pub name:          String,
+    /// Absolute offset of the section data
+    pub start:         usize,
+    /// Offsets of the fixup tables and the section end, relative to `start`
+    pub local_fixups:  usize,
+    pub global_fixups: usize,
+    pub end:           usize,
+}
+
+/// An actor in a static compound. Its hash ID matches a map object, whose
+/// collision is the range of shapes given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompoundActor {
+    pub hash_id:     u32,
+    pub srt_hash:    u32,
+    pub shape_start: u32,
+    pub shape_end:   u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompoundShape {
+    pub actor_index:     u32,
+    pub instance_id:     u32,
+    pub body_group:      u8,
+    pub body_layer_type: u8,
+}
+
+/// The instance lists of a static compound, which bakes the collision of the
+/// static objects in a map section or shrine. Only the `StaticCompoundInfo`
+/// at the start of the file is read, so this can't be written back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticCompound {
+    pub header: HavokHeader,
+    pub actors: Vec<CompoundActor>,
+    pub shapes: Vec<CompoundShape>,
+}
+
+impl HavokHeader {
+    pub fn from_binary(data: impl AsRef<[u8]>) -> Result<Self> {
+        let data = data.as_ref();
+        if data.get(..8) != Some(MAGIC.as_slice()) {
+            return Err(UKError::Other("Not a Havok packfile"));
+        }
+        let endian = match data.get(0x11) {
+            Some(1) => Endian::Little,
+            _ => Endian::Big,
+        };
+        let reader = BinReader::new(data, endian, "Havok file");
+        let file_version = reader.u32(0x0C)?;
+        let section_count = reader.u32(0x14)? as usize;
+        // Version 11 adds padding after the header and to each section header
+        let (mut pos, section_size) = if file_version >= 11 {
+            (HEADER_SIZE + reader.u16(0x3E)? as usize, 0x40)
+        } else {
+            (HEADER_SIZE, 0x30)
+        };
+        let mut sections = Vec::with_capacity(section_count);
+        for _ in 0..section_count {
+            sections.push(HavokSection {
+                name:          reader.fixed_string(pos, 20)?,
+                start:         reader.u32(pos + 0x14)? as usize,
+                local_fixups:  reader.u32(pos + 0x18)? as usize,
+                global_fixups: reader.u32(pos + 0x1C)? as usize,
+                end:           reader.u32(pos + 0x2C)? as usize,
+            });
+            pos += section_size;
+        }
+        let class_section = sections
+            .get(reader.u32(0x20)? as usize)
+            .ok_or(UKError::Other("Havok contents class section missing"))?;
+        let class_name = class_section.start + reader.u32(0x24)? as usize;
+        Ok(Self {
+            user_tag: reader.u32(0x08)?,
+            file_version,
+            pointer_size: reader.u8(0x10)?,
+            endian,
+            contents_version: reader.fixed_string(0x28, 16)?,
+            contents_class: reader.fixed_string(class_name, 256)?,
+            contents_section: reader.u32(0x18)? as usize,
+            contents_offset: reader.u32(0x1C)? as usize,
+            sections,
+        })
+    }
+}
+
+impl StaticCompound {
+    pub fn from_binary(data: impl AsRef<[u8]>) -> Result<Self> {
+        let data = data.as_ref();
+        let header = HavokHeader::from_binary(data)?;
+        if header.contents_class != "StaticCompoundInfo" {
+            return Err(UKError::OtherD(format!(
+                "Expected StaticCompoundInfo, found {}",
+                header.contents_class
+            )));
+        }
+        let reader = BinReader::new(data, header.endian, "Havok file");
+        let section = header
+            .sections
+            .get(header.contents_section)
+            .ok_or(UKError::Other("Havok contents section missing"))?;
+        // Pointers are stored as fixups from one section offset to another
+        let mut fixups = HashMap::default();
+        let mut pos = section.start + section.local_fixups;
+        while pos < section.start + section.global_fixups {
+            let src = reader.u32(pos)?;
+            if src != u32::MAX {
+                fixups.insert(src as usize, reader.u32(pos + 4)? as usize);
+            }
+            pos += 8;
+        }
+        let ptr = header.pointer_size.max(4) as usize;
+        let array = |pos: usize| -> Result<(usize, usize)> {
+            let count = reader.u32(pos + ptr)? as usize;
+            if count == 0 {
+                return Ok((0, 0));
+            }
+            let start = fixups
+                .get(&(pos - section.start))
+                .ok_or(UKError::Other("Unresolved array in static compound"))?;
+            Ok((section.start + start, count))
+        };
+        // The info starts with a 32-bit offset, then arrays of actors and shapes
+        let contents = section.start + header.contents_offset;
+        let (actors_pos, actor_count) = array(contents + ptr)?;
+        let (shapes_pos, shape_count) = array(contents + ptr + ptr + 8)?;
+        let actors = (0..actor_count)
+            .map(|i| {
+                let pos = actors_pos + i * ACTOR_SIZE;
+                Ok(CompoundActor {
+                    hash_id:     reader.u32(pos)?,
+                    srt_hash:    reader.u32(pos + 0x4)?,
+                    shape_start: reader.u32(pos + 0x8)?,
+                    shape_end:   reader.u32(pos + 0xC)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let shapes = (0..shape_count)
+            .map(|i| {
+                let pos = shapes_pos + i * SHAPE_SIZE;
+                Ok(CompoundShape {
+                    actor_index:     reader.u32(pos)?,
+                    instance_id:     reader.u32(pos + 0x4)?,
+                    body_group:      reader.u8(pos + 0x8)?,
+                    body_layer_type: reader.u8(pos + 0x9)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            header,
+            actors,
+            shapes,
+        })
+    }
+
+    /// Whether the compound has baked collision for a map object
+    pub fn contains(&self, hash_id: u32) -> bool {
+        self.actors.iter().any(|actor| actor.hash_id == hash_id)
+    }
+
+    pub fn path_matches(path: impl AsRef<std::path::Path>) -> bool {
+        path.as_ref()
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(|x| x.ends_with("hksc"))
+            .unwrap_or(false)
+    }
+}
+
+#[allow(clippy::unwrap_used)]
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a minimal version 11 packfile holding a `StaticCompoundInfo`
+    fn packfile(endian: Endian, pointer_size: usize, actors: &[u32]) -> Vec<u8> {
+        let word = |value: u32| {
+            match endian {
+                Endian::Big => value.to_be_bytes(),
+                Endian::Little => value.to_le_bytes(),
+            }
+        };
+        let mut data = MAGIC.to_vec();
+        data.extend(word(0));
+        data.extend(word(11));
+        data.extend([pointer_size as u8, (endian == Endian::Little) as u8, 0, 1]);
+        data.extend(word(2));
+        data.extend(word(1)); // Contents section
+        data.extend(word(0));
+        data.extend(word(0)); // Contents class name section
+        data.extend(word(5));
+        data.extend(b"hk_2014.2.0-r1\0\0");
+        data.extend(word(0));
+        data.extend([0xFF, 0xFF, 0, 0]);
+
+        let sections = data.len();
+        data.resize(sections + 0x80, 0);
+        let classes = data.len();
+        data.extend(word(0x12345678));
+        data.push(9);
+        data.extend(b"StaticCompoundInfo\0");
+        data.resize(data.len().next_multiple_of(0x10), 0);
+        let classes_end = data.len() - classes;
+
+        // Offset, then the actor, shape, and tile arrays, then the actors
+        let contents = data.len();
+        let ptr = pointer_size;
+        let array_size = ptr + 8;
+        let actors_start = ptr + array_size * 3;
+        data.resize(contents + actors_start, 0);
+        let count_pos = contents + ptr + ptr;
+        data[count_pos..count_pos + 4].copy_from_slice(&word(actors.len() as u32));
+        for (i, hash_id) in actors.iter().enumerate() {
+            data.extend(word(*hash_id));
+            data.extend(word(0));
+            data.extend(word(i as u32));
+            data.extend(word(i as u32));
+        }
+        let fixups = data.len() - contents;
+        data.extend(word(ptr as u32));
+        data.extend(word(actors_start as u32));
+        data.extend(word(u32::MAX));
+        data.extend(word(u32::MAX));
+        let end = data.len() - contents;
+
+        for (i, (name, start, fixups, end)) in [
+            ("__classnames__", classes, classes_end, classes_end),
+            ("__data__", contents, fixups, end),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let pos = sections + i * 0x40;
+            data[pos..pos + name.len()].copy_from_slice(name.as_bytes());
+            for (j, value) in [start, fixups, end, end, end, end, end]
+                .into_iter()
+                .enumerate()
+            {
+                let field = pos + 0x14 + j * 4;
+                data[field..field + 4].copy_from_slice(&word(value as u32));
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn wiiu_compound() {
+        let compound =
+            StaticCompound::from_binary(packfile(Endian::Big, 4, &[101, 202, 303])).unwrap();
+        assert_eq!(compound.header.endian, Endian::Big);
+        assert_eq!(compound.header.contents_version.as_str(), "hk_2014.2.0-r1");
+        assert_eq!(compound.header.sections[1].name.as_str(), "__data__");
+        assert_eq!(compound.actors[1].hash_id, 202);
+        assert!(compound.contains(303));
+        assert!(!compound.contains(404));
+    }
+
+    #[test]
+    fn switch_compound() {
+        let compound = StaticCompound::from_binary(packfile(Endian::Little, 8, &[7])).unwrap();
+        assert_eq!(compound.header.pointer_size, 8);
+        assert_eq!(compound.actors.len(), 1);
+        assert!(compound.contains(7));
+    }
+
+    #[test]
+    #[ignore = "needs Dungeon044.shksc from Pack/Dungeon044.pack in a game dump"]
+    fn stock_shrine() {
+        let compound = StaticCompound::from_binary(
+            roead::yaz0::decompress(
+                std::fs::read("test/Physics/StaticCompound/CDungeon/Dungeon044.shksc").unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let unit = crate::map::unit::MapUnit::try_from(
+            &roead::byml::Byml::from_binary(
+                roead::yaz0::decompress(
+                    std::fs::read("test/Map/CDungeon/Dungeon044/Dungeon044_Static.smubin")
+                        .unwrap(),
+                )
+                .unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(!compound.actors.is_empty());
+        // The shrine's baked collision belongs to objects in its map units
+        assert!(unit.hash_ids().any(|id| compound.contains(id)));
+    }
+
+    #[test]
+    fn truncated() {
+        let data = packfile(Endian::Big, 4, &[101]);
+        let err = StaticCompound::from_binary(&data[..0x50]).unwrap_err();
+        assert!(err.to_string().contains("Havok file ends before"));
+        assert!(HavokHeader::from_binary(b"not havok").is_err());
+    }
+
+    #[test]
+    fn identify() {
+        let path = std::path::Path::new("Physics/StaticCompound/CDungeon/Dungeon000.shksc");
+        assert!(super::StaticCompound::path_matches(path));
+    }
+}
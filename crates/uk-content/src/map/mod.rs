@@ -1,4 +1,5 @@
 pub mod cdungeon;
+pub mod compound;
 pub mod lazy;
 pub mod mainfield;
 pub mod static_;
@@ -10,6 +10,7 @@ use crate::{
     Result, UKError,
 };
 
+use super::cdungeon::{DungeonEntries, EntryPos};
 use super::mainfield::{
     ScaleTranslate,
     collab_anchor::CollabAnchor,
@@ -25,19 +26,12 @@ use super::mainfield::{
     target_pos_marker::TargetPosMarker,
 };
 
-#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
-
-pub struct EntryPos {
-    pub rotate: Byml,
-    pub translate: Byml,
-    pub player_state: Option<String>,
-}
-
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 
 pub struct Static {
     pub general:   BTreeMap<String, DeleteVec<Byml>>,
-    pub start_pos: DeleteMap<String, DeleteMap<String, EntryPos>>,
+    /// Shrine entrances and warp destinations, by shrine map
+    pub start_pos: DeleteMap<String, DungeonEntries>,
 }
 
 impl TryFrom<&Byml> for Static {
@@ -51,57 +45,25 @@ impl TryFrom<&Byml> for Static {
                 .ok_or(UKError::MissingBymlKey("CDungeon static missing StartPos"))?
                 .as_array()?
                 .iter()
+                .enumerate()
                 .try_fold(
                     DeleteMap::new(),
-                    |mut entry_map,
-                     entry|
-                     -> Result<DeleteMap<String, DeleteMap<String, EntryPos>>> {
-                        let entry = entry.as_map()?;
-                        let map = entry
+                    |mut entry_map, (index, entry)| -> Result<DeleteMap<String, DungeonEntries>> {
+                        let hash = entry.as_map()?;
+                        let map = hash
                             .get("Map")
                             .ok_or(UKError::MissingBymlKey(
                                 "CDungeon static entry missing Map name",
                             ))?
                             .as_string()?
                             .clone();
-                        let pos_name = match entry.get("PosName") {
+                        let pos_name = match hash.get("PosName") {
                             Some(pos_name) => pos_name.as_string()?.clone(),
                             _ => return Ok(entry_map),
                         };
-                        let rotate = entry
-                            .get("Rotate")
-                            .ok_or(UKError::MissingBymlKey(
-                                "CDungeon static entry missing Rotate",
-                            ))?
-                            .clone();
-                        let translate = entry
-                            .get("Translate")
-                            .ok_or(UKError::MissingBymlKey(
-                                "CDungeon static entry missing Translate",
-                            ))?
-                            .clone();
-                        let player_state = entry
-                            .get("PlayerState")
-                            .map(|state| -> Result<String> { Ok(state.as_string()?.clone()) })
-                            .transpose()?;
-                        if let Some(map_entries) = entry_map.get_mut(&map) {
-                            map_entries.insert(pos_name, EntryPos {
-                                rotate,
-                                translate,
-                                player_state,
-                            });
-                        } else {
-                            entry_map.insert(
-                                map,
-                                [(pos_name, EntryPos {
-                                    rotate,
-                                    translate,
-                                    player_state,
-                                })]
-                                .into_iter()
-                                .collect(),
-                            );
-                        };
+                        let pos = EntryPos::try_from(entry)
+                            .with_context(|| format!("Could not read StartPos {}", index))?;
+                        entry_map.get_or_insert_default(map).0.insert(pos_name, pos);
                         Ok(entry_map)
                     },
                 )?,
@@ -123,22 +85,17 @@ impl From<Static> for Byml {
             "StartPos".into(),
             val.start_pos
                 .into_iter()
-                .flat_map(|(map, entries): (String, DeleteMap<String, EntryPos>)| {
+                .flat_map(|(map, entries): (String, DungeonEntries)| {
                     entries
+                        .0
                         .into_iter()
                         .map(|(pos_name, pos)| {
-                            [
-                                ("Map", Byml::String(map.clone())),
-                                ("PosName", Byml::String(pos_name)),
-                                ("Rotate", pos.rotate),
-                                ("Translate", pos.translate),
-                            ]
-                            .into_iter()
-                            .chain(
-                                pos.player_state
-                                    .map(|state| ("PlayerState", Byml::String(state))),
-                            )
-                            .collect()
+                            if !pos.is_complete() {
+                                log::warn!(
+                                    "CDungeon StartPos {pos_name} in {map} is missing an axis"
+                                );
+                            }
+                            pos.into_byml(&map, &pos_name)
                         })
                         .collect::<Vec<Byml>>()
                 })
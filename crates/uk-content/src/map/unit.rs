@@ -1,7 +1,11 @@
 use roead::byml::Byml;
 use serde::{Deserialize, Serialize};
 
-use crate::{prelude::*, util::SortedDeleteMap, Result, UKError};
+use crate::{
+    prelude::*,
+    util::{HashMap, HashSet, SortedDeleteMap},
+    Result, UKError,
+};
 
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 
@@ -108,6 +112,177 @@ impl Mergeable for MapUnit {
     }
 }
 
+/// HashIds from outside a map unit which matter when merging a mod's diff
+/// into it. HashIds have to be unique across the Static and Dynamic units of
+/// a map section.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnitIds {
+    /// IDs the mod's static compound has collision for, which can't change
+    pub fixed:    HashSet<u32>,
+    /// IDs used by the other unit of the map section, which can't be given out
+    pub reserved: HashSet<u32>,
+    /// IDs earlier mods add to the other unit, which collide if the mod adds
+    /// them too
+    pub taken:    HashSet<u32>,
+}
+
+impl MapUnit {
+    /// Merges a mod's diff like [`Mergeable::merge`], but first gives new
+    /// HashIds to any objects or rails it adds which collide with different
+    /// ones an earlier mod added. `stock` is the unit before any mods.
+    pub fn merge_with_stock(&self, stock: &Self, diff: &Self, ids: &UnitIds) -> Self {
+        let mut used: HashSet<u32> = [self, stock, diff]
+            .into_iter()
+            .flat_map(|unit| unit.hash_ids())
+            .chain(ids.reserved.iter().copied())
+            .collect();
+        let objects = collisions(
+            &self.objects,
+            &stock.objects,
+            &diff.objects,
+            &ids.fixed,
+            &ids.taken,
+            &mut used,
+        );
+        // Static compounds only hold objects
+        let rails = collisions(
+            &self.rails,
+            &stock.rails,
+            &diff.rails,
+            &HashSet::default(),
+            &ids.taken,
+            &mut used,
+        );
+        if objects.is_empty() && rails.is_empty() {
+            return self.merge(diff);
+        }
+        let reassign = |map: &SortedDeleteMap<u32, Byml>, ids: &HashMap<u32, u32>, kind: &str| {
+            map.iter_full()
+                .map(|(id, (obj, del))| {
+                    let mut obj = obj.clone();
+                    relink(&mut obj, "LinksToObj", &objects);
+                    relink(&mut obj, "LinksToRail", &rails);
+                    let id = match ids.get(id) {
+                        Some(new_id) => {
+                            log::info!(
+                                "Map unit {kind} HashId {id} collides with one added by another \
+                                 mod, changing it to {new_id}"
+                            );
+                            if let Ok(obj) = obj.as_mut_map() {
+                                obj.insert("HashId".into(), Byml::U32(*new_id));
+                            }
+                            *new_id
+                        }
+                        None => *id,
+                    };
+                    (id, obj, *del)
+                })
+                .collect()
+        };
+        let diff = Self {
+            objects: reassign(&diff.objects, &objects, "object"),
+            rails: reassign(&diff.rails, &rails, "rail"),
+            ..diff.clone()
+        };
+        self.merge(&diff)
+    }
+
+    /// The HashIds of all the unit's objects and rails
+    pub fn hash_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.objects.keys().chain(self.rails.keys()).copied()
+    }
+
+    /// The path of the other unit in the same map section, e.g. the Dynamic
+    /// unit for a Static one
+    pub fn sibling_path(path: &str) -> Option<std::string::String> {
+        let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let name = if name.contains("_Static") {
+            name.replace("_Static", "_Dynamic")
+        } else if name.contains("_Dynamic") {
+            name.replace("_Dynamic", "_Static")
+        } else {
+            return None;
+        };
+        Some(if dir.is_empty() {
+            name
+        } else {
+            format!("{dir}/{name}")
+        })
+    }
+}
+
+/// Finds objects a diff adds, not in the stock unit, with the same HashId as
+/// a different object already added, or one an earlier mod added to the other
+/// unit, and picks an unused ID for each. They count as the same object if the
+/// actor and position match. Objects in the mod's static compound keep their
+/// IDs, since the compound can't be rewritten.
+fn collisions(
+    current: &SortedDeleteMap<u32, Byml>,
+    stock: &SortedDeleteMap<u32, Byml>,
+    diff: &SortedDeleteMap<u32, Byml>,
+    fixed: &HashSet<u32>,
+    taken: &HashSet<u32>,
+    used: &mut HashSet<u32>,
+) -> HashMap<u32, u32> {
+    let same_object = |a: &Byml, b: &Byml| {
+        match (a.as_map(), b.as_map()) {
+            (Ok(a), Ok(b)) => {
+                ["UnitConfigName", "Translate"]
+                    .into_iter()
+                    .all(|key| a.get(key) == b.get(key))
+            }
+            _ => a == b,
+        }
+    };
+    diff.iter()
+        .filter(|(id, obj)| {
+            let collides = !stock.contains_key(*id)
+                && (taken.contains(*id)
+                    || current.get(*id).is_some_and(|cur| !same_object(cur, obj)));
+            if collides && fixed.contains(*id) {
+                log::warn!(
+                    "Map unit HashId {id} collides with one added by another mod, but has \
+                     collision in the mod's static compound, so it can't be changed"
+                );
+                return false;
+            }
+            collides
+        })
+        .map(|(id, _)| {
+            let mut new_id = *id;
+            loop {
+                new_id = new_id.wrapping_add(1);
+                if used.insert(new_id) {
+                    break;
+                }
+            }
+            (*id, new_id)
+        })
+        .collect()
+}
+
+/// Updates the `DestUnitHashId` of each link in a list to any new IDs.
+fn relink(obj: &mut Byml, key: &str, ids: &HashMap<u32, u32>) {
+    if ids.is_empty() {
+        return;
+    }
+    let Some(Byml::Array(links)) = obj.as_mut_map().ok().and_then(|obj| obj.get_mut(key)) else {
+        return;
+    };
+    for dest in links
+        .iter_mut()
+        .filter_map(|link| link.as_mut_map().ok()?.get_mut("DestUnitHashId"))
+    {
+        if let Some(new_id) = dest.as_int::<u32>().ok().and_then(|id| ids.get(&id)) {
+            log::info!("Relinking map unit {key} from {dest:?} to {new_id}");
+            *dest = match dest {
+                Byml::I32(_) => Byml::I32(*new_id as i32),
+                _ => Byml::U32(*new_id),
+            };
+        }
+    }
+}
+
 impl Resource for MapUnit {
     fn from_binary(data: impl AsRef<[u8]>) -> crate::Result<Self> {
         (&Byml::from_binary(data.as_ref())?).try_into()
@@ -238,6 +413,91 @@ mod tests {
         assert_eq!(merged, munt2);
     }
 
+    #[test]
+    fn merge_colliding_ids() {
+        let munt = super::MapUnit::try_from(&load_mainfield_munt()).unwrap();
+        let obj = |name: &str, id: u32, links: &[u32]| {
+            let mut obj = munt.objects.values().next().unwrap().clone();
+            let map = obj.as_mut_map().unwrap();
+            map.insert("UnitConfigName".into(), Byml::String(name.into()));
+            map.insert("HashId".into(), Byml::U32(id));
+            map.insert(
+                "LinksToObj".into(),
+                links
+                    .iter()
+                    .map(|id| {
+                        [("DestUnitHashId", Byml::U32(*id))]
+                            .into_iter()
+                            .collect::<Byml>()
+                    })
+                    .collect(),
+            );
+            obj
+        };
+        let id = 0xDEADBEEF;
+        let mut mod_a = munt.clone();
+        mod_a.objects.insert(id, obj("Enemy_Lynel_Dark", id, &[]));
+        let mut mod_b = munt.clone();
+        mod_b.objects.insert(id, obj("TBox_Field_Iron", id, &[]));
+        mod_b
+            .objects
+            .insert(id + 5, obj("Obj_Switch", id + 5, &[id]));
+        let no_ids = super::UnitIds::default();
+        let merged_a = munt.merge_with_stock(&munt, &munt.diff(&mod_a), &no_ids);
+        // IDs in the other unit of the section aren't given out
+        let ids = super::UnitIds {
+            reserved: [id + 1].into_iter().collect(),
+            ..Default::default()
+        };
+        let merged = merged_a.merge_with_stock(&munt, &munt.diff(&mod_b), &ids);
+        assert_eq!(merged.objects.len(), munt.objects.len() + 3);
+        assert_eq!(merged.objects.get(id), mod_a.objects.get(id));
+        let new_id = id + 2;
+        let moved = merged.objects.get(new_id).unwrap().as_map().unwrap();
+        assert_eq!(
+            moved["UnitConfigName"],
+            Byml::String("TBox_Field_Iron".into())
+        );
+        assert_eq!(moved["HashId"], Byml::U32(new_id));
+        let switch = merged.objects.get(id + 5).unwrap().as_map().unwrap();
+        assert_eq!(
+            switch["LinksToObj"].as_array().unwrap()[0]
+                .as_map()
+                .unwrap()["DestUnitHashId"],
+            Byml::U32(new_id)
+        );
+        // The same object added by two mods is not a collision
+        let merged = merged.merge_with_stock(&munt, &munt.diff(&mod_a), &no_ids);
+        assert_eq!(merged.objects.len(), munt.objects.len() + 3);
+        // Objects in the mod's static compound keep their IDs
+        let ids = super::UnitIds {
+            fixed: [id].into_iter().collect(),
+            ..Default::default()
+        };
+        let merged = merged_a.merge_with_stock(&munt, &munt.diff(&mod_b), &ids);
+        assert_eq!(merged.objects.get(id), mod_b.objects.get(id));
+        // Adding an ID an earlier mod added to the other unit is a collision
+        let ids = super::UnitIds {
+            taken: [id].into_iter().collect(),
+            ..Default::default()
+        };
+        let merged = munt.merge_with_stock(&munt, &munt.diff(&mod_a), &ids);
+        assert!(merged.objects.get(id).is_none());
+        assert_eq!(merged.objects.len(), munt.objects.len() + 1);
+    }
+
+    #[test]
+    fn sibling_path() {
+        assert_eq!(
+            super::MapUnit::sibling_path("Map/MainField/F-3/F-3_Dynamic.smubin").as_deref(),
+            Some("Map/MainField/F-3/F-3_Static.smubin")
+        );
+        assert_eq!(
+            super::MapUnit::sibling_path("Map/MainField/Foo.smubin"),
+            None
+        );
+    }
+
     #[test]
     fn identify() {
         let path = std::path::Path::new("content/Map/MainField/F-3/F-3_Dynamic.smubin");
@@ -4,6 +4,7 @@ use anyhow::{Context, Result};
 use roead::{aamp::ParameterIO, byml::Byml, sarc::Sarc};
 use serde::{Deserialize, Serialize};
 
+use crate::conflict::ConflictTracker;
 pub use crate::{
     actor::{
         info::ActorInfo,
@@ -16,20 +17,21 @@ pub use crate::{
             rgconfig::RagdollConfig, rgconfiglist::RagdollConfigList, shop::ShopData, umii::UMii,
         },
         residents::ResidentActors,
-        // Actor,
     },
     chemical::chmres::ChemicalRes,
     cooking::data::CookData,
     data::{gamedata::GameDataPack, savedata::SaveDataPack, shop::ShopGameDataInfo},
     demo::Demo,
     eco::{areadata::AreaData, level::LevelSensor, status::StatusEffectList},
+    effect::esetlist::EffectSetList,
     event::{info::EventInfo, residents::ResidentEvents},
     font::FontArchive,
     layout::LayoutArchive,
-    map::{lazy::LazyTraverseList, mainfield::location::Location, static_::{MainStatic, Static}, unit::MapUnit},
+    map::{lazy::LazyTraverseList, mainfield::location::Location, static_::{MainStatic, Static}, unit::{MapUnit, UnitIds}},
     message::MessagePack,
     quest::product::QuestProduct,
     sound::barslist::BarslistInfo,
+    terrain::tscb::TerrainScene,
     tips::Tips,
     util::SortedDeleteMap,
     worldmgr::info::WorldInfo,
@@ -39,7 +41,6 @@ use crate::{prelude::*, util::SortedDeleteSet};
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 
 pub enum MergeableResource {
-    // Actor(Box<Actor>),
     ActorInfo(Box<ActorInfo>),
     ActorLink(Box<ActorLink>),
     AIProgram(Box<AIProgram>),
@@ -59,6 +60,7 @@ pub enum MergeableResource {
     DamageParam(Box<DamageParam>),
     Demo(Box<Demo>),
     DropTable(Box<DropTable>),
+    EffectSetList(Box<EffectSetList>),
     EventInfo(Box<EventInfo>),
     FontArchive(Box<FontArchive>),
     GameDataPack(Box<GameDataPack>),
@@ -86,6 +88,7 @@ pub enum MergeableResource {
     ShopGameDataInfo(Box<ShopGameDataInfo>),
     Static(Box<Static>),
     StatusEffectList(Box<StatusEffectList>),
+    TerrainScene(Box<TerrainScene>),
     Tips(Box<Tips>),
     UMii(Box<UMii>),
     WorldInfo(Box<WorldInfo>),
@@ -97,7 +100,6 @@ pub enum MergeableResource {
 impl std::fmt::Display for MergeableResource {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            // Self::Actor(_) => "Actor",
             Self::ActorInfo(_) => "ActorInfo",
             Self::ActorLink(_) => "ActorLink",
             Self::AIProgram(_) => "AIProgram",
@@ -117,6 +119,7 @@ impl std::fmt::Display for MergeableResource {
             Self::DamageParam(_) => "DamageParam",
             Self::Demo(_) => "Demo",
             Self::DropTable(_) => "DropTable",
+            Self::EffectSetList(_) => "EffectSetList",
             Self::EventInfo(_) => "EventInfo",
             Self::FontArchive(_) => "FontArchive",
             Self::GameDataPack(_) => "GameDataPack",
@@ -144,6 +147,7 @@ impl std::fmt::Display for MergeableResource {
             Self::ShopGameDataInfo(_) => "ShopGameDataInfo",
             Self::Static(_) => "Static",
             Self::StatusEffectList(_) => "StatusEffectList",
+            Self::TerrainScene(_) => "TerrainScene",
             Self::Tips(_) => "Tips",
             Self::UMii(_) => "UMii",
             Self::WorldInfo(_) => "WorldInfo",
@@ -193,7 +197,6 @@ macro_rules! impl_from_res {
     };
 }
 
-// impl_from_res!(Actor);
 impl_from_res!(ActorInfo);
 impl_from_res!(ActorLink);
 impl_from_res!(AIProgram);
@@ -213,6 +216,7 @@ impl_from_res!(CookData);
 impl_from_res!(DamageParam);
 impl_from_res!(Demo);
 impl_from_res!(DropTable);
+impl_from_res!(EffectSetList);
 impl_from_res!(EventInfo);
 impl_from_res!(FontArchive);
 impl_from_res!(GameDataPack);
@@ -240,6 +244,7 @@ impl_from_res!(ShopData);
 impl_from_res!(ShopGameDataInfo);
 impl_from_res!(Static);
 impl_from_res!(StatusEffectList);
+impl_from_res!(TerrainScene);
 impl_from_res!(Tips);
 impl_from_res!(UMii);
 impl_from_res!(WorldInfo);
@@ -247,7 +252,6 @@ impl_from_res!(WorldInfo);
 impl Mergeable for MergeableResource {
     fn diff(&self, other: &Self) -> Self {
         match (self, other) {
-            // (Self::Actor(a), Self::Actor(b)) => Self::Actor(Box::new(a.diff(b))),
             (Self::ActorInfo(a), Self::ActorInfo(b)) => Self::ActorInfo(Box::new(a.diff(b))),
             (Self::ActorLink(a), Self::ActorLink(b)) => Self::ActorLink(Box::new(a.diff(b))),
             (Self::AIProgram(a), Self::AIProgram(b)) => Self::AIProgram(Box::new(a.diff(b))),
@@ -273,6 +277,9 @@ impl Mergeable for MergeableResource {
             (Self::DamageParam(a), Self::DamageParam(b)) => Self::DamageParam(Box::new(a.diff(b))),
             (Self::Demo(a), Self::Demo(b)) => Self::Demo(Box::new(a.diff(b))),
             (Self::DropTable(a), Self::DropTable(b)) => Self::DropTable(Box::new(a.diff(b))),
+            (Self::EffectSetList(a), Self::EffectSetList(b)) => {
+                Self::EffectSetList(Box::new(a.diff(b)))
+            }
             (Self::EventInfo(a), Self::EventInfo(b)) => Self::EventInfo(Box::new(a.diff(b))),
             (Self::FontArchive(a), Self::FontArchive(b)) => Self::FontArchive(Box::new(a.diff(b))),
             (Self::GameDataPack(a), Self::GameDataPack(b)) => {
@@ -328,6 +335,9 @@ impl Mergeable for MergeableResource {
             (Self::StatusEffectList(a), Self::StatusEffectList(b)) => {
                 Self::StatusEffectList(Box::new(a.diff(b)))
             }
+            (Self::TerrainScene(a), Self::TerrainScene(b)) => {
+                Self::TerrainScene(Box::new(a.diff(b)))
+            }
             (Self::Tips(a), Self::Tips(b)) => Self::Tips(Box::new(a.diff(b))),
             (Self::UMii(a), Self::UMii(b)) => Self::UMii(Box::new(a.diff(b))),
             (Self::WorldInfo(a), Self::WorldInfo(b)) => Self::WorldInfo(Box::new(a.diff(b))),
@@ -346,7 +356,6 @@ impl Mergeable for MergeableResource {
 
     fn merge(&self, diff: &Self) -> Self {
         match (self, diff) {
-            // (Self::Actor(a), Self::Actor(b)) => Self::Actor(Box::new(a.merge(b))),
             (Self::ActorInfo(a), Self::ActorInfo(b)) => Self::ActorInfo(Box::new(a.merge(b))),
             (Self::ActorLink(a), Self::ActorLink(b)) => Self::ActorLink(Box::new(a.merge(b))),
             (Self::AIProgram(a), Self::AIProgram(b)) => Self::AIProgram(Box::new(a.merge(b))),
@@ -372,6 +381,9 @@ impl Mergeable for MergeableResource {
             (Self::DamageParam(a), Self::DamageParam(b)) => Self::DamageParam(Box::new(a.merge(b))),
             (Self::Demo(a), Self::Demo(b)) => Self::Demo(Box::new(a.merge(b))),
             (Self::DropTable(a), Self::DropTable(b)) => Self::DropTable(Box::new(a.merge(b))),
+            (Self::EffectSetList(a), Self::EffectSetList(b)) => {
+                Self::EffectSetList(Box::new(a.merge(b)))
+            }
             (Self::EventInfo(a), Self::EventInfo(b)) => Self::EventInfo(Box::new(a.merge(b))),
             (Self::FontArchive(a), Self::FontArchive(b)) => Self::FontArchive(Box::new(a.merge(b))),
             (Self::GameDataPack(a), Self::GameDataPack(b)) => {
@@ -427,6 +439,9 @@ impl Mergeable for MergeableResource {
             (Self::StatusEffectList(a), Self::StatusEffectList(b)) => {
                 Self::StatusEffectList(Box::new(a.merge(b)))
             }
+            (Self::TerrainScene(a), Self::TerrainScene(b)) => {
+                Self::TerrainScene(Box::new(a.merge(b)))
+            }
             (Self::Tips(a), Self::Tips(b)) => Self::Tips(Box::new(a.merge(b))),
             (Self::UMii(a), Self::UMii(b)) => Self::UMii(Box::new(a.merge(b))),
             (Self::WorldInfo(a), Self::WorldInfo(b)) => Self::WorldInfo(Box::new(a.merge(b))),
@@ -445,6 +460,51 @@ impl Mergeable for MergeableResource {
 }
 
 impl MergeableResource {
+    /// Merges a diff like [`Mergeable::merge`], given the resource before
+    /// any mods. Map units use this to keep new objects from different mods
+    /// from replacing each other.
+    pub fn merge_with_stock(&self, stock: &Self, diff: &Self, ids: &UnitIds) -> Self {
+        match (self, stock, diff) {
+            (Self::MapUnit(a), Self::MapUnit(stock), Self::MapUnit(b)) => {
+                Self::MapUnit(Box::new(a.merge_with_stock(stock, b, ids)))
+            }
+            _ => self.merge(diff),
+        }
+    }
+
+    /// Merges a diff like [`Self::merge_with_stock`], recording the fields
+    /// the mod sets so that any it overwrites can be reported.
+    pub fn merge_with_report(
+        &self,
+        stock: &Self,
+        diff: &Self,
+        ids: &UnitIds,
+        mod_name: &str,
+        tracker: &mut ConflictTracker,
+    ) -> Self {
+        tracker.record(mod_name, diff);
+        self.merge_with_stock(stock, diff, ids)
+    }
+
+    /// Parses a resource whose parser may not cover every file, leaving any
+    /// it can't read to be replaced whole instead of failing.
+    fn parse_or_replace<R: Resource>(
+        name: &Path,
+        data: &[u8],
+        wrap: impl FnOnce(Box<R>) -> Self,
+    ) -> Option<Self> {
+        match R::from_binary(data) {
+            Ok(res) => Some(wrap(Box::new(res))),
+            Err(e) => {
+                log::warn!(
+                    "Could not parse {}, so it can't be merged: {e}",
+                    name.display()
+                );
+                None
+            }
+        }
+    }
+
     pub fn from_binary(name: &Path, data: &[u8]) -> Result<Option<MergeableResource>> {
         let result: Result<Option<MergeableResource>> = if ActorInfo::path_matches(name) {
             Ok(Some(Self::ActorInfo(Box::new(ActorInfo::from_binary(
@@ -510,6 +570,8 @@ impl MergeableResource {
             Ok(Some(Self::DropTable(Box::new(DropTable::from_binary(
                 data,
             )?))))
+        } else if EffectSetList::path_matches(name) {
+            Ok(Self::parse_or_replace(name, data, Self::EffectSetList))
         } else if EventInfo::path_matches(name) {
             Ok(Some(Self::EventInfo(Box::new(EventInfo::from_binary(
                 data,
@@ -602,6 +664,8 @@ impl MergeableResource {
             Ok(Some(Self::StatusEffectList(Box::new(
                 StatusEffectList::from_binary(data)?,
             ))))
+        } else if TerrainScene::path_matches(name) {
+            Ok(Self::parse_or_replace(name, data, Self::TerrainScene))
         } else if Tips::path_matches(name) {
             Ok(Some(Self::Tips(Box::new(Tips::from_binary(data)?))))
         } else if UMii::path_matches(name) {
@@ -633,7 +697,6 @@ impl MergeableResource {
 
     pub fn into_binary(self, endian: Endian) -> Vec<u8> {
         match self {
-            // Self::Actor(v) => v.into_binary(endian),
             Self::ActorInfo(v) => v.into_binary(endian),
             Self::ActorLink(v) => v.into_binary(endian),
             Self::AIProgram(v) => v.into_binary(endian),
@@ -653,6 +716,7 @@ impl MergeableResource {
             Self::DamageParam(v) => v.into_binary(endian),
             Self::Demo(v) => v.into_binary(endian),
             Self::DropTable(v) => v.into_binary(endian),
+            Self::EffectSetList(v) => v.into_binary(endian),
             Self::EventInfo(v) => v.into_binary(endian),
             Self::FontArchive(v) => v.into_binary(endian),
             Self::GameDataPack(v) => v.into_binary(endian),
@@ -680,6 +744,7 @@ impl MergeableResource {
             Self::ShopGameDataInfo(v) => v.into_binary(endian),
             Self::Static(v) => v.into_binary(endian),
             Self::StatusEffectList(v) => v.into_binary(endian),
+            Self::TerrainScene(v) => v.into_binary(endian),
             Self::Tips(v) => v.into_binary(endian),
             Self::UMii(v) => v.into_binary(endian),
             Self::WorldInfo(v) => v.into_binary(endian),
@@ -774,27 +839,88 @@ pub const EXCLUDE_EXTS: &[&str] = &["genvb", "sarc", "arc"];
 pub const EXCLUDE_NAMES: &[&str] = &["tera_resource.Nin_NX_NVN", "tera_resource.Cafe_Cafe_GX2"];
 
 pub fn is_mergeable_sarc(name: impl AsRef<Path>, data: impl AsRef<[u8]>) -> bool {
-    fn inner(name: &Path, data: &[u8]) -> bool {
-        static MAGIC: &[u8; 4] = b"SARC";
-        data.len() >= 0x40
-            && (&data[..4] == MAGIC || &data[0x11..0x15] == MAGIC)
-            && name
-                .extension()
-                .and_then(|e| e.to_str())
-                .map(|e| !EXCLUDE_EXTS.contains(&e.strip_prefix('s').unwrap_or(e)))
-                .unwrap_or(false)
-            && name
-                .file_stem()
-                .and_then(|n| n.to_str())
-                .map(|n| !EXCLUDE_NAMES.iter().any(|xn| n.starts_with(xn)))
-                .unwrap_or(false)
+    ArchiveExts::default().is_mergeable_sarc(name, data)
+}
+
+/// Archive extensions to treat differently from [`EXCLUDE_EXTS`]. Each
+/// extension also matches its Yaz0 compressed form, e.g. `sarc` matches
+/// `.ssarc`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveExts {
+    /// Archives to merge file by file, even if excluded by default
+    pub merge:   Vec<String>,
+    /// Archives to always replace whole
+    pub exclude: Vec<String>,
+}
+
+impl ArchiveExts {
+    pub fn is_empty(&self) -> bool {
+        self.merge.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Adds the extensions from another set which aren't already listed
+    pub fn extend(&mut self, other: &Self) {
+        for (list, other) in [
+            (&mut self.merge, &other.merge),
+            (&mut self.exclude, &other.exclude),
+        ] {
+            for ext in other {
+                if !list.contains(ext) {
+                    list.push(ext.clone());
+                }
+            }
+        }
+    }
+
+    fn matches(list: &[String], ext: &str) -> bool {
+        list.iter().any(|e| {
+            let e = e.trim().trim_start_matches('.');
+            !e.is_empty() && (ext == e || ext.strip_prefix('s') == Some(e))
+        })
+    }
+
+    pub fn is_mergeable_sarc(&self, name: impl AsRef<Path>, data: impl AsRef<[u8]>) -> bool {
+        fn inner(exts: &ArchiveExts, name: &Path, data: &[u8]) -> bool {
+            static MAGIC: &[u8; 4] = b"SARC";
+            data.len() >= 0x40
+                && (&data[..4] == MAGIC || &data[0x11..0x15] == MAGIC)
+                && name
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| {
+                        if ArchiveExts::matches(&exts.exclude, e) {
+                            false
+                        } else if ArchiveExts::matches(&exts.merge, e) {
+                            true
+                        } else {
+                            !EXCLUDE_EXTS.contains(&e.strip_prefix('s').unwrap_or(e))
+                        }
+                    })
+                    .unwrap_or(false)
+                && name
+                    .file_stem()
+                    .and_then(|n| n.to_str())
+                    .map(|n| !EXCLUDE_NAMES.iter().any(|xn| n.starts_with(xn)))
+                    .unwrap_or(false)
+        }
+        inner(self, name.as_ref(), data.as_ref())
     }
-    inner(name.as_ref(), data.as_ref())
 }
 
 impl ResourceData {
     pub fn from_binary<'a>(name: impl AsRef<Path>, data: impl Into<Cow<'a, [u8]>>) -> Result<Self> {
-        fn inner(name: &Path, data: Cow<'_, [u8]>) -> Result<ResourceData> {
+        Self::from_binary_with_archives(name, data, &ArchiveExts::default())
+    }
+
+    /// Parses a resource like [`Self::from_binary`], deciding which archives
+    /// to merge from the given extensions.
+    pub fn from_binary_with_archives<'a>(
+        name: impl AsRef<Path>,
+        data: impl Into<Cow<'a, [u8]>>,
+        archives: &ArchiveExts,
+    ) -> Result<Self> {
+        fn inner(name: &Path, data: Cow<'_, [u8]>, archives: &ArchiveExts) -> Result<ResourceData> {
             let stem = name
                 .file_stem()
                 .unwrap_or_default()
@@ -807,13 +933,13 @@ impl ResourceData {
                 .with_context(|| format!("Failed to parse resource {}", name.display()))?
             {
                 Ok(ResourceData::Mergeable(mergeable))
-            } else if is_mergeable_sarc(name, &data) {
+            } else if archives.is_mergeable_sarc(name, &data) {
                 Ok(ResourceData::Sarc(SarcMap::from_binary(data)?))
             } else {
                 Ok(ResourceData::Binary(data.to_vec()))
             }
         }
-        inner(name.as_ref(), data.into())
+        inner(name.as_ref(), data.into(), archives)
     }
 
     #[inline]
@@ -864,3 +990,40 @@ impl ResourceData {
         }
     }
 }
+
+#[allow(clippy::unwrap_used)]
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod tests {
+    use roead::sarc::SarcWriter;
+
+    use super::*;
+
+    #[test]
+    fn archive_exts() {
+        let data = SarcWriter::new(Endian::Big.into())
+            .with_file("Test/Data.bxml", vec![0; 0x40])
+            .to_binary();
+        assert!(is_mergeable_sarc("Pack/Test.pack", &data));
+        assert!(!is_mergeable_sarc("Test/Custom.sarc", &data));
+        let archives = ArchiveExts {
+            merge:   vec!["sarc".into()],
+            exclude: vec![".pack".into()],
+        };
+        assert!(archives.is_mergeable_sarc("Test/Custom.sarc", &data));
+        assert!(archives.is_mergeable_sarc("Test/Custom.ssarc", &data));
+        assert!(!archives.is_mergeable_sarc("Pack/Test.pack", &data));
+        assert!(!archives.is_mergeable_sarc("Pack/Test.sarc", b"not an archive"));
+    }
+
+    #[test]
+    fn unreadable_replaced_whole() {
+        for (name, data) in [
+            ("Terrain/A/MainField.tscb", b"TSCB\x0A\x00".as_slice()),
+            ("Effect/Lynel.sesetlist", b"SESL\xFE\xFF\x00\x01".as_slice()),
+        ] {
+            let res = ResourceData::from_binary(name, data).unwrap();
+            assert_eq!(res.as_binary(), Some(data));
+        }
+    }
+}
@@ -0,0 +1,11 @@
+pub mod tscb;
+
+/// Whether a file is a terrain tile archive (`.sstera`). These are merged tile
+/// by tile, so untouched tiles are copied from the stock archive.
+pub fn is_tile_archive(path: impl AsRef<std::path::Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(|x| x.to_str())
+        .map(|x| matches!(x, "stera" | "sstera"))
+        .unwrap_or(false)
+}
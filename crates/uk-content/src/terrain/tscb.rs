@@ -0,0 +1,411 @@
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+
+use crate::{
+    prelude::*,
+    util::{BinReader, DeleteMap},
+    Result, UKError,
+};
+
+const HEADER_SIZE: usize = 0x30;
+const MATERIAL_SIZE: usize = 0x14;
+const AREA_SIZE: usize = 0x2C;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TscbHeader {
+    pub version:     u32,
+    pub unknown:     u32,
+    pub world_scale: f32,
+    pub max_height:  f32,
+    pub tile_size:   f32,
+    pub unknown2:    u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MaterialInfo {
+    pub u_scale: f32,
+    pub v_scale: f32,
+    pub unknown: [f32; 2],
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AreaInfo {
+    pub x:                f32,
+    pub z:                f32,
+    pub size:             f32,
+    pub min_height:       f32,
+    pub max_height:       f32,
+    pub min_water_height: f32,
+    pub max_water_height: f32,
+    pub unknown:          [u32; 3],
+    /// Words after the fixed fields, including any extra info, kept as-is
+    pub extra:            Vec<u32>,
+}
+
+/// The terrain scene table (`MainField.tscb`), which lists the materials and
+/// every area tile of the terrain. Areas are keyed by tile file name, so mods
+/// editing different areas merge cleanly.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TerrainScene {
+    pub header:    TscbHeader,
+    /// Materials by index
+    pub materials: DeleteMap<u32, MaterialInfo>,
+    /// Areas by tile file name, in table order. The stock table is sorted by
+    /// name.
+    pub areas:     DeleteMap<String, AreaInfo>,
+}
+
+struct Writer {
+    data:   Vec<u8>,
+    endian: Endian,
+}
+
+impl Writer {
+    fn bytes(&self, value: u32) -> [u8; 4] {
+        match self.endian {
+            Endian::Big => value.to_be_bytes(),
+            Endian::Little => value.to_le_bytes(),
+        }
+    }
+
+    fn u32(&mut self, value: u32) {
+        let bytes = self.bytes(value);
+        self.data.extend(bytes);
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.u32(value.to_bits())
+    }
+
+    /// Points the offset at `pos` to the current end of the data
+    fn set_offset(&mut self, pos: usize) {
+        let bytes = self.bytes((self.data.len() - pos) as u32);
+        self.data[pos..pos + 4].copy_from_slice(&bytes);
+    }
+}
+
+impl Mergeable for TerrainScene {
+    fn diff(&self, other: &Self) -> Self {
+        Self {
+            header:    other.header.clone(),
+            materials: self.materials.diff(&other.materials),
+            areas:     self.areas.diff(&other.areas),
+        }
+    }
+
+    fn merge(&self, diff: &Self) -> Self {
+        Self {
+            header:    diff.header.clone(),
+            materials: self.materials.merge(&diff.materials),
+            areas:     place_added_areas(&self.areas, self.areas.merge(&diff.areas)),
+        }
+    }
+}
+
+/// Moves areas added by a merge from the end of the table to their place by
+/// name, leaving the order of existing areas alone.
+fn place_added_areas(
+    base: &DeleteMap<String, AreaInfo>,
+    merged: DeleteMap<String, AreaInfo>,
+) -> DeleteMap<String, AreaInfo> {
+    let (mut areas, mut added): (Vec<_>, Vec<_>) = merged
+        .iter()
+        .map(|(name, area)| (name.clone(), area.clone()))
+        .partition(|(name, _)| base.contains_key(name));
+    if added.is_empty() {
+        return merged;
+    }
+    added.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (name, area) in added {
+        let pos = areas
+            .iter()
+            .position(|(other, _)| *other > name)
+            .unwrap_or(areas.len());
+        areas.insert(pos, (name, area));
+    }
+    areas.into_iter().collect()
+}
+
+impl Resource for TerrainScene {
+    fn from_binary(data: impl AsRef<[u8]>) -> Result<Self> {
+        let data = data.as_ref();
+        if data.get(..4) != Some(b"TSCB") {
+            return Err(UKError::Other("Not a TSCB file"));
+        }
+        let endian = if data.get(4) == Some(&0x0A) {
+            Endian::Big
+        } else {
+            Endian::Little
+        };
+        let reader = BinReader::new(data, endian, "TSCB file");
+        let header = TscbHeader {
+            version:     reader.u32(0x04)?,
+            unknown:     reader.u32(0x08)?,
+            world_scale: reader.f32(0x10)?,
+            max_height:  reader.f32(0x14)?,
+            tile_size:   reader.f32(0x28)?,
+            unknown2:    reader.u32(0x2C)?,
+        };
+        let material_count = reader.u32(0x18)? as usize;
+        let area_count = reader.u32(0x1C)? as usize;
+
+        // The material section starts with its size, then an offset table
+        let mut materials = DeleteMap::with_capacity(material_count);
+        let mut area_table = HEADER_SIZE + 4 + material_count * 4;
+        for i in 0..material_count {
+            let pos = reader.offset(HEADER_SIZE + 4 + i * 4)?;
+            materials.insert(reader.u32(pos)?, MaterialInfo {
+                u_scale: reader.f32(pos + 0x4)?,
+                v_scale: reader.f32(pos + 0x8)?,
+                unknown: [reader.f32(pos + 0xC)?, reader.f32(pos + 0x10)?],
+            });
+            area_table = area_table.max(pos + MATERIAL_SIZE);
+        }
+
+        // Areas vary in size, so each ends where the next one (or the file
+        // names after them) begins
+        let positions = (0..area_count)
+            .map(|i| reader.offset(area_table + i * 4))
+            .collect::<Result<Vec<_>>>()?;
+        let names = positions
+            .iter()
+            .map(|pos| reader.offset(pos + 0x20))
+            .collect::<Result<Vec<_>>>()?;
+        let names_start = names.iter().copied().min().unwrap_or(data.len());
+        let mut bounds = positions.clone();
+        bounds.push(names_start);
+        bounds.sort_unstable();
+        let mut areas = DeleteMap::with_capacity(area_count);
+        for (&pos, &name) in positions.iter().zip(names.iter()) {
+            let end = bounds
+                .iter()
+                .copied()
+                .find(|b| *b > pos)
+                .unwrap_or(names_start);
+            if end < pos + AREA_SIZE {
+                return Err(UKError::OtherD(format!(
+                    "TSCB area at {pos:#x} is truncated"
+                )));
+            }
+            areas.insert(reader.string(name)?, AreaInfo {
+                x:                reader.f32(pos)?,
+                z:                reader.f32(pos + 0x4)?,
+                size:             reader.f32(pos + 0x8)?,
+                min_height:       reader.f32(pos + 0xC)?,
+                max_height:       reader.f32(pos + 0x10)?,
+                min_water_height: reader.f32(pos + 0x14)?,
+                max_water_height: reader.f32(pos + 0x18)?,
+                unknown:          [
+                    reader.u32(pos + 0x1C)?,
+                    reader.u32(pos + 0x24)?,
+                    reader.u32(pos + 0x28)?,
+                ],
+                extra:            (pos + AREA_SIZE..end)
+                    .step_by(4)
+                    .map(|p| reader.u32(p))
+                    .collect::<Result<_>>()?,
+            });
+        }
+        Ok(Self {
+            header,
+            materials,
+            areas,
+        })
+    }
+
+    fn into_binary(self, endian: Endian) -> Vec<u8> {
+        let mut writer = Writer {
+            data: Vec::with_capacity(HEADER_SIZE + self.areas.len() * 0x50),
+            endian,
+        };
+        writer.data.extend(b"TSCB");
+        writer.u32(self.header.version);
+        writer.u32(self.header.unknown);
+        writer.u32(0); // File name table offset, set below
+        writer.f32(self.header.world_scale);
+        writer.f32(self.header.max_height);
+        writer.u32(self.materials.len() as u32);
+        writer.u32(self.areas.len() as u32);
+        writer.u32(0);
+        writer.u32(0);
+        writer.f32(self.header.tile_size);
+        writer.u32(self.header.unknown2);
+
+        let material_table = HEADER_SIZE + 4;
+        writer.u32((self.materials.len() * (4 + MATERIAL_SIZE)) as u32);
+        writer
+            .data
+            .resize(material_table + self.materials.len() * 4, 0);
+        for (i, (index, material)) in self.materials.iter().enumerate() {
+            writer.set_offset(material_table + i * 4);
+            writer.u32(*index);
+            writer.f32(material.u_scale);
+            writer.f32(material.v_scale);
+            writer.f32(material.unknown[0]);
+            writer.f32(material.unknown[1]);
+        }
+
+        let area_table = writer.data.len();
+        writer.data.resize(area_table + self.areas.len() * 4, 0);
+        let mut name_offsets = Vec::with_capacity(self.areas.len());
+        for (i, (name, area)) in self.areas.iter().enumerate() {
+            writer.set_offset(area_table + i * 4);
+            writer.f32(area.x);
+            writer.f32(area.z);
+            writer.f32(area.size);
+            writer.f32(area.min_height);
+            writer.f32(area.max_height);
+            writer.f32(area.min_water_height);
+            writer.f32(area.max_water_height);
+            writer.u32(area.unknown[0]);
+            name_offsets.push((writer.data.len(), name));
+            writer.u32(0);
+            writer.u32(area.unknown[1]);
+            writer.u32(area.unknown[2]);
+            for word in &area.extra {
+                writer.u32(*word);
+            }
+        }
+
+        writer.set_offset(0x0C);
+        for (pos, name) in name_offsets {
+            writer.set_offset(pos);
+            writer.data.extend(name.as_bytes());
+            writer.data.push(0);
+            writer.data.resize(writer.data.len().next_multiple_of(4), 0);
+        }
+        writer.data
+    }
+
+    fn path_matches(path: impl AsRef<std::path::Path>) -> bool {
+        path.as_ref()
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(|x| x == "tscb")
+            .unwrap_or(false)
+    }
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(x: f32, height: f32) -> AreaInfo {
+        AreaInfo {
+            x,
+            z: -x,
+            size: 32.0,
+            min_height: 0.0,
+            max_height: height,
+            unknown: [1, 0, 0],
+            ..Default::default()
+        }
+    }
+
+    fn scene() -> TerrainScene {
+        TerrainScene {
+            header:    TscbHeader {
+                version:     0x0A000000,
+                unknown:     1,
+                world_scale: 500.0,
+                max_height:  800.0,
+                tile_size:   32.0,
+                unknown2:    8,
+            },
+            materials: [(0, MaterialInfo {
+                u_scale: 1.0,
+                v_scale: 1.0,
+                unknown: [1.0, 1.0],
+            })]
+            .into_iter()
+            .collect(),
+            areas:     [
+                ("5000000000".into(), area(0.0, 300.0)),
+                ("5100000000".into(), AreaInfo {
+                    extra: vec![1, 4, 0x14, 3, 0],
+                    ..area(16.0, 200.0)
+                }),
+                ("5200000000".into(), area(-16.0, 100.0)),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    #[test]
+    fn serde() {
+        let scene = scene();
+        for endian in [Endian::Big, Endian::Little] {
+            let data = scene.clone().into_binary(endian);
+            assert_eq!(TerrainScene::from_binary(data).unwrap(), scene);
+        }
+    }
+
+    #[test]
+    fn unreadable_table() {
+        let mut data = scene().into_binary(Endian::Big);
+        data.truncate(data.len() - 0x20);
+        assert!(TerrainScene::from_binary(&data).is_err());
+    }
+
+    #[test]
+    fn merge() {
+        let base = scene();
+        let mut mod_a = base.clone();
+        mod_a
+            .areas
+            .insert(String::from("5000000000"), area(0.0, 600.0));
+        let mut mod_b = base.clone();
+        mod_b
+            .areas
+            .insert(String::from("5300000000"), area(48.0, 50.0));
+        let merged = base.merge(&base.diff(&mod_a)).merge(&base.diff(&mod_b));
+        assert_eq!(
+            merged
+                .areas
+                .get(String::from("5000000000"))
+                .unwrap()
+                .max_height,
+            600.0
+        );
+        assert_eq!(merged.areas.len(), 4);
+        // New areas go in by name rather than at the end
+        let mut mod_c = base.clone();
+        mod_c
+            .areas
+            .insert(String::from("5150000000"), area(8.0, 50.0));
+        let merged = merged.merge(&base.diff(&mod_c));
+        assert_eq!(
+            merged.areas.keys().map(|k| k.as_str()).collect::<Vec<_>>(),
+            [
+                "5000000000",
+                "5100000000",
+                "5150000000",
+                "5200000000",
+                "5300000000"
+            ]
+        );
+    }
+
+    #[test]
+    #[ignore = "needs MainField.tscb from Wii U and Switch game dumps"]
+    fn stock_table() {
+        for (path, endian) in [
+            ("test/Terrain/MainField.tscb", Endian::Big),
+            ("test/Terrain/MainField.nx.tscb", Endian::Little),
+        ] {
+            let data = std::fs::read(path).unwrap();
+            let scene = TerrainScene::from_binary(&data).unwrap();
+            let names: Vec<_> = scene.areas.keys().collect();
+            assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+            assert_eq!(scene.into_binary(endian), data);
+        }
+    }
+
+    #[test]
+    fn identify() {
+        let path = std::path::Path::new("content/Terrain/A/MainField.tscb");
+        assert!(super::TerrainScene::path_matches(path));
+    }
+}
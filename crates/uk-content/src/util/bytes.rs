@@ -0,0 +1,104 @@
+use smartstring::alias::String;
+
+use crate::{prelude::Endian, Result, UKError};
+
+/// Reads values at given positions in a binary file. `kind` names the file
+/// format in errors.
+pub(crate) struct BinReader<'a> {
+    data:   &'a [u8],
+    endian: Endian,
+    kind:   &'static str,
+}
+
+impl<'a> BinReader<'a> {
+    pub fn new(data: &'a [u8], endian: Endian, kind: &'static str) -> Self {
+        Self { data, endian, kind }
+    }
+
+    pub fn bytes(&self, pos: usize, len: usize) -> Result<&'a [u8]> {
+        self.data
+            .get(pos..pos + len)
+            .ok_or_else(|| UKError::OtherD(format!("{} ends before {:#x}", self.kind, pos + len)))
+    }
+
+    fn array<const N: usize>(&self, pos: usize) -> Result<[u8; N]> {
+        Ok(self
+            .bytes(pos, N)?
+            .try_into()
+            .expect("Slice should have the requested length"))
+    }
+
+    pub fn u8(&self, pos: usize) -> Result<u8> {
+        Ok(self.bytes(pos, 1)?[0])
+    }
+
+    pub fn u16(&self, pos: usize) -> Result<u16> {
+        let bytes = self.array(pos)?;
+        Ok(match self.endian {
+            Endian::Big => u16::from_be_bytes(bytes),
+            Endian::Little => u16::from_le_bytes(bytes),
+        })
+    }
+
+    pub fn u32(&self, pos: usize) -> Result<u32> {
+        let bytes = self.array(pos)?;
+        Ok(match self.endian {
+            Endian::Big => u32::from_be_bytes(bytes),
+            Endian::Little => u32::from_le_bytes(bytes),
+        })
+    }
+
+    pub fn f32(&self, pos: usize) -> Result<f32> {
+        self.u32(pos).map(f32::from_bits)
+    }
+
+    /// Follows an offset relative to its own position
+    pub fn offset(&self, pos: usize) -> Result<usize> {
+        Ok(pos + self.u32(pos)? as usize)
+    }
+
+    /// Reads a null-terminated string
+    pub fn string(&self, pos: usize) -> Result<String> {
+        let bytes = self.data.get(pos..).unwrap_or_default();
+        let end = bytes.iter().position(|b| *b == 0).ok_or_else(|| {
+            UKError::OtherD(format!("Unterminated string in {} at {pos:#x}", self.kind))
+        })?;
+        self.utf8(&bytes[..end], pos)
+    }
+
+    /// Reads a string ending at a null byte or after `max` bytes
+    pub fn fixed_string(&self, pos: usize, max: usize) -> Result<String> {
+        let bytes = self.data.get(pos..).unwrap_or_default();
+        let bytes = &bytes[..bytes.len().min(max)];
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        self.utf8(&bytes[..end], pos)
+    }
+
+    fn utf8(&self, bytes: &[u8], pos: usize) -> Result<String> {
+        std::str::from_utf8(bytes)
+            .map(Into::into)
+            .map_err(|_| UKError::OtherD(format!("Invalid string in {} at {pos:#x}", self.kind)))
+    }
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod tests {
+    use super::BinReader;
+    use crate::prelude::Endian;
+
+    #[test]
+    fn read_values() {
+        let data = b"\x00\x00\x00\x08\x3F\x80\x00\x00Area\x00Scene\x00";
+        let reader = BinReader::new(data, Endian::Big, "test file");
+        assert_eq!(reader.offset(0).unwrap(), 8);
+        assert_eq!(reader.f32(4).unwrap(), 1.0);
+        assert_eq!(reader.string(8).unwrap(), "Area");
+        assert_eq!(reader.fixed_string(13, 3).unwrap(), "Sce");
+        let little = BinReader::new(data, Endian::Little, "test file");
+        assert_eq!(little.u16(2).unwrap(), 0x0800);
+        let err = reader.u32(data.len() - 2).unwrap_err();
+        assert!(err.to_string().contains("test file ends before"));
+        assert!(reader.string(data.len()).is_err());
+    }
+}
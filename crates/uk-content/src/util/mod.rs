@@ -1,9 +1,11 @@
+mod bytes;
 mod collections;
 pub mod converts;
 pub mod parsers;
 
 use std::{collections::BTreeMap, str::FromStr};
 
+pub(crate) use bytes::BinReader;
 pub use collections::*;
 use roead::{
     aamp::*,
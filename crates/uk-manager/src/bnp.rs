@@ -15,7 +15,7 @@ use roead::{
 };
 use rustc_hash::FxHashMap;
 use uk_content::{constants::Language, resource::ResourceData};
-use uk_mod::pack::ModPacker;
+use uk_mod::{pack::ModPacker, Meta};
 use uk_reader::ResourceReader;
 use uk_util::PathExt;
 
@@ -523,6 +523,16 @@ pub fn unpack_bnp(core: &crate::core::Manager, path: &Path) -> Result<PathBuf> {
 }
 
 pub fn convert_bnp(core: &crate::core::Manager, path: &Path) -> Result<PathBuf> {
+    convert_bnp_with_meta(core, path, |_| ())
+}
+
+/// Converts a BNP like [`convert_bnp`], but lets the caller adjust the meta
+/// parsed from the BNP before the mod is packaged.
+pub fn convert_bnp_with_meta(
+    core: &crate::core::Manager,
+    path: &Path,
+    edit_meta: impl FnOnce(&mut Meta),
+) -> Result<PathBuf> {
     let tempdir = unpack_bnp(core, path).with_context(|| {
         format!(
             "Failed to unpack {}",
@@ -532,18 +542,20 @@ pub fn convert_bnp(core: &crate::core::Manager, path: &Path) -> Result<PathBuf>
         )
     })?;
     let tempfile = std::env::temp_dir();
-    let meta = if let Some(info_path) = tempdir.join("info.json").exists_then() {
+    let mut meta = if let Some(info_path) = tempdir.join("info.json").exists_then() {
         ModPacker::parse_info(info_path)?
     } else {
         ModPacker::parse_rules(tempdir.join("rules.txt")).context("Failed to parse BNP metadata")?
     };
+    edit_meta(&mut meta);
     let name = meta.name.clone();
     let new_mod = ModPacker::new(tempdir, tempfile.as_path(), Some(meta), vec![
         core.settings()
             .dump()
             .context("No dump for current platform")?,
     ])
-    .with_context(|| format!("Failed to package converted BNP for mod {}", name))?;
+    .with_context(|| format!("Failed to package converted BNP for mod {}", name))?
+    .with_archives(&core.settings().archives);
     new_mod.pack()
 }
 
@@ -19,9 +19,9 @@ use roead::yaz0::{compress, decompress};
 use rstb::ResourceSizeTable;
 use serde::{Deserialize, Serialize};
 use smartstring::alias::String;
-use uk_content::{constants::Language, platform_prefixes};
+use uk_content::{canonicalize, conflict::ConflictReport, constants::Language, platform_prefixes};
 use uk_mod::{
-    unpack::{ModReader, ModUnpacker},
+    unpack::{ModReader, ModUnpacker, ACTOR_INFO_PATH},
     Manifest,
 };
 
@@ -53,6 +53,87 @@ impl Manager {
         settings.platform_dir().join("pending.yml")
     }
 
+    #[inline(always)]
+    fn conflicts_path(settings: &Settings) -> PathBuf {
+        settings.platform_dir().join("conflicts.yml")
+    }
+
+    fn read_conflicts(settings: &Settings) -> Result<ConflictReport> {
+        let path = Self::conflicts_path(settings);
+        if !path.exists() {
+            return Ok(Default::default());
+        }
+        serde_yaml::from_str(&fs::read_to_string(path)?).context("Failed to parse conflict report")
+    }
+
+    /// Updates the conflict report with the resources rebuilt by a merge,
+    /// starting over if everything was remerged. For a partial merge,
+    /// `changed` is its manifest, including files no mod provides anymore.
+    fn save_conflicts(
+        settings: &Settings,
+        conflicts: ConflictReport,
+        changed: Option<&Manifest>,
+    ) -> Result<()> {
+        let mut report = match changed {
+            None => ConflictReport::default(),
+            Some(changed) => {
+                let mut report = Self::read_conflicts(settings).unwrap_or_else(|e| {
+                    log::warn!("{e:?}");
+                    Default::default()
+                });
+                let aoc_files = changed.aoc_files.iter().map(|file| {
+                    if file.starts_with("Aoc/0010/") {
+                        canonicalize(file.as_str())
+                    } else {
+                        canonicalize(jstr!("Aoc/0010/{file}"))
+                    }
+                });
+                for resource in changed
+                    .content_files
+                    .iter()
+                    .map(|file| canonicalize(file.as_str()))
+                    .chain(aoc_files)
+                {
+                    report.remove(&resource);
+                }
+                report
+            }
+        };
+        report.update(conflicts);
+        let total: usize = report.fields.values().map(Vec::len).sum();
+        if total > 0 {
+            log::warn!(
+                "{} field(s) in {} resource(s) were set by more than one mod",
+                total,
+                report.fields.len()
+            );
+        }
+        let warnings: usize = report.warnings.values().map(Vec::len).sum();
+        if warnings > 0 {
+            log::warn!(
+                "Found {} problem(s) in {} merged resource(s)",
+                warnings,
+                report.warnings.len()
+            );
+        }
+        fs::write(
+            Self::conflicts_path(settings),
+            serde_yaml::to_string(&report)?,
+        )?;
+        Ok(())
+    }
+
+    /// Conflicts between mods and other problems found by the last merge, by
+    /// resource.
+    pub fn conflicts(&self) -> Result<ConflictReport> {
+        let settings = self
+            .settings
+            .upgrade()
+            .context("YIKES, the settings manager is gone")?;
+        let settings = settings.read();
+        Self::read_conflicts(&settings)
+    }
+
     pub fn init(
         settings: &Arc<RwLock<Settings>>,
         mod_manager: &Arc<RwLock<mods::Manager>>,
@@ -397,6 +478,7 @@ impl Manager {
             .context("No dump available for current platform")?;
         let endian = settings.current_mode.into();
         let out_dir = settings.merged_dir();
+        let changed = manifest.clone();
         let unpacker = if let Some(mut manifest) = manifest {
             log::info!("Manifest provided, applying limited changes");
             let mut total_manifest = Manifest::default();
@@ -447,9 +529,26 @@ impl Manager {
                 out_dir.clone(),
             )
         };
+        let resolutions = mod_manager.read().resolutions().unwrap_or_else(|e| {
+            log::warn!("Could not load conflict resolutions: {e}");
+            vec![]
+        });
         log::info!("Applying changes");
-        let rstb_updates = unpacker.unpack()?;
+        let (rstb_updates, conflicts) = unpacker
+            .with_resolutions(resolutions)
+            .sync_actor_info(settings.sync_actor_info)
+            .unpack_with_report()?;
+        if settings.sync_actor_info {
+            let (content, _) = platform_prefixes(endian);
+            if out_dir.join(content).join(ACTOR_INFO_PATH).exists() {
+                self.pending_log.write().extend_copies(&Manifest {
+                    content_files: [ACTOR_INFO_PATH.into()].into(),
+                    ..Default::default()
+                })?;
+            }
+        }
         self.apply_rstb(&out_dir, settings.current_mode, rstb_updates)?;
+        Self::save_conflicts(&settings, conflicts, changed.as_ref())?;
         self.save()?;
         log::info!("All changed applied successfully");
         Ok(())
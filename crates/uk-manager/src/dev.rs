@@ -0,0 +1,147 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use jwalk::WalkDir;
+use serde::{Deserialize, Serialize};
+
+use crate::{settings::Settings, util::HashMap};
+
+/// An installed mod bound to the source folder it is built from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevBinding {
+    /// Path of the installed mod package
+    pub mod_path: PathBuf,
+    pub source: PathBuf,
+    /// Modified time of each source file as of the last scan, saved so that
+    /// edits made while UKMM is closed are picked up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stamps: Option<HashMap<PathBuf, u64>>,
+    /// Changes from a reload which failed, retried with the next change
+    #[serde(skip)]
+    failed: Vec<PathBuf>,
+}
+
+impl DevBinding {
+    /// Reads the modified time of every file in a source folder, to be passed
+    /// to [`DevBinding::update`].
+    pub fn snapshot(source: &Path) -> HashMap<PathBuf, u64> {
+        WalkDir::new(source)
+            .into_iter()
+            .filter_map(std::result::Result::ok)
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let modified = e.metadata().ok()?.modified().ok()?;
+                let stamp = modified
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64;
+                Some((e.path(), stamp))
+            })
+            .collect()
+    }
+
+    /// Whether the source has not been scanned yet.
+    #[inline]
+    pub fn is_new(&self) -> bool {
+        self.stamps.is_none()
+    }
+
+    /// Records a new snapshot of the source and returns the files which were
+    /// added, changed, or removed since the last one, along with any left
+    /// over from a failed reload. The first snapshot only records the current
+    /// state.
+    pub fn update(&mut self, stamps: HashMap<PathBuf, u64>) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = match self.stamps.as_ref() {
+            Some(old) => {
+                stamps
+                    .iter()
+                    .filter(|(path, stamp)| old.get(*path) != Some(stamp))
+                    .map(|(path, _)| path.clone())
+                    .chain(old.keys().filter(|p| !stamps.contains_key(*p)).cloned())
+                    .collect()
+            }
+            None => vec![],
+        };
+        self.stamps = Some(stamps);
+        if !changed.is_empty() {
+            changed.append(&mut self.failed);
+            changed.sort();
+            changed.dedup();
+        }
+        changed
+    }
+
+    /// Keeps the changes from a failed reload so they are packaged again
+    /// once the source is next edited.
+    pub fn retry(&mut self, changed: Vec<PathBuf>) {
+        self.failed = changed;
+    }
+}
+
+/// Mods bound to source folders for live reloading, saved so the bindings
+/// and the last scan of each source survive restarts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DevState {
+    pub bindings: Vec<DevBinding>,
+}
+
+impl DevState {
+    #[inline]
+    pub fn path() -> PathBuf {
+        Settings::config_dir().join("dev.yml")
+    }
+
+    pub fn load() -> Self {
+        fs::read_to_string(Self::path())
+            .context("Failed to read dev mode bindings")
+            .and_then(|text| {
+                serde_yaml::from_str(&text).context("Failed to parse dev mode bindings")
+            })
+            .unwrap_or_else(|e| {
+                log::debug!("{e:?}");
+                Default::default()
+            })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::write(Self::path(), serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_bound(&self, mod_path: &Path) -> bool {
+        self.bindings.iter().any(|b| b.mod_path == mod_path)
+    }
+
+    /// Binds a mod to a source folder, replacing any existing binding.
+    pub fn bind(&mut self, mod_path: PathBuf, source: PathBuf) -> Result<()> {
+        if !source.join("meta.yml").exists() {
+            anyhow_ext::bail!(
+                "{} is not a mod source folder, it has no meta.yml",
+                source.display()
+            );
+        }
+        self.unbind(&mod_path);
+        log::info!(
+            "Watching {} for changes to {}",
+            source.display(),
+            mod_path.display()
+        );
+        self.bindings.push(DevBinding {
+            mod_path,
+            source,
+            stamps: None,
+            failed: vec![],
+        });
+        self.save()
+    }
+
+    pub fn unbind(&mut self, mod_path: &Path) {
+        self.bindings.retain(|b| b.mod_path != mod_path);
+    }
+}
@@ -3,6 +3,12 @@
 pub mod bnp;
 pub mod core;
 pub mod deploy;
+pub mod dev;
+pub mod load_order;
+pub mod modpack;
 pub mod mods;
+pub mod presets;
+pub mod projects;
 pub mod settings;
 pub mod util;
+pub mod watch;
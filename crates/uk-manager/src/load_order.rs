@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use uk_mod::Manifest;
+
+use crate::{mods::Mod, presets::validate_options};
+
+/// A shareable list of mods, in load order, with their enabled state and
+/// options. Unlike a modpack it holds no mod files, so everyone using it needs
+/// to have the mods installed already.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadOrderFile {
+    pub mods: Vec<LoadOrderEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadOrderEntry {
+    pub name:    String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url:     Option<String>,
+    pub enabled: bool,
+    /// Folders of the enabled options
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<PathBuf>,
+}
+
+/// The result of applying a load order file to a profile's mods.
+#[derive(Debug, Default)]
+pub struct LoadOrderImport {
+    /// All the profile's mods, in the new order and with the new states
+    pub mods:    Vec<Mod>,
+    /// Listed mods which are not installed
+    pub missing: Vec<LoadOrderEntry>,
+    /// Files affected by the changes
+    pub dirty:   Manifest,
+}
+
+impl LoadOrderFile {
+    pub fn new<'a>(mods: impl IntoIterator<Item = &'a Mod>) -> Self {
+        Self {
+            mods: mods
+                .into_iter()
+                .map(|mod_| {
+                    LoadOrderEntry {
+                        name:    mod_.meta.name.clone(),
+                        version: mod_.meta.version.clone(),
+                        url:     mod_.meta.url.clone(),
+                        enabled: mod_.enabled,
+                        options: mod_
+                            .enabled_options
+                            .iter()
+                            .map(|opt| opt.path.clone())
+                            .collect(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        serde_yaml::from_str(&fs::read_to_string(path)?)
+            .with_context(|| format!("Failed to parse load order file {}", path.display()))
+    }
+
+    pub fn save_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path.as_ref(), serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Matches the listed mods against installed ones by name, then puts them
+    /// first in the listed order with the listed states. Installed mods which
+    /// aren't listed keep their order after them. Listed options which aren't
+    /// valid for a mod are replaced by its defaults.
+    pub fn apply_to(&self, installed: &[Mod]) -> Result<LoadOrderImport> {
+        let mut result = LoadOrderImport::default();
+        for entry in &self.mods {
+            let Some(old) = installed.iter().find(|mod_| mod_.meta.name == entry.name) else {
+                log::warn!("{} is not installed", entry.name);
+                result.missing.push(entry.clone());
+                continue;
+            };
+            if result.mods.contains(old) {
+                continue;
+            }
+            if old.meta.version != entry.version {
+                log::warn!(
+                    "{} is installed at version {}, but the load order lists {}",
+                    entry.name,
+                    old.meta.version,
+                    entry.version
+                );
+            }
+            let mut mod_ = old.clone();
+            mod_.enabled = entry.enabled;
+            if !mod_.meta.options.is_empty() {
+                let options = entry
+                    .options
+                    .iter()
+                    .filter_map(|path| {
+                        let option = mod_.meta.option(path).cloned();
+                        if option.is_none() {
+                            log::warn!("{} has no option {}", entry.name, path.display());
+                        }
+                        option
+                    })
+                    .collect();
+                match validate_options(&mod_.meta, options) {
+                    Ok(options) => mod_.enabled_options = options,
+                    Err(e) => {
+                        log::warn!(
+                            "Invalid options for {}, using its defaults: {e:?}",
+                            entry.name
+                        );
+                        mod_.enabled_options.clear();
+                        mod_.enable_default_options();
+                    }
+                }
+            }
+            result.mods.push(mod_);
+        }
+        for mod_ in installed {
+            if !result.mods.contains(mod_) {
+                result.mods.push(mod_.clone());
+            }
+        }
+        for (i, mod_) in result.mods.iter().enumerate() {
+            let old = installed
+                .iter()
+                .position(|m| m == mod_)
+                .with_context(|| format!("{} is not installed", mod_.meta.name))?;
+            if old != i || !installed[old].state_eq(mod_) {
+                result.dirty.extend(installed[old].manifest()?.as_ref());
+                result.dirty.extend(mod_.manifest()?.as_ref());
+            }
+        }
+        Ok(result)
+    }
+}
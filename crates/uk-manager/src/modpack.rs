@@ -0,0 +1,269 @@
+use std::{
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use smartstring::alias::String;
+use uk_mod::{
+    pack::{package_hash, sanitise},
+    unpack::ModReader,
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    core::Manager,
+    mods::{Manager as ModManager, Mod},
+    presets::validate_options,
+    settings::Platform,
+    util::HashMap,
+};
+
+/// Name of the modpack manifest in the archive root
+pub const MANIFEST_NAME: &str = "modpack.yml";
+
+/// Describes the profile packed in a modpack. Mods are listed in load order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModpackManifest {
+    pub profile:      String,
+    pub platform:     Platform,
+    pub ukmm_version: String,
+    pub mods:         Vec<ModpackEntry>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModpackEntry {
+    pub name:    String,
+    pub version: String,
+    /// Path of the mod package in the archive
+    pub file:    String,
+    #[serde_as(as = "DisplayFromStr")]
+    pub hash:    usize,
+    /// Content hash of the package, used to reuse mods already installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    pub enabled: bool,
+    /// Folders of the enabled options
+    pub options: Vec<PathBuf>,
+}
+
+impl ModpackEntry {
+    fn new(mod_: &Mod) -> Self {
+        Self {
+            name:    mod_.meta.name.clone(),
+            version: mod_.meta.version.clone(),
+            file:    format!("mods/{}-{}.zip", sanitise(&mod_.meta.name), mod_.hash()).into(),
+            hash:    mod_.hash(),
+            content_hash: mod_.meta.content_hash.clone(),
+            enabled: mod_.enabled,
+            options: mod_
+                .enabled_options
+                .iter()
+                .map(|opt| opt.path.clone())
+                .collect(),
+        }
+    }
+}
+
+/// Writes a profile and all of its mod packages to a single archive.
+pub fn export(core: &Manager, profile: Option<&String>, dest: &Path) -> Result<ModpackManifest> {
+    let mod_manager = core.mod_manager();
+    let profile_name = profile.unwrap_or(mod_manager.current_profile()).clone();
+    let mods: Vec<Mod> = {
+        let profile = mod_manager.get_profile(Some(&profile_name));
+        let mods = profile.mods();
+        let load_order = profile.load_order();
+        load_order
+            .iter()
+            .filter_map(|hash| mods.get(hash).cloned())
+            .collect()
+    };
+    let manifest = ModpackManifest {
+        profile:      profile_name,
+        platform:     core.settings().current_mode,
+        ukmm_version: env!("CARGO_PKG_VERSION").into(),
+        mods:         mods.iter().map(ModpackEntry::new).collect(),
+    };
+    log::info!(
+        "Exporting profile {} with {} mods to {}",
+        manifest.profile,
+        manifest.mods.len(),
+        dest.display()
+    );
+    let mut zip = ZipWriter::new(fs::File::create(dest)?);
+    // Mod packages are already compressed, so there's no point doing it twice
+    let opts = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    zip.start_file(MANIFEST_NAME, opts)?;
+    serde_yaml::to_writer(&mut zip, &manifest)?;
+    for (mod_, entry) in mods.iter().zip(manifest.mods.iter()) {
+        if !mod_.path.is_file() {
+            anyhow_ext::bail!(
+                "{} is not stored as a package and can't be added to a modpack",
+                mod_.meta.name
+            );
+        }
+        zip.start_file(entry.file.as_str(), opts)?;
+        io::copy(&mut fs::File::open(&mod_.path)?, &mut zip)
+            .with_context(|| format!("Failed to add {} to modpack", mod_.meta.name))?;
+    }
+    zip.finish()?;
+    Ok(manifest)
+}
+
+/// Recreates the profile in a modpack, then switches to it and remerges. Mods
+/// whose content hash matches one in the mods folder are reused rather than
+/// extracted again. Returns the name of the new profile.
+pub fn import(core: &Manager, path: &Path, profile: Option<String>) -> Result<String> {
+    let mut zip =
+        ZipArchive::new(BufReader::new(fs::File::open(path)?)).context("Failed to open modpack")?;
+    let manifest: ModpackManifest = serde_yaml::from_reader(
+        zip.by_name(MANIFEST_NAME)
+            .context("Modpack has no manifest")?,
+    )
+    .context("Failed to parse modpack manifest")?;
+    let (platform, mods_dir) = {
+        let settings = core.settings();
+        (settings.current_mode, settings.mods_dir())
+    };
+    if manifest.platform != platform {
+        anyhow_ext::bail!(
+            "This modpack is for {}, but the current mode is {}",
+            manifest.platform,
+            platform
+        );
+    }
+    let profile = profile.unwrap_or_else(|| manifest.profile.clone());
+    if core
+        .settings()
+        .profiles_dir()
+        .join(profile.as_str())
+        .exists()
+    {
+        anyhow_ext::bail!("Profile {} already exists", profile);
+    }
+    log::info!(
+        "Importing modpack profile {} (made with UKMM {})",
+        profile,
+        manifest.ukmm_version
+    );
+    // Packages built before content hashes were stored get one computed
+    let mut stored: HashMap<String, PathBuf> = HashMap::default();
+    let mut stored_names: HashMap<String, PathBuf> = HashMap::default();
+    for path in glob::glob(&mods_dir.join("*.zip").to_string_lossy())?.filter_map(|p| p.ok()) {
+        let Ok(meta) = ModReader::open_peek(&path, vec![]).map(|reader| reader.meta) else {
+            continue;
+        };
+        let hash = match meta.content_hash {
+            Some(hash) => Some(hash),
+            None => package_hash(&path).ok().map(Into::into),
+        };
+        if let Some(hash) = hash {
+            stored.insert(hash, path.clone());
+        }
+        stored_names.insert(meta.name, path);
+    }
+    let mod_manager = core.mod_manager();
+    mod_manager.create_profile_if(&profile)?;
+    if let Err(e) = add_mods(
+        &mod_manager,
+        &mut zip,
+        &manifest,
+        &profile,
+        &stored,
+        &stored_names,
+    ) {
+        // Don't leave a half-built profile, or mods only it used, behind
+        let installed: Vec<Mod> = mod_manager
+            .get_profile(Some(&profile))
+            .mods()
+            .values()
+            .cloned()
+            .collect();
+        for mod_ in installed {
+            let result = if stored_names.values().any(|path| *path == mod_.path) {
+                Ok(())
+            } else {
+                mod_manager.del(&mod_, Some(&profile)).map(|_| ())
+            };
+            if let Err(e) = result {
+                log::warn!("Failed to remove {}: {e:?}", mod_.meta.name);
+            }
+        }
+        if let Err(e) = mod_manager.remove_profile(&profile) {
+            log::warn!("Failed to remove profile {profile}: {e:?}");
+        }
+        return Err(e);
+    }
+    mod_manager.save_profile(&profile)?;
+    drop(mod_manager);
+    core.change_profile(&profile)?;
+    core.settings().save()?;
+    log::info!("Remerging for profile {}", profile);
+    core.deploy_manager().apply(None)?;
+    Ok(profile)
+}
+
+/// Installs the mods in a modpack to a profile, in load order
+fn add_mods<R: io::Read + io::Seek>(
+    mod_manager: &ModManager,
+    zip: &mut ZipArchive<R>,
+    manifest: &ModpackManifest,
+    profile: &String,
+    stored: &HashMap<String, PathBuf>,
+    stored_names: &HashMap<String, PathBuf>,
+) -> Result<()> {
+    let temp = crate::util::get_temp_folder();
+    for entry in &manifest.mods {
+        let reused = entry
+            .content_hash
+            .as_ref()
+            .and_then(|hash| stored.get(hash));
+        let path = match reused {
+            Some(path) => path.clone(),
+            None => {
+                let path = temp.join(sanitise(&entry.name) + ".zip");
+                let mut file = zip
+                    .by_name(entry.file.as_str())
+                    .with_context(|| format!("Modpack is missing {}", entry.file))?;
+                io::copy(&mut file, &mut fs::File::create(&path)?)
+                    .with_context(|| format!("Failed to extract {}", entry.name))?;
+                let hash: String = package_hash(&path)?.into();
+                match stored.get(&hash) {
+                    Some(stored) => stored.clone(),
+                    None if stored_names.contains_key(&entry.name) => {
+                        anyhow_ext::bail!(
+                            "A different version of {} is already installed. Remove it to import \
+                             this modpack.",
+                            entry.name
+                        )
+                    }
+                    None => path,
+                }
+            }
+        };
+        if stored.values().any(|stored| *stored == path) {
+            log::info!("{} is already installed, reusing it", entry.name);
+        }
+        let mod_ = mod_manager
+            .add(&path, Some(profile))
+            .with_context(|| format!("Failed to install {}", entry.name))?;
+        let options = entry
+            .options
+            .iter()
+            .map(|path| {
+                mod_.meta
+                    .option(path)
+                    .cloned()
+                    .with_context(|| format!("{} has no option {}", mod_.meta.name, path.display()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let options = validate_options(&mod_.meta, options)?;
+        mod_manager.set_enabled_options(&mod_, options, Some(profile))?;
+        mod_manager.set_enabled(&mod_, entry.enabled, Some(profile))?;
+    }
+    Ok(())
+}
@@ -15,7 +15,7 @@ use sanitise_file_name as sfn;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
 use smartstring::alias::String;
-use uk_content::platform_prefixes;
+use uk_content::{conflict::Resolution, platform_prefixes};
 use uk_mod::{pack::ModPacker, unpack::ModReader, Manifest, Meta, ModOption};
 
 use crate::{
@@ -25,6 +25,9 @@ use crate::{
 
 type ManifestCache = LazyLock<RwLock<HashMap<(usize, Vec<PathBuf>), Result<Arc<Manifest>>>>>;
 
+/// Name of the conflict resolutions file in each profile folder
+pub const RESOLUTIONS_NAME: &str = "resolutions.yml";
+
 #[serde_as]
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Mod {
@@ -104,22 +107,8 @@ impl Mod {
 
     pub fn enable_default_options(&mut self) {
         if !self.meta.options.is_empty() {
-            for group in self.meta.options.iter_mut() {
-                match group {
-                    uk_mod::OptionGroup::Exclusive(group) => {
-                        self.enabled_options
-                            .extend(group.default.iter().filter_map(|path| {
-                                group.options.iter().find(|o| &o.path == path).cloned()
-                            }));
-                    }
-                    uk_mod::OptionGroup::Multiple(group) => {
-                        self.enabled_options
-                            .extend(group.defaults.iter().filter_map(|path| {
-                                group.options.iter().find(|o| &o.path == path).cloned()
-                            }));
-                    }
-                };
-            }
+            self.enabled_options.extend(self.meta.default_options());
+            self.enabled_options = self.meta.with_requirements(&self.enabled_options);
         }
     }
 }
@@ -291,6 +280,12 @@ impl Manager {
         self.dir.join(self.current_profile.as_str())
     }
 
+    #[inline(always)]
+    pub fn profile_path(&self, profile: Option<&String>) -> PathBuf {
+        self.dir
+            .join(profile.unwrap_or(&self.current_profile).as_str())
+    }
+
     #[inline(always)]
     pub fn profile(&self) -> MappedRef<'_, String, Profile, Profile> {
         self.profiles
@@ -338,6 +333,19 @@ impl Manager {
         Ok(())
     }
 
+    /// Removes a profile other than the current one, along with its folder.
+    pub fn remove_profile(&self, profile: &str) -> Result<()> {
+        if profile == self.current_profile.as_str() {
+            anyhow_ext::bail!("Cannot remove the current profile");
+        }
+        self.profiles.remove(profile);
+        let path = self.dir.join(profile);
+        if path.exists() {
+            util::remove_dir_all(path)?;
+        }
+        Ok(())
+    }
+
     pub fn set_profile(&mut self, profile: &str) -> Result<()> {
         self.current_profile = profile.into();
         self.create_profile_if(profile)?;
@@ -404,6 +412,53 @@ impl Manager {
         Ok(())
     }
 
+    /// Save a profile other than the current one.
+    pub fn save_profile(&self, profile: &String) -> Result<()> {
+        if profile == &self.current_profile {
+            return self.save();
+        }
+        fs::write(
+            self.dir.join(profile.as_str()).join("profile.yml"),
+            serde_yaml::to_string(self.get_profile(Some(profile)).deref())?,
+        )?;
+        log::info!("Saved profile data for {profile}");
+        Ok(())
+    }
+
+    /// Chosen values for conflicting fields in the current profile
+    pub fn resolutions(&self) -> Result<Vec<Resolution>> {
+        let path = self.path().join(RESOLUTIONS_NAME);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        serde_yaml::from_str(&fs::read_to_string(path)?)
+            .context("Failed to parse conflict resolutions")
+    }
+
+    /// Saves a choice for a conflicting field in the current profile,
+    /// replacing any earlier choice for the same field
+    pub fn set_resolution(&self, resolution: Resolution) -> Result<()> {
+        let mut resolutions = self.resolutions()?;
+        resolutions.retain(|r| r.resource != resolution.resource || r.path != resolution.path);
+        log::info!(
+            "Resolving {} in {} with {:?}",
+            resolution.path,
+            resolution.resource,
+            resolution.choice
+        );
+        resolutions.push(resolution);
+        fs::write(
+            self.path().join(RESOLUTIONS_NAME),
+            serde_yaml::to_string(&resolutions)?,
+        )?;
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn current_profile(&self) -> &String {
+        &self.current_profile
+    }
+
     /// Iterate all mods, including disabled, in load order.
     pub fn all_mods(&self) -> ModIterator<'_> {
         Profile::iter(self.profile())
@@ -552,6 +607,28 @@ impl Manager {
         Ok(mod_)
     }
 
+    /// Installs a new build of a mod over the copy in the current profile,
+    /// keeping its storage path, load order position, enabled state and
+    /// options.
+    pub fn reinstall(&self, mod_path: &Path, old_hash: usize) -> Result<Mod> {
+        let old_mod = self
+            .profile()
+            .mods()
+            .get(&old_hash)
+            .cloned()
+            .context("The mod to replace is not installed")?;
+        if mod_path.is_file() {
+            fs::copy(mod_path, &old_mod.path).context("Failed to copy mod to storage folder")?;
+        } else {
+            util::copy_dir(mod_path, &old_mod.path)
+                .context("Failed to copy mod to storage folder")?;
+        }
+        let mut mod_ = Mod::from_reader(ModReader::open_peek(&old_mod.path, vec![])?);
+        mod_.enabled_options = old_mod.enabled_options;
+        log::info!("Reinstalled mod {}", mod_.meta.name);
+        self.replace(mod_, old_hash)
+    }
+
     pub fn set_enabled(
         &self,
         mod_: impl LookupMod,
@@ -581,10 +658,12 @@ impl Manager {
         &self,
         mod_: impl LookupMod,
         options: Vec<ModOption>,
+        profile: Option<&String>,
     ) -> Result<Arc<Manifest>> {
         let hash = mod_.as_map_id();
         let manifest;
-        if let Some(mod_) = self.profile().mods_mut().get_mut(&hash) {
+        if let Some(mod_) = self.get_profile(profile).mods_mut().get_mut(&hash) {
+            let options = mod_.meta.with_requirements(&options);
             manifest = mod_.manifest_with_options(&options)?;
             mod_.enabled_options = options;
         } else {
@@ -705,7 +784,8 @@ pub fn convert_gfx(
         core.settings()
             .dump()
             .context("No dump available for current platform")?,
-    ])?;
+    ])?
+    .with_archives(&core.settings().archives);
     let result_path = packer.pack()?;
     log::info!("Conversion complete");
     Ok(result_path)
@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use uk_mod::{pack::sanitise, Meta, ModOption, ModOptionGroup};
+
+/// A named selection of options for a mod. Presets are saved under
+/// `presets/<mod name>` in a profile folder, and each is a small YAML file
+/// which can be shared and loaded anywhere.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptionPreset {
+    pub name:     String,
+    #[serde(rename = "mod")]
+    pub mod_name: String,
+    /// Folders of the selected options
+    pub options:  Vec<PathBuf>,
+}
+
+impl OptionPreset {
+    pub fn new(name: impl Into<String>, meta: &Meta, options: &[ModOption]) -> Self {
+        Self {
+            name:     name.into(),
+            mod_name: meta.name.clone(),
+            options:  options.iter().map(|opt| opt.path.clone()).collect(),
+        }
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        serde_yaml::from_str(&fs::read_to_string(path)?)
+            .with_context(|| format!("Failed to parse option preset {}", path.display()))
+    }
+
+    pub fn save_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path.as_ref(), serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn dir(profile_dir: &Path, mod_name: &str) -> PathBuf {
+        profile_dir.join("presets").join(sanitise(mod_name))
+    }
+
+    /// Lists the presets saved in a profile for a mod, sorted by name.
+    pub fn list(profile_dir: &Path, meta: &Meta) -> Vec<Self> {
+        let mut presets: Vec<_> = fs::read_dir(Self::dir(profile_dir, &meta.name))
+            .into_iter()
+            .flat_map(|entries| {
+                entries
+                    .filter_map(std::result::Result::ok)
+                    .filter(|e| e.path().extension().and_then(|e| e.to_str()) == Some("yml"))
+                    .filter_map(|e| {
+                        Self::open(e.path())
+                            .inspect_err(|e| log::warn!("{e:?}"))
+                            .ok()
+                    })
+            })
+            .collect();
+        presets.sort_by(|a, b| a.name.cmp(&b.name));
+        presets
+    }
+
+    /// Saves the preset in a profile, replacing any preset with the same
+    /// name for the mod.
+    pub fn save(&self, profile_dir: &Path) -> Result<PathBuf> {
+        let name = sanitise(&self.name);
+        if name.is_empty() {
+            anyhow_ext::bail!("An option preset needs a name");
+        }
+        let dir = Self::dir(profile_dir, &self.mod_name);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(name).with_extension("yml");
+        self.save_to(&path)?;
+        log::info!("Saved option preset {} for {}", self.name, self.mod_name);
+        Ok(path)
+    }
+
+    /// Finds a preset by name among those saved in a profile for a mod.
+    pub fn find(profile_dir: &Path, meta: &Meta, name: &str) -> Option<Self> {
+        Self::list(profile_dir, meta)
+            .into_iter()
+            .find(|preset| preset.name == name)
+    }
+
+    /// Looks up the preset's options in a mod, checking the selection is
+    /// complete.
+    pub fn resolve(&self, meta: &Meta) -> Result<Vec<ModOption>> {
+        if self.mod_name != meta.name {
+            log::warn!(
+                "Option preset {} is for {}, applying it to {}",
+                self.name,
+                self.mod_name,
+                meta.name
+            );
+        }
+        let options = self
+            .options
+            .iter()
+            .map(|path| {
+                meta.option(path)
+                    .cloned()
+                    .with_context(|| format!("{} has no option {}", meta.name, path.display()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        validate_options(meta, options)
+    }
+}
+
+/// Adds the options a selection requires, then checks there are no problems
+/// with it and that every required group has a selection.
+pub fn validate_options(meta: &Meta, selected: Vec<ModOption>) -> Result<Vec<ModOption>> {
+    let options = meta.with_requirements(&selected);
+    if let Some(problem) = meta.option_problems(&options).first() {
+        anyhow_ext::bail!("Invalid options for {}: {}", meta.name, problem);
+    }
+    if let Some(group) = meta.options.iter().find(|group| {
+        group.required()
+            && !group
+                .options()
+                .iter()
+                .any(|opt| options.iter().any(|o| o.path == opt.path))
+    }) {
+        anyhow_ext::bail!(
+            "{} requires an option from the group {}",
+            meta.name,
+            group.name()
+        );
+    }
+    Ok(options)
+}
@@ -0,0 +1,127 @@
+use std::path::{Path, PathBuf};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use uk_mod::{pack::sanitise, Meta};
+
+use crate::settings::Settings;
+
+/// Per-project settings, stored as `project.yml` in the project folder.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectConfig {
+    /// Where the packaged mod is written, defaults to a ZIP next to the
+    /// project folder
+    pub output:    Option<PathBuf>,
+    /// Storage path of the mod installed from this project, which is
+    /// replaced when the project is repackaged
+    pub installed: Option<PathBuf>,
+}
+
+/// A mod under development, kept as a named folder under
+/// [`Settings::projects_dir`]. The folder is an ordinary mod source tree: the
+/// `content`/`aoc` folders, `options`, and `meta.yml`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Project {
+    pub name:   String,
+    pub path:   PathBuf,
+    pub config: ProjectConfig,
+}
+
+impl Project {
+    /// Lists all projects in the projects folder, sorted by name.
+    pub fn list(settings: &Settings) -> Vec<Project> {
+        let mut projects: Vec<_> = fs::read_dir(settings.projects_dir())
+            .into_iter()
+            .flat_map(|entries| {
+                entries
+                    .filter_map(std::result::Result::ok)
+                    .filter(|e| e.path().join("meta.yml").exists())
+                    .filter_map(|e| {
+                        Project::open(e.path())
+                            .inspect_err(|e| log::warn!("{e:?}"))
+                            .ok()
+                    })
+            })
+            .collect();
+        projects.sort_by(|a, b| a.name.cmp(&b.name));
+        projects
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .context("Project has no folder name")?
+            .to_string_lossy()
+            .into();
+        let config = match path.join("project.yml") {
+            file if file.exists() => {
+                serde_yaml::from_str(&fs::read_to_string(file)?)
+                    .with_context(|| format!("Failed to parse project config for {name}"))?
+            }
+            _ => Default::default(),
+        };
+        Ok(Self {
+            name,
+            path: path.to_path_buf(),
+            config,
+        })
+    }
+
+    /// Creates a new project folder for the given meta. If `source` is set,
+    /// its contents are copied in as the starting source tree.
+    pub fn create(settings: &Settings, meta: &Meta, source: Option<&Path>) -> Result<Self> {
+        let name: String = sanitise(&meta.name).into();
+        if name.is_empty() {
+            anyhow_ext::bail!("A project needs a mod name");
+        }
+        let path = settings.projects_dir().join(name.as_str());
+        if path.exists() {
+            anyhow_ext::bail!("A project named {} already exists", name);
+        }
+        fs::create_dir_all(&path)?;
+        if let Some(source) = source.filter(|s| s.is_dir()) {
+            log::info!("Copying {} into project {}", source.display(), name);
+            crate::util::copy_dir(source, &path)
+                .with_context(|| format!("Failed to copy source into project {name}"))?;
+        }
+        let project = Self {
+            name,
+            path,
+            config: Default::default(),
+        };
+        project.save_meta(meta)?;
+        project.save()?;
+        Ok(project)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::write(
+            self.path.join("project.yml"),
+            serde_yaml::to_string(&self.config)?,
+        )?;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn meta(&self) -> Result<Meta> {
+        Meta::parse(self.path.join("meta.yml"))
+    }
+
+    pub fn save_meta(&self, meta: &Meta) -> Result<()> {
+        fs::write(self.path.join("meta.yml"), serde_yaml::to_string(meta)?)?;
+        Ok(())
+    }
+
+    /// The remembered output path, or a ZIP named after the project beside
+    /// the project folder.
+    pub fn output(&self) -> PathBuf {
+        self.config
+            .output
+            .clone()
+            .unwrap_or_else(|| self.path.with_file_name(format!("{}.zip", self.name)))
+    }
+}
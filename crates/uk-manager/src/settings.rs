@@ -11,7 +11,7 @@ use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DefaultOnError};
 use smartstring::alias::String;
-use uk_content::{constants::Language, prelude::Endian};
+use uk_content::{constants::Language, prelude::Endian, resource::ArchiveExts};
 use uk_localization::LocLang;
 use uk_reader::ResourceReader;
 
@@ -200,6 +200,14 @@ impl DeployLayout {
     }
 }
 
+/// A folder checked for new mod archives, which are installed automatically.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchConfig {
+    pub folder:  PathBuf,
+    /// The profile new mods are installed into
+    pub profile: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlatformSettings {
     pub language: Language,
@@ -240,6 +248,13 @@ pub struct Settings {
     pub wiiu_config: Option<PlatformSettings>,
     pub switch_config: Option<PlatformSettings>,
     pub lang: LocLang,
+    pub watch_config: Option<WatchConfig>,
+    /// Recompute derivable actor info fields for merged actor packs
+    pub sync_actor_info: bool,
+    /// Check GameBanana for newer versions of installed mods at startup
+    pub check_mod_updates: bool,
+    /// Archive extensions to merge or replace whole when packaging mods
+    pub archives: ArchiveExts,
 }
 
 impl Default for Settings {
@@ -254,6 +269,10 @@ impl Default for Settings {
             show_changelog: true,
             last_version: None,
             lang: LocLang::English,
+            watch_config: None,
+            sync_actor_info: false,
+            check_mod_updates: true,
+            archives: Default::default(),
         }
     }
 }
@@ -0,0 +1,103 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow_ext::{Context, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+use crate::{settings::Settings, util::HashMap};
+
+/// Archive extensions picked up from the watched folder.
+pub static WATCH_EXTS: &[&str] = &["zip", "bnp", "7z", "rar"];
+
+/// Files modified more recently than this may still be copying, so they are
+/// left for the next scan.
+const SETTLE_TIME: Duration = Duration::from_secs(3);
+
+/// Persistent state of the watched folder, shared by the GUI and `ukmm watch`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchState {
+    /// Modified time of each archive already handled, so that it is only
+    /// installed again if it changes.
+    seen:        HashMap<PathBuf, u64>,
+    /// Mods which need meta info or option selection before they can be
+    /// installed. These are opened the next time the GUI starts.
+    pub pending: Vec<PathBuf>,
+}
+
+impl WatchState {
+    #[inline]
+    pub fn path() -> PathBuf {
+        Settings::config_dir().join("watch.yml")
+    }
+
+    pub fn load() -> Self {
+        fs::read_to_string(Self::path())
+            .context("Failed to read watch folder state")
+            .and_then(|text| {
+                serde_yaml::from_str(&text).context("Failed to parse watch folder state")
+            })
+            .unwrap_or_else(|e| {
+                log::debug!("{e:?}");
+                Default::default()
+            })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::write(Self::path(), serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Returns the archives in `folder` which have been added or changed since
+    /// the last scan, and marks them as seen.
+    pub fn scan(&mut self, folder: &Path) -> Result<Vec<PathBuf>> {
+        let now = SystemTime::now();
+        let mut found = vec![];
+        for entry in fs::read_dir(folder)?.filter_map(std::result::Result::ok) {
+            let path = entry.path();
+            let is_mod = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| WATCH_EXTS.contains(&e.to_lowercase().as_str()))
+                .unwrap_or(false);
+            if !is_mod || !path.is_file() {
+                continue;
+            }
+            let Ok(modified) = entry.metadata().and_then(|m| m.modified()) else {
+                continue;
+            };
+            if now.duration_since(modified).unwrap_or_default() < SETTLE_TIME {
+                continue;
+            }
+            let stamp = modified
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            if self.seen.get(&path) != Some(&stamp) {
+                self.seen.insert(path.clone(), stamp);
+                found.push(path);
+            }
+        }
+        self.seen.retain(|path, _| path.exists());
+        found.sort();
+        Ok(found)
+    }
+
+    /// Marks a mod as waiting for user input.
+    pub fn defer(&mut self, path: PathBuf) {
+        if !self.pending.contains(&path) {
+            self.pending.push(path);
+        }
+    }
+
+    /// Removes a mod from those waiting for user input, returning whether it
+    /// was pending.
+    pub fn resolve(&mut self, path: &Path) -> bool {
+        let len = self.pending.len();
+        self.pending.retain(|p| p != path);
+        self.pending.len() != len
+    }
+}
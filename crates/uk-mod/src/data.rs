@@ -1,17 +1,84 @@
 use std::{
     collections::{BTreeMap, BTreeSet},
-    io::BufWriter,
+    io::Write,
     path::{Path, PathBuf},
-    sync::Arc,
 };
 
 use anyhow_ext::{bail, Context, Result};
+use fs_err as fs;
 use join_str::jstr;
 use jwalk::WalkDir;
-use parking_lot::RwLock;
+use path_slash::PathExt;
 use rayon::prelude::*;
+use roead::{
+    sarc::{Sarc, SarcWriter},
+    yaz0::{compress_if, decompress_if},
+};
 use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
 use uk_content::{canonicalize, platform_prefixes, prelude::*, resource::ResourceData};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use crate::{unpack::ParallelZipReader, Manifest, Meta, ModOptionGroup};
+
+/// Text format used when exporting resource diffs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiffFormat {
+    #[default]
+    Yaml,
+    Json,
+}
+
+impl DiffFormat {
+    #[inline]
+    fn ext(self) -> &'static str {
+        match self {
+            DiffFormat::Yaml => "yml",
+            DiffFormat::Json => "json",
+        }
+    }
+
+    /// Resources are converted through a JSON value first. This keeps the
+    /// externally tagged enum layout for YAML too, which `serde_yaml` cannot
+    /// otherwise write for nested enums like `ResourceData::Mergeable`.
+    pub fn to_text(self, resource: &ResourceData) -> Result<std::string::String> {
+        let value = serde_json::to_value(resource)?;
+        Ok(match self {
+            DiffFormat::Yaml => serde_yaml::to_string(&value)?,
+            DiffFormat::Json => serde_json::to_string_pretty(&value)?,
+        })
+    }
+
+    fn from_text(self, text: &str) -> Result<ResourceData> {
+        let value: serde_json::Value = match self {
+            DiffFormat::Yaml => serde_yaml::from_str(text)?,
+            DiffFormat::Json => serde_json::from_str(text)?,
+        };
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl std::str::FromStr for DiffFormat {
+    type Err = anyhow_ext::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "yaml" | "yml" => Ok(DiffFormat::Yaml),
+            "json" => Ok(DiffFormat::Json),
+            _ => anyhow_ext::bail!("Unknown diff format {s}, expected yaml or json"),
+        }
+    }
+}
+
+/// Files in a mod root which are not resources.
+fn is_resource(name: &str) -> bool {
+    !(name == "manifest.yml" || name == "meta.yml" || name.starts_with("thumb."))
+}
+
+/// Extensions given to resources in an exported diff. Binary files whose own
+/// names end in one of these get an extra `.bin` so they are not read back as
+/// something else.
+const DIFF_EXTS: &[&str] = &["yml", "yaml", "json", "cbor", "bin"];
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct DataTree {
@@ -118,70 +185,246 @@ impl Mergeable for DataTree {
 }
 
 impl DataTree {
+    /// Writes every resource out as a game file in the content and DLC
+    /// folders, rebuilding SARCs from the resources they contain.
     pub fn write_files(self, dir: impl AsRef<Path>, endian: Endian) -> Result<()> {
         let (content, aoc) = platform_prefixes(endian);
         let dir = dir.as_ref();
         self.content_files
-            .into_par_iter()
+            .par_iter()
             .try_for_each(|path| -> Result<()> {
-                println!("Writing {}", &path);
-                let canon = canonicalize(&path);
-                let out = dir.join(content).join(path);
-                let compress = out
-                    .extension()
-                    .and_then(|ext| ext.to_str().map(|ext| ext.starts_with('s')))
-                    .unwrap_or(false);
-                if !out.parent().unwrap().exists() {
-                    std::fs::create_dir_all(out.parent().unwrap())?;
-                }
-                let data = self
-                    .resources
-                    .get(&canon)
-                    .with_context(|| jstr!("Mod missing needed resource {&canon}"))?
-                    .to_binary(endian, &self.resources)?;
-                use std::io::Write;
-                let mut writer = BufWriter::new(std::fs::File::create(&out)?);
-                if compress {
-                    let data = Binary::Bytes(roead::yaz0::compress(data));
-                    writer.write_all(&data)?;
-                } else {
-                    writer.write_all(&data)?;
-                };
-                Ok(())
+                log::trace!("Writing {}", &path);
+                let canon = canonicalize(path.as_str());
+                self.write_file(
+                    &dir.join(content).join(path.as_str()),
+                    &canon,
+                    false,
+                    endian,
+                )
             })?;
         self.aoc_files
-            .into_par_iter()
+            .par_iter()
             .try_for_each(|path| -> Result<()> {
-                let canon = canonicalize(&path);
-                let out = dir.join(aoc).join(path);
-                let compress = out
-                    .extension()
-                    .and_then(|ext| ext.to_str().map(|ext| ext.starts_with('s')))
-                    .unwrap_or(false);
-                if !out.parent().unwrap().exists() {
-                    std::fs::create_dir_all(out.parent().unwrap())?;
-                }
-                let data = self
-                    .resources
-                    .get(&canon)
-                    .with_context(|| jstr!("Mod missing needed resource {&canon}"))?
-                    .to_binary(endian, &self.resources)?;
-                use std::io::Write;
-                let mut writer = BufWriter::new(std::fs::File::create(&out)?);
-                if compress {
-                    let data = Binary::Bytes(roead::yaz0::compress(data));
-                    writer.write_all(&data)?;
-                } else {
-                    writer.write_all(&data)?;
-                };
-                Ok(())
+                log::trace!("Writing {}", &path);
+                let canon = canonicalize(jstr!("Aoc/0010/{path}"));
+                self.write_file(&dir.join(aoc).join(path.as_str()), &canon, true, endian)
             })?;
         Ok(())
     }
 
+    fn write_file(&self, out: &Path, canon: &str, aoc: bool, endian: Endian) -> Result<()> {
+        if let Some(parent) = out.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let data = self.to_binary(canon, aoc, endian)?;
+        fs::write(out, compress_if(&data, out))?;
+        Ok(())
+    }
+
+    fn to_binary(&self, canon: &str, aoc: bool, endian: Endian) -> Result<Vec<u8>> {
+        let resource = self
+            .resources
+            .get(canon)
+            .with_context(|| jstr!("Mod missing needed resource {canon}"))?;
+        Ok(match resource {
+            ResourceData::Binary(data) => data.clone(),
+            ResourceData::Mergeable(resource) => resource.clone().into_binary(endian),
+            ResourceData::Sarc(sarc) => {
+                let mut writer = SarcWriter::new(endian.into()).with_min_alignment(sarc.alignment);
+                for file in sarc.files.iter() {
+                    let canon = match aoc {
+                        true => canonicalize(jstr!("Aoc/0010/{file}")),
+                        false => canonicalize(file.as_str()),
+                    };
+                    let data = self.to_binary(&canon, aoc, endian)?;
+                    writer.add_file(file.as_str(), compress_if(&data, file.as_str()).as_ref());
+                }
+                writer.to_binary()
+            }
+        })
+    }
+
     pub fn from_files(dir: impl AsRef<Path>) -> Result<Self> {
         DataTreeBuilder::new(dir).build()
     }
+
+    #[inline]
+    pub fn manifest(&self) -> Manifest {
+        Manifest {
+            content_files: self.content_files.clone(),
+            aoc_files:     self.aoc_files.clone(),
+        }
+    }
+
+    /// Reads one root of a packaged mod. The main root is the empty path, and
+    /// each option is stored under `options/<path>`.
+    pub fn from_package(zip: &ParallelZipReader, root: &Path) -> Result<Self> {
+        let manifest: Manifest = serde_yaml::from_slice(
+            &zip.get_file(root.join("manifest.yml"))
+                .context("Mod root missing manifest")?,
+        )
+        .context("Failed to parse manifest")?;
+        let mut decompressor = zstd::bulk::Decompressor::with_dictionary(super::DICTIONARY)?;
+        let mut resources: BTreeMap<String, ResourceData> = BTreeMap::new();
+        for file in zip.iter() {
+            let Ok(name) = file.strip_prefix(root) else {
+                continue;
+            };
+            let name = name.to_slash_lossy();
+            if !is_resource(&name) || (root.as_os_str().is_empty() && name.starts_with("options/"))
+            {
+                continue;
+            }
+            let data = zip.get_file(file)?;
+            let size = zstd::bulk::Decompressor::upper_bound(&data).unwrap_or(data.len() * 1024);
+            let data = decompressor
+                .decompress(&data, size)
+                .with_context(|| format!("Failed to decompress {name}"))?;
+            let resource: ResourceData = minicbor_ser::from_slice(&data)
+                .map_err(|e| anyhow::format_err!("{:?}", e))
+                .with_context(|| format!("Failed to parse {name}"))?;
+            resources.insert((&*name).into(), resource);
+        }
+        Ok(Self {
+            content_files: manifest.content_files,
+            aoc_files: manifest.aoc_files,
+            resources,
+        })
+    }
+
+    /// Writes the manifest and every resource to a folder. Mergeable resources
+    /// and SARC maps are written as text named `<canon>.<ext>`, and other
+    /// files are written as-is, or as `<canon>.bin` if their names end in one
+    /// of [`DIFF_EXTS`]. A resource which does not survive the trip through
+    /// text unchanged is kept as raw CBOR in `<canon>.cbor`.
+    pub fn write_diff(&self, dir: &Path, format: DiffFormat) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join("manifest.yml"),
+            serde_yaml::to_string(&self.manifest())?,
+        )?;
+        for (canon, resource) in self.resources.iter() {
+            let (file, data) = match resource {
+                ResourceData::Binary(data) => {
+                    let reserved = Path::new(canon.as_str())
+                        .extension()
+                        .and_then(|e| e.to_str())
+                        .map(|e| DIFF_EXTS.contains(&e))
+                        .unwrap_or(false);
+                    match reserved {
+                        true => (dir.join(format!("{canon}.bin")), data.clone()),
+                        false => (dir.join(canon.as_str()), data.clone()),
+                    }
+                }
+                _ => {
+                    let text = format.to_text(resource).and_then(|text| {
+                        (format.from_text(&text)? == *resource)
+                            .then_some(text)
+                            .context("Resource changed when read back from text")
+                    });
+                    match text {
+                        Ok(text) => (dir.join(format!("{canon}.{}", format.ext())), text.into()),
+                        Err(e) => {
+                            log::warn!("Could not convert {canon} to text, keeping as CBOR: {e}");
+                            (
+                                dir.join(format!("{canon}.cbor")),
+                                minicbor_ser::to_vec(resource)
+                                    .map_err(|e| anyhow::format_err!("{:?}", e))?,
+                            )
+                        }
+                    }
+                }
+            };
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(file, data)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a folder written by [`DataTree::write_diff`], possibly after
+    /// hand edits. The `options` folder is skipped, as it holds the other mod
+    /// roots.
+    pub fn read_diff(dir: &Path) -> Result<Self> {
+        let manifest: Manifest = serde_yaml::from_str(
+            &fs::read_to_string(dir.join("manifest.yml"))
+                .context("Diff folder missing manifest")?,
+        )
+        .context("Failed to parse manifest")?;
+        let mut resources: BTreeMap<String, ResourceData> = BTreeMap::new();
+        for entry in WalkDir::new(dir).sort(true) {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let path = entry.path();
+            let name = path.strip_prefix(dir)?.to_slash_lossy().into_owned();
+            if !is_resource(&name) || name.starts_with("options/") {
+                continue;
+            }
+            let ext = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default();
+            let stem = name
+                .strip_suffix(ext)
+                .and_then(|n| n.strip_suffix('.'))
+                .unwrap_or(name.as_str());
+            let (canon, resource) = match ext {
+                "yml" | "yaml" | "json" => {
+                    let format = if ext == "json" {
+                        DiffFormat::Json
+                    } else {
+                        DiffFormat::Yaml
+                    };
+                    (
+                        stem,
+                        format
+                            .from_text(&fs::read_to_string(&path)?)
+                            .with_context(|| format!("Failed to parse {name}"))?,
+                    )
+                }
+                "cbor" => {
+                    (
+                        stem,
+                        minicbor_ser::from_slice(&fs::read(&path)?)
+                            .map_err(|e| anyhow::format_err!("{:?}", e))
+                            .with_context(|| format!("Failed to parse {name}"))?,
+                    )
+                }
+                "bin" => (stem, ResourceData::Binary(fs::read(&path)?)),
+                _ => (name.as_str(), ResourceData::Binary(fs::read(&path)?)),
+            };
+            resources.insert(canon.into(), resource);
+        }
+        Ok(Self {
+            content_files: manifest.content_files,
+            aoc_files: manifest.aoc_files,
+            resources,
+        })
+    }
+
+    /// Writes the manifest and resources into a mod package under `root`, in
+    /// the same format as [`crate::pack::ModPacker`].
+    pub fn write_package(
+        &self,
+        zip: &mut ZipWriter<fs::File>,
+        root: &Path,
+        compressor: &mut zstd::bulk::Compressor<'static>,
+    ) -> Result<()> {
+        let opts = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (canon, resource) in self.resources.iter() {
+            let data = minicbor_ser::to_vec(resource)
+                .map_err(|e| anyhow::format_err!("{:?}", e))
+                .with_context(|| format!("Failed to serialize {canon}"))?;
+            zip.start_file(root.join(canon.as_str()).to_slash_lossy(), opts)?;
+            zip.write_all(&compressor.compress(&data)?)?;
+        }
+        zip.start_file(root.join("manifest.yml").to_slash_lossy(), opts)?;
+        zip.write_all(serde_yaml::to_string(&self.manifest())?.as_bytes())?;
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -198,60 +441,67 @@ impl DataTreeBuilder {
         }
     }
 
-    fn collect_resources(&mut self, dir: impl AsRef<Path>) -> Result<BTreeSet<String>> {
-        let resources = Arc::new(RwLock::new(&mut self.resources));
-        WalkDir::new(dir.as_ref())
-            .into_iter()
-            .filter_map(|f| {
-                f.ok()
-                    .and_then(|f| f.file_type().is_file().then(|| f.path()))
-            })
-            .collect::<BTreeSet<PathBuf>>()
-            .into_par_iter()
-            .map(|path| -> Result<String> {
-                let name = path
-                    .strip_prefix(&self.root)
-                    .unwrap()
-                    .to_string_lossy()
-                    .to_string();
-                let canon = canonicalize(&name);
-                if !resources.read().unwrap().contains_key(&canon) {
-                    let mut resources = resources.write().unwrap();
-                    let resource =
-                        ResourceData::from_binary(&name, std::fs::read(&path)?, &mut resources)
-                            .with_context(|| jstr!("Error parsing resource at {&name}"))?;
-                    resources.insert(canon, resource);
+    /// Parses a file and, for SARCs, every file nested in it.
+    fn add_resource(&mut self, name: &str, data: &[u8], aoc: bool) -> Result<()> {
+        let canon = canonicalize(name);
+        if self.resources.contains_key(&canon) {
+            return Ok(());
+        }
+        let data = decompress_if(data);
+        let resource = ResourceData::from_binary(name, data.as_ref())
+            .with_context(|| jstr!("Error parsing resource at {name}"))?;
+        if let ResourceData::Sarc(_) = &resource {
+            let sarc = Sarc::new(data.as_ref())?;
+            for file in sarc.files() {
+                let Some(file_name) = file.name else {
+                    continue;
+                };
+                let file_name = match aoc {
+                    true => jstr!("Aoc/0010/{file_name}"),
+                    false => file_name.to_owned(),
                 };
-                Ok(name)
-            })
-            .collect::<Result<_>>()
+                self.add_resource(&file_name, file.data, aoc)?;
+            }
+        }
+        self.resources.insert(canon, resource);
+        Ok(())
+    }
+
+    fn collect_resources(&mut self, dir: PathBuf, aoc: bool) -> Result<BTreeSet<String>> {
+        let mut files = BTreeSet::new();
+        for entry in WalkDir::new(&dir).sort(true) {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let path = entry.path();
+            let file: String = path.strip_prefix(&dir)?.to_slash_lossy().into();
+            let name = path.strip_prefix(&self.root)?.to_slash_lossy().into_owned();
+            self.add_resource(&name, &fs::read(&path)?, aoc)?;
+            files.insert(file);
+        }
+        Ok(files)
     }
 
     pub fn build(mut self) -> Result<DataTree> {
         let (content_u, aoc_u) = platform_prefixes(Endian::Big);
         let (content_nx, aoc_nx) = platform_prefixes(Endian::Little);
-        let content = self
-            .root
-            .join(content_u)
-            .exists()
-            .then(|| content_u)
-            .or_else(|| self.root.join(content_nx).exists().then(|| content_nx));
-        let aoc = self
-            .root
-            .join(aoc_u)
-            .exists()
-            .then(|| aoc_u)
-            .or_else(|| self.root.join(aoc_nx).exists().then(|| aoc_nx));
+        let content = [content_u, content_nx]
+            .into_iter()
+            .find(|content| self.root.join(content).exists());
+        let aoc = [aoc_u, aoc_nx]
+            .into_iter()
+            .find(|aoc| self.root.join(aoc).exists());
         if content.is_none() && aoc.is_none() {
             bail!("No content or aoc directory found in mod")
         };
         Ok(DataTree {
             content_files: content
-                .map(|content| self.collect_resources(self.root.join(content)))
+                .map(|content| self.collect_resources(self.root.join(content), false))
                 .transpose()?
                 .unwrap_or_default(),
             aoc_files:     aoc
-                .map(|aoc| self.collect_resources(self.root.join(aoc)))
+                .map(|aoc| self.collect_resources(self.root.join(aoc), true))
                 .transpose()?
                 .unwrap_or_default(),
             resources:     self.resources,
@@ -259,8 +509,84 @@ impl DataTreeBuilder {
     }
 }
 
+/// The ZIP roots of a mod: the main root, then one per option.
+pub(crate) fn mod_roots(meta: &Meta) -> Vec<PathBuf> {
+    let mut roots: BTreeSet<PathBuf> = BTreeSet::new();
+    for group in meta.options.iter() {
+        roots.extend(
+            group
+                .options()
+                .iter()
+                .map(|opt| Path::new("options").join(&opt.path)),
+        );
+    }
+    std::iter::once(PathBuf::new()).chain(roots).collect()
+}
+
+/// Exports a packaged mod as a folder of readable diffs. The meta is copied
+/// as-is, the main root is written to the folder itself, and each option is
+/// written to `options/<path>`.
+pub fn export_diff(mod_path: &Path, dir: &Path, format: DiffFormat) -> Result<()> {
+    let zip = ParallelZipReader::open(mod_path, false).context("Failed to open mod")?;
+    let meta_data = zip.get_file("meta.yml").context("Mod missing meta file")?;
+    let meta: Meta = serde_yaml::from_slice(&meta_data).context("Failed to parse meta file")?;
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("meta.yml"), &meta_data)?;
+    for file in zip
+        .iter()
+        .filter(|f| f.to_str().map(|n| n.starts_with("thumb.")).unwrap_or(false))
+    {
+        fs::write(dir.join(file), zip.get_file(file)?)?;
+    }
+    for root in mod_roots(&meta) {
+        log::info!("Exporting diffs for root {}", root.display());
+        DataTree::from_package(&zip, &root)
+            .with_context(|| format!("Failed to read mod root {}", root.display()))?
+            .write_diff(&dir.join(&root), format)
+            .with_context(|| format!("Failed to export mod root {}", root.display()))?;
+    }
+    Ok(())
+}
+
+/// Rebuilds a mod package from a folder written by [`export_diff`].
+pub fn import_diff(dir: &Path, output: &Path) -> Result<PathBuf> {
+    let mut meta = Meta::parse(dir.join("meta.yml"))?;
+    // The contents may have been edited, so the old hash no longer applies
+    meta.content_hash = None;
+    let output = if output.is_dir() {
+        output
+            .join(crate::pack::sanitise(&meta.name))
+            .with_extension("zip")
+    } else {
+        output.to_path_buf()
+    };
+    let mut zip = ZipWriter::new(fs::File::create(&output)?);
+    let mut compressor = zstd::bulk::Compressor::with_dictionary(8, super::DICTIONARY)?;
+    for root in mod_roots(&meta) {
+        log::info!("Importing diffs for root {}", root.display());
+        DataTree::read_diff(&dir.join(&root))
+            .with_context(|| format!("Failed to read diffs for root {}", root.display()))?
+            .write_package(&mut zip, &root, &mut compressor)?;
+    }
+    let opts = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for ext in ["jpg", "jpeg", "png", "svg"] {
+        let thumb = dir.join("thumb").with_extension(ext);
+        if thumb.exists() {
+            zip.start_file(format!("thumb.{ext}"), opts)?;
+            zip.write_all(&fs::read(thumb)?)?;
+            break;
+        }
+    }
+    zip.start_file("meta.yml", opts)?;
+    zip.write_all(serde_yaml::to_string(&meta)?.as_bytes())?;
+    zip.finish()?;
+    Ok(output)
+}
+
 #[cfg(test)]
 mod tests {
+    use uk_content::resource::SarcMap;
+
     use super::*;
 
     #[test]
@@ -277,4 +603,45 @@ mod tests {
         println!("Comparing...");
         assert_eq!(tree, tree2);
     }
+
+    #[test]
+    fn diff_roundtrip() {
+        let tree = DataTree {
+            content_files: [
+                "Pack/Test.pack".into(),
+                "Test.txt".into(),
+                "Test.yml".into(),
+            ]
+            .into_iter()
+            .collect(),
+            aoc_files:     Default::default(),
+            resources:     [
+                (
+                    "Pack/Test.pack".into(),
+                    ResourceData::Sarc(SarcMap {
+                        alignment: 4,
+                        files:     [String::from("Actor/ActorLink/Test.bxml")]
+                            .into_iter()
+                            .collect(),
+                    }),
+                ),
+                ("Test.txt".into(), ResourceData::Binary(b"Hello".to_vec())),
+                ("Test.yml".into(), ResourceData::Binary(b"Hello".to_vec())),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        for format in [DiffFormat::Yaml, DiffFormat::Json] {
+            let dir = tempfile::tempdir().unwrap();
+            tree.write_diff(dir.path(), format).unwrap();
+            assert!(dir.path().join("Test.txt").exists());
+            assert!(dir.path().join("Test.yml.bin").exists());
+            assert!(
+                dir.path()
+                    .join(format!("Pack/Test.pack.{}", format.ext()))
+                    .exists()
+            );
+            assert_eq!(DataTree::read_diff(dir.path()).unwrap(), tree);
+        }
+    }
 }
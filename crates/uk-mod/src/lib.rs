@@ -10,8 +10,11 @@ use smartstring::alias::String;
 use uk_content::{
     constants::Language,
     prelude::Endian,
+    resource::ArchiveExts,
     util::{HashSet, IndexMap},
 };
+pub mod data;
+pub mod lint;
 pub mod pack;
 pub mod unpack;
 pub use zstd;
@@ -45,6 +48,25 @@ impl Manifest {
             )
     }
 
+    /// The files which are the given resource, by its canonical path, rather
+    /// than holding it in an archive
+    pub fn files_for(&self, resource: &str) -> Manifest {
+        Manifest {
+            content_files: self
+                .content_files
+                .iter()
+                .filter(|file| file.replace(".s", ".") == resource)
+                .cloned()
+                .collect(),
+            aoc_files:     self
+                .aoc_files
+                .iter()
+                .filter(|file| ["Aoc/0010/", &file.replace(".s", ".")].join("") == resource)
+                .cloned()
+                .collect(),
+        }
+    }
+
     pub fn extend(&mut self, other: &Manifest) {
         self.content_files
             .extend(other.content_files.iter().cloned());
@@ -198,6 +220,52 @@ pub enum OptionGroup {
     Multiple(MultipleOptionGroup),
 }
 
+/// A problem with a selection of mod options which stops it being used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionProblem {
+    /// An option requires an option the mod does not have
+    MissingRequirement { option: String, requires: PathBuf },
+    /// An option requires an option which is not selected
+    Unselected { option: String, requires: String },
+    /// More than one option is selected in an exclusive group
+    ExclusiveConflict {
+        group:   String,
+        options: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for OptionProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptionProblem::MissingRequirement { option, requires } => {
+                write!(
+                    f,
+                    "{option} requires option {}, which does not exist",
+                    requires.display()
+                )
+            }
+            OptionProblem::Unselected { option, requires } => {
+                write!(f, "{option} requires {requires}")
+            }
+            OptionProblem::ExclusiveConflict { group, options } => {
+                write!(
+                    f,
+                    "Only one of {} can be used ({group})",
+                    options.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// A resource which more than one option replaces outright instead of
+/// merging, so only the last of them takes effect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionConflict {
+    pub file:    String,
+    pub options: Vec<PathBuf>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ModCategory {
     #[serde(alias = "")]
@@ -368,6 +436,12 @@ pub struct Meta {
     #[serde(rename = "option_groups")]
     pub options: Vec<OptionGroup>,
     pub masters: IndexMap<usize, (String, String)>,
+    /// SHA-256 of the packaged files, set when the mod is packaged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Archive extensions to merge or replace whole, beyond the defaults
+    #[serde(default, skip_serializing_if = "ArchiveExts::is_empty")]
+    pub archives: ArchiveExts,
 }
 
 #[allow(clippy::derived_hash_with_manual_eq)]
@@ -402,6 +476,90 @@ impl Meta {
             .context("Failed to read meta file")
             .and_then(|s| serde_yaml::from_str(&s).context("Failed to parse meta file"))
     }
+
+    pub fn option(&self, path: &Path) -> Option<&ModOption> {
+        self.options
+            .iter()
+            .flat_map(|group| group.options().iter())
+            .find(|opt| opt.path == path)
+    }
+
+    /// The options selected by default in each group.
+    pub fn default_options(&self) -> Vec<ModOption> {
+        self.options
+            .iter()
+            .flat_map(|group| {
+                let defaults: Vec<&PathBuf> = match group {
+                    OptionGroup::Exclusive(group) => group.default.iter().collect(),
+                    OptionGroup::Multiple(group) => group.defaults.iter().collect(),
+                };
+                group
+                    .options()
+                    .iter()
+                    .filter(move |opt| defaults.contains(&&opt.path))
+                    .cloned()
+            })
+            .collect()
+    }
+
+    /// Adds every option the selection requires, including the requirements
+    /// of those options in turn. Requirements the mod does not have are
+    /// skipped, and reported by [`Meta::option_problems`].
+    pub fn with_requirements(&self, selected: &[ModOption]) -> Vec<ModOption> {
+        let mut resolved = selected.to_vec();
+        let mut i = 0;
+        while i < resolved.len() {
+            for req in resolved[i].requires.clone() {
+                if !resolved.iter().any(|opt| opt.path == req) {
+                    if let Some(opt) = self.option(&req) {
+                        resolved.push(opt.clone());
+                    }
+                }
+            }
+            i += 1;
+        }
+        resolved
+    }
+
+    pub fn option_problems(&self, selected: &[ModOption]) -> Vec<OptionProblem> {
+        let mut problems = vec![];
+        for opt in selected {
+            for req in &opt.requires {
+                match self.option(req) {
+                    None => {
+                        problems.push(OptionProblem::MissingRequirement {
+                            option:   opt.name.clone(),
+                            requires: req.clone(),
+                        })
+                    }
+                    Some(req) if !selected.iter().any(|opt| opt.path == req.path) => {
+                        problems.push(OptionProblem::Unselected {
+                            option:   opt.name.clone(),
+                            requires: req.name.clone(),
+                        })
+                    }
+                    _ => (),
+                }
+            }
+        }
+        for group in &self.options {
+            if let OptionGroup::Exclusive(group) = group {
+                let options: Vec<String> = group
+                    .options
+                    .iter()
+                    .filter(|opt| selected.iter().any(|sel| sel.path == opt.path))
+                    .map(|opt| opt.name.clone())
+                    .collect();
+                if options.len() > 1 {
+                    problems.push(OptionProblem::ExclusiveConflict {
+                        group: group.name.clone(),
+                        options,
+                    });
+                }
+            }
+        }
+        problems
+    }
 }
 
 #[cfg(test)]
@@ -427,9 +585,75 @@ mod tests {
                 url: None,
                 version: "1.0.0".into(),
                 masters: Default::default(),
+                content_hash: None,
+                archives: Default::default(),
                 options: Default::default(),
             })
             .unwrap()
         );
     }
+
+    #[test]
+    fn option_requirements() {
+        let opt = |path: &str, requires: &[&str]| {
+            ModOption {
+                name: path.into(),
+                description: Default::default(),
+                path: path.into(),
+                requires: requires.iter().map(PathBuf::from).collect(),
+            }
+        };
+        let meta = Meta {
+            api: env!("CARGO_PKG_VERSION").into(),
+            name: "Test Mod".into(),
+            description: Default::default(),
+            category: ModCategory::Other,
+            author: Default::default(),
+            platform: ModPlatform::Universal,
+            url: None,
+            version: "1.0.0".into(),
+            masters: Default::default(),
+            content_hash: None,
+            archives: Default::default(),
+            options: vec![
+                OptionGroup::Exclusive(ExclusiveOptionGroup {
+                    name: "Color".into(),
+                    description: Default::default(),
+                    required: false,
+                    default: None,
+                    options: vec![opt("red", &[]), opt("blue", &[])],
+                }),
+                OptionGroup::Multiple(MultipleOptionGroup {
+                    name: "Extras".into(),
+                    options: vec![
+                        opt("hat", &["cape"]),
+                        opt("cape", &["red"]),
+                        opt("wand", &["blue", "staff"]),
+                    ],
+                    ..Default::default()
+                }),
+            ],
+        };
+        let resolved = meta.with_requirements(&[opt("hat", &["cape"])]);
+        let paths: Vec<_> = resolved.iter().map(|o| o.path.to_str().unwrap()).collect();
+        assert_eq!(paths, ["hat", "cape", "red"]);
+        assert!(meta.option_problems(&resolved).is_empty());
+        assert_eq!(meta.option_problems(&resolved[..1]), vec![
+            OptionProblem::Unselected {
+                option:   "hat".into(),
+                requires: "cape".into(),
+            }
+        ]);
+        let resolved = meta.with_requirements(&[opt("red", &[]), opt("wand", &["blue", "staff"])]);
+        assert_eq!(meta.option_problems(&resolved), vec![
+            OptionProblem::MissingRequirement {
+                option:   "wand".into(),
+                requires: "staff".into(),
+            },
+            OptionProblem::ExclusiveConflict {
+                group:   "Color".into(),
+                options: vec!["red".into(), "blue".into()],
+            },
+        ]);
+    }
 }
@@ -0,0 +1,415 @@
+use std::path::{Path, PathBuf};
+
+use anyhow_ext::{Context, Result};
+use botw_utils::hashes::StockHashTable;
+use fs_err as fs;
+use jwalk::WalkDir;
+use path_slash::PathExt;
+use rayon::prelude::*;
+use roead::{sarc::Sarc, yaz0::decompress_if};
+use serde::Serialize;
+use smartstring::alias::String;
+use uk_content::{
+    canonicalize, platform_prefixes,
+    prelude::Endian,
+    resource::{MergeableResource, ResourceData},
+    util::HashSet,
+};
+use uk_util::PathExt as UkPathExt;
+
+use crate::{
+    data::{mod_roots, DataTree},
+    pack::{ModPacker, NX_HASH_TABLE, WIIU_HASH_TABLE},
+    unpack::ParallelZipReader,
+    Meta, ModOptionGroup, ModPlatform, OptionGroup,
+};
+
+/// A problem found in a mod. Files are named relative to the mod root, and
+/// files inside SARCs are written as `<archive>//<file>`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Lint {
+    /// A resource which cannot be merged in a mod marked as universal
+    UnmergeableInUniversal { file: String },
+    /// A file with a compressed extension which is not Yaz0 compressed
+    Uncompressed { file: String },
+    /// A Yaz0 compressed file without a compressed extension
+    UnexpectedlyCompressed { file: String },
+    /// A file identical to the stock game file, which only adds size
+    Unmodified { file: String },
+    /// An option whose folder does not exist
+    MissingOption {
+        group:  String,
+        option: String,
+        path:   PathBuf,
+    },
+    /// An option which requires an option that does not exist
+    MissingRequirement { option: String, requires: PathBuf },
+    /// A group default which is not one of the group's options
+    InvalidDefault { group: String, default: PathBuf },
+    /// A resource which could not be parsed at all
+    ParseFailed { file: String, error: String },
+    /// A resource which could not be parsed as its usual type, so it would be
+    /// stored as a binary override of every other mod
+    BinaryOverride { file: String, error: String },
+}
+
+impl Lint {
+    /// Errors stop a mod from packaging or working as intended, while the
+    /// rest are warnings.
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            Lint::UnmergeableInUniversal { .. }
+                | Lint::MissingOption { .. }
+                | Lint::MissingRequirement { .. }
+                | Lint::InvalidDefault { .. }
+                | Lint::ParseFailed { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LintReport {
+    pub name:     String,
+    pub path:     PathBuf,
+    pub errors:   usize,
+    pub warnings: usize,
+    pub lints:    Vec<Lint>,
+}
+
+/// Checks a mod source folder or a packaged mod for common mistakes. Checks
+/// on the raw files, like compression and stock copies, only apply to source
+/// folders, since packaging already strips those.
+pub fn lint(path: &Path) -> Result<LintReport> {
+    let (meta, lints) = if path.is_dir() {
+        lint_source(path)?
+    } else {
+        lint_package(path)?
+    };
+    let errors = lints.iter().filter(|l| l.is_error()).count();
+    Ok(LintReport {
+        name: meta.name,
+        path: path.to_path_buf(),
+        errors,
+        warnings: lints.len() - errors,
+        lints,
+    })
+}
+
+fn lint_options(meta: &Meta, exists: impl Fn(&Path) -> bool) -> Vec<Lint> {
+    let mut lints = vec![];
+    let paths: HashSet<&Path> = meta
+        .options
+        .iter()
+        .flat_map(|group| group.options().iter().map(|opt| opt.path.as_path()))
+        .collect();
+    for group in &meta.options {
+        for opt in group.options() {
+            if !exists(&opt.path) {
+                lints.push(Lint::MissingOption {
+                    group:  group.name().into(),
+                    option: opt.name.clone(),
+                    path:   opt.path.clone(),
+                });
+            }
+            for req in &opt.requires {
+                if !paths.contains(req.as_path()) || !exists(req) {
+                    lints.push(Lint::MissingRequirement {
+                        option:   opt.name.clone(),
+                        requires: req.clone(),
+                    });
+                }
+            }
+        }
+        let defaults: Vec<&PathBuf> = match group {
+            OptionGroup::Exclusive(group) => group.default.iter().collect(),
+            OptionGroup::Multiple(group) => group.defaults.iter().collect(),
+        };
+        for default in defaults {
+            if !group.options().iter().any(|opt| &opt.path == default) {
+                lints.push(Lint::InvalidDefault {
+                    group:   group.name().into(),
+                    default: default.clone(),
+                });
+            }
+        }
+    }
+    lints
+}
+
+fn lint_package(path: &Path) -> Result<(Meta, Vec<Lint>)> {
+    let zip = ParallelZipReader::open(path, true).context("Failed to open mod")?;
+    let meta: Meta =
+        serde_yaml::from_slice(&zip.get_file("meta.yml").context("Mod missing meta file")?)
+            .context("Failed to parse meta file")?;
+    let has_root = |root: &Path| zip.get_file(root.join("manifest.yml")).is_ok();
+    let mut lints = lint_options(&meta, |opt| has_root(&Path::new("options").join(opt)));
+    let universal = meta.platform == ModPlatform::Universal;
+    for root in mod_roots(&meta).into_iter().filter(|root| has_root(root)) {
+        let tree = DataTree::from_package(&zip, &root)
+            .with_context(|| format!("Failed to read mod root {}", root.display()))?;
+        for (name, resource) in tree.resources {
+            let file: String = root.join(name.as_str()).to_slash_lossy().into();
+            match resource {
+                ResourceData::Binary(_) if universal => {
+                    lints.push(Lint::UnmergeableInUniversal { file })
+                }
+                ResourceData::Mergeable(MergeableResource::BinaryOverride(v)) => {
+                    lints.push(Lint::BinaryOverride {
+                        file,
+                        error: v.1.as_str().into(),
+                    })
+                }
+                _ => (),
+            }
+        }
+    }
+    Ok((meta, lints))
+}
+
+fn source_meta(source: &Path) -> Result<Meta> {
+    if let Some(meta) = source.join("meta.yml").exists_then() {
+        Meta::parse(meta)
+    } else if let Some(rules) = source.join("rules.txt").exists_then() {
+        ModPacker::parse_rules(rules)
+    } else if let Some(info) = source.join("info.json").exists_then() {
+        ModPacker::parse_info(info)
+    } else {
+        anyhow_ext::bail!("No meta file found in {}", source.display())
+    }
+}
+
+/// Option folders live under `options`, except in graphic packs converted
+/// from `rules.txt`, which keep them in the source folder itself.
+fn option_root(source: &Path, opt: &Path) -> Option<PathBuf> {
+    source
+        .join("options")
+        .join(opt)
+        .exists_then()
+        .or_else(|| source.join(opt).exists_then())
+}
+
+fn lint_source(source: &Path) -> Result<(Meta, Vec<Lint>)> {
+    let meta = source_meta(source)?;
+    let mut lints = lint_options(&meta, |opt| option_root(source, opt).is_some());
+    let endian = [Endian::Big, Endian::Little]
+        .into_iter()
+        .find(|endian| {
+            let (content, aoc) = platform_prefixes(*endian);
+            source.join(content).exists() || source.join(aoc).exists()
+        })
+        .with_context(|| format!("No content or DLC folder found in {}", source.display()))?;
+    let table: &StockHashTable = match endian {
+        Endian::Little => &NX_HASH_TABLE,
+        Endian::Big => &WIIU_HASH_TABLE,
+    };
+    let mut roots = vec![source.to_path_buf()];
+    roots.extend(
+        meta.options
+            .iter()
+            .flat_map(|group| group.options().iter())
+            .filter_map(|opt| option_root(source, &opt.path)),
+    );
+    let (content, aoc) = platform_prefixes(endian);
+    let files: Vec<(PathBuf, PathBuf)> = roots
+        .iter()
+        .flat_map(|root| {
+            [root.join(content), root.join(aoc)]
+                .into_iter()
+                .filter(|dir| dir.is_dir())
+                .flat_map(move |dir| {
+                    WalkDir::new(dir)
+                        .into_iter()
+                        .filter_map(std::result::Result::ok)
+                        .filter(|e| e.file_type().is_file())
+                        .map(move |e| (root.clone(), e.path()))
+                })
+        })
+        .collect();
+    let file_lints = files
+        .into_par_iter()
+        .map(|(root, path)| lint_source_file(&root, &path, &meta, table))
+        .collect::<Result<Vec<_>>>()?;
+    lints.extend(file_lints.into_iter().flatten());
+    Ok((meta, lints))
+}
+
+/// Yaz0 compressed files are named with an `s` before their usual
+/// extension, apart from plain `.sarc` archives.
+fn expects_yaz0(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.starts_with('s') && e != "sarc")
+        .unwrap_or(false)
+}
+
+fn lint_compression(file: &str, name: &str, data: &[u8], lints: &mut Vec<Lint>) {
+    match (expects_yaz0(name), data.starts_with(b"Yaz0")) {
+        (true, false) => lints.push(Lint::Uncompressed { file: file.into() }),
+        (false, true) => lints.push(Lint::UnexpectedlyCompressed { file: file.into() }),
+        _ => (),
+    }
+}
+
+fn lint_source_file(
+    root: &Path,
+    path: &Path,
+    meta: &Meta,
+    table: &StockHashTable,
+) -> Result<Vec<Lint>> {
+    let mut lints = vec![];
+    let name = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .to_slash_lossy()
+        .into_owned();
+    let data = fs::read(path)?;
+    lint_compression(&name, &name, &data, &mut lints);
+    let data = decompress_if(&data);
+    if name.ends_with("sizetable") || data.len() < 4 {
+        return Ok(lints);
+    }
+    let canon = canonicalize(&name);
+    if !table.is_file_modded(&canon, &*data, true) {
+        lints.push(Lint::Unmodified {
+            file: name.as_str().into(),
+        });
+        return Ok(lints);
+    }
+    lint_resource(
+        &name,
+        &name,
+        &data,
+        meta,
+        table,
+        table.is_file_new(&canon),
+        &mut lints,
+    );
+    Ok(lints)
+}
+
+/// Checks how a resource would be packaged, descending into mergeable SARCs
+/// the same way the packager does.
+fn lint_resource(
+    file: &str,
+    name: &str,
+    data: &[u8],
+    meta: &Meta,
+    table: &StockHashTable,
+    is_new: bool,
+    lints: &mut Vec<Lint>,
+) {
+    let universal = meta.platform == ModPlatform::Universal;
+    match ResourceData::from_binary_with_archives(name, data, &meta.archives) {
+        Err(e) => {
+            lints.push(Lint::ParseFailed {
+                file:  file.into(),
+                error: format!("{e:?}").into(),
+            });
+            return;
+        }
+        Ok(ResourceData::Binary(_)) if universal => {
+            lints.push(Lint::UnmergeableInUniversal { file: file.into() })
+        }
+        Ok(ResourceData::Mergeable(MergeableResource::BinaryOverride(v))) => {
+            lints.push(Lint::BinaryOverride {
+                file:  file.into(),
+                error: v.1.as_str().into(),
+            })
+        }
+        Ok(_) => (),
+    }
+    let canon = canonicalize(name);
+    if !meta.archives.is_mergeable_sarc(canon.as_str(), data) {
+        return;
+    }
+    let Ok(sarc) = Sarc::new(data) else {
+        return;
+    };
+    let is_aoc = canon.starts_with("Aoc");
+    for entry in sarc.files() {
+        let Some(inner) = entry.name().filter(|_| !entry.data.is_empty()) else {
+            continue;
+        };
+        let nested_file = format!("{file}//{inner}");
+        lint_compression(&nested_file, inner, entry.data, lints);
+        let nested_name = match is_aoc {
+            true => format!("Aoc/0010/{inner}"),
+            false => inner.to_owned(),
+        };
+        let nested_data = decompress_if(entry.data);
+        if !is_new && !table.is_file_modded(&canonicalize(&nested_name), &*nested_data, true) {
+            continue;
+        }
+        lint_resource(
+            &nested_file,
+            &nested_name,
+            &nested_data,
+            meta,
+            table,
+            is_new,
+            lints,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExclusiveOptionGroup, ModCategory, ModOption};
+
+    #[test]
+    fn option_lints() {
+        let option = |path: &str, requires: &[&str]| {
+            ModOption {
+                name: path.into(),
+                description: Default::default(),
+                path: path.into(),
+                requires: requires.iter().map(PathBuf::from).collect(),
+            }
+        };
+        let meta = Meta {
+            api: env!("CARGO_PKG_VERSION").into(),
+            name: "Test Mod".into(),
+            description: Default::default(),
+            category: ModCategory::Other,
+            author: Default::default(),
+            platform: ModPlatform::Universal,
+            url: None,
+            version: "1.0.0".into(),
+            masters: Default::default(),
+            content_hash: None,
+            archives: Default::default(),
+            options: vec![OptionGroup::Exclusive(ExclusiveOptionGroup {
+                name: "Colors".into(),
+                description: Default::default(),
+                required: false,
+                default: Some("purple".into()),
+                options: vec![
+                    option("red", &[]),
+                    option("blue", &["red", "green"]),
+                    option("yellow", &[]),
+                ],
+            })],
+        };
+        let lints = lint_options(&meta, |path| path != Path::new("yellow"));
+        assert_eq!(lints, vec![
+            Lint::MissingRequirement {
+                option:   "blue".into(),
+                requires: "green".into(),
+            },
+            Lint::MissingOption {
+                group:  "Colors".into(),
+                option: "yellow".into(),
+                path:   "yellow".into(),
+            },
+            Lint::InvalidDefault {
+                group:   "Colors".into(),
+                default: "purple".into(),
+            },
+        ]);
+        assert!(lints.iter().all(Lint::is_error));
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
-    collections::{BTreeSet, HashSet},
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashSet},
     io::Write,
     path::{Path, PathBuf},
     sync::{atomic::AtomicUsize, Arc, LazyLock},
@@ -17,13 +18,14 @@ use roead::{sarc::Sarc, yaz0::decompress_if};
 pub use sanitise_file_name::sanitise;
 use serde::Deserialize;
 use serde_with::{serde_as, DefaultOnError};
+use sha2::{Digest, Sha256};
 use smartstring::alias::String;
 use uk_content::{
     canonicalize,
     constants::Language,
     platform_prefixes,
     prelude::{Endian, Mergeable},
-    resource::{is_mergeable_sarc, ResourceData},
+    resource::{ArchiveExts, ResourceData, SarcMap},
 };
 use uk_util::PathExt as UkPathExt;
 use zip::{
@@ -32,20 +34,22 @@ use zip::{
 };
 
 use crate::{
+    unpack::ParallelZipReader,
     ExclusiveOptionGroup, Manifest, Meta, ModOption, ModOptionGroup, ModPlatform,
     MultipleOptionGroup, OptionGroup,
 };
 
 pub type ZipWriter = Arc<Mutex<ZipW<fs::File>>>;
 
-static NX_HASH_TABLE: LazyLock<StockHashTable> =
+pub(crate) static NX_HASH_TABLE: LazyLock<StockHashTable> =
     LazyLock::new(|| StockHashTable::new(&botw_utils::hashes::Platform::Switch));
-static WIIU_HASH_TABLE: LazyLock<StockHashTable> =
+pub(crate) static WIIU_HASH_TABLE: LazyLock<StockHashTable> =
     LazyLock::new(|| StockHashTable::new(&botw_utils::hashes::Platform::WiiU));
 
 pub struct ModPacker {
     source_dir: PathBuf,
     current_root: PathBuf,
+    current_zip_root: PathBuf,
     meta: Meta,
     zip: ZipWriter,
     endian: Endian,
@@ -53,6 +57,10 @@ pub struct ModPacker {
     masters: Vec<Arc<uk_reader::ResourceReader>>,
     hash_table: &'static StockHashTable,
     compressor: Arc<Mutex<zstd::bulk::Compressor<'static>>>,
+    /// Digest of every file written, for the content hash in the meta
+    hashes: Mutex<BTreeMap<std::string::String, [u8; 32]>>,
+    /// Process files in order and sort the ZIP once it is written
+    deterministic: bool,
     _zip_opts: SimpleFileOptions,
     _out_file: PathBuf,
 }
@@ -62,6 +70,7 @@ impl std::fmt::Debug for ModPacker {
         f.debug_struct("ModBuilder")
             .field("source_dir", &self.source_dir)
             .field("current_root", &self.current_root)
+            .field("current_zip_root", &self.current_zip_root)
             .field("meta", &self.meta)
             .field("endian", &self.endian)
             .field("masters", &self.masters)
@@ -162,12 +171,132 @@ impl From<BnpGroup> for ExclusiveOptionGroup {
     }
 }
 
+/// A single `[Preset]` section from a Cemu graphic pack `rules.txt`.
+#[derive(Debug, Default, PartialEq)]
+struct RulesPreset {
+    category: Option<String>,
+    name:     String,
+    default:  bool,
+}
+
+/// Collects the `[Preset]` sections from a `rules.txt`. These can't be read
+/// through the INI parser, since the section name repeats for every preset.
+fn parse_rules_presets(text: &str) -> Vec<RulesPreset> {
+    let mut presets = vec![];
+    let mut current: Option<RulesPreset> = None;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') {
+            presets.extend(current.take().filter(|p| !p.name.is_empty()));
+            if line.eq_ignore_ascii_case("[preset]") {
+                current = Some(RulesPreset::default());
+            }
+            continue;
+        }
+        let Some(preset) = current.as_mut() else {
+            continue;
+        };
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim().trim_matches('"');
+            match key.trim().to_lowercase().as_str() {
+                "category" => preset.category = Some(value.into()),
+                "name" => preset.name = value.into(),
+                "default" => preset.default = matches!(value, "1" | "true"),
+                _ => (),
+            }
+        }
+    }
+    presets.extend(current.filter(|p| !p.name.is_empty()));
+    presets
+}
+
+/// Maps each preset category onto an exclusive option group. Presets only
+/// become options when they have their own folder of game files, found at
+/// either `<category>/<name>` or `<name>` in the graphic pack root.
+fn options_from_presets(root: &Path, presets: Vec<RulesPreset>) -> Vec<OptionGroup> {
+    let has_files = |folder: &Path| {
+        let (content, aoc) = platform_prefixes(Endian::Big);
+        root.join(folder).join(content).exists() || root.join(folder).join(aoc).exists()
+    };
+    let mut categories: indexmap::IndexMap<Option<String>, Vec<RulesPreset>> = Default::default();
+    for preset in presets {
+        categories
+            .entry(preset.category.clone())
+            .or_default()
+            .push(preset);
+    }
+    categories
+        .into_iter()
+        .filter_map(|(category, presets)| {
+            let mut required = true;
+            let mut default = None;
+            let mut options = vec![];
+            for preset in presets {
+                let folder = category
+                    .as_ref()
+                    .map(|cat| Path::new(cat.as_str()).join(preset.name.as_str()))
+                    .filter(|folder| has_files(folder))
+                    .or_else(|| {
+                        let folder = PathBuf::from(preset.name.as_str());
+                        has_files(&folder).then_some(folder)
+                    });
+                match folder {
+                    Some(folder) => {
+                        if preset.default {
+                            default = Some(folder.clone());
+                        }
+                        options.push(ModOption {
+                            name: preset.name,
+                            description: Default::default(),
+                            path: folder,
+                            requires: vec![],
+                        });
+                    }
+                    None => required = false,
+                }
+            }
+            (!options.is_empty()).then(|| {
+                OptionGroup::Exclusive(ExclusiveOptionGroup {
+                    name: category.unwrap_or_else(|| "Presets".into()),
+                    description: "Converted from graphic pack presets".into(),
+                    required,
+                    default: default.or_else(|| required.then(|| options[0].path.clone())),
+                    options,
+                })
+            })
+        })
+        .collect()
+}
+
+/// Applies the changes stored in a packaged master to the resource from the
+/// masters before it. Binary files replace the base outright.
+fn apply_package_master(base: &ResourceData, diff: &Arc<ResourceData>) -> Arc<ResourceData> {
+    match (base, diff.as_ref()) {
+        (ResourceData::Mergeable(base), ResourceData::Mergeable(diff)) => {
+            Arc::new(ResourceData::Mergeable(base.merge(diff)))
+        }
+        (ResourceData::Sarc(base), ResourceData::Sarc(diff)) => {
+            Arc::new(ResourceData::Sarc(base.merge(diff)))
+        }
+        (ResourceData::Binary(base), ResourceData::Sarc(diff)) => {
+            match SarcMap::from_binary(decompress_if(base)) {
+                Ok(base) => Arc::new(ResourceData::Sarc(base.merge(diff))),
+                Err(_) => diff.clone(),
+            }
+        }
+        _ => diff.clone(),
+    }
+}
+
 impl ModPacker {
     pub fn parse_rules(path: PathBuf) -> Result<Meta> {
         use configparser::ini::Ini;
         let mut rules = Ini::new();
         let parent = path.parent().context("No parent path???")?;
-        rules.load(&path).map_err(|e| anyhow_ext::anyhow!(e))?;
+        let text = fs::read_to_string(&path)?;
+        rules.read(text.clone()).map_err(|e| anyhow_ext::anyhow!(e))?;
         Ok(Meta {
             api: env!("CARGO_PKG_VERSION").into(),
             name: rules
@@ -183,7 +312,9 @@ impl ModPacker {
             category: crate::ModCategory::Other,
             author: Default::default(),
             masters: Default::default(),
-            options: vec![],
+            content_hash: None,
+            archives: Default::default(),
+            options: options_from_presets(parent, parse_rules_presets(&text)),
             platform: if parent.join("content").exists() || parent.join("aoc").exists() {
                 ModPlatform::Specific(Endian::Big)
             } else {
@@ -203,6 +334,8 @@ impl ModPacker {
             category: crate::ModCategory::Other,
             author: Default::default(),
             masters: Default::default(),
+            content_hash: None,
+            archives: Default::default(),
             options: (!info.options.multi.is_empty())
                 .then(|| multi_from_bnp_multi(info.options.multi))
                 .into_iter()
@@ -282,6 +415,7 @@ impl ModPacker {
             let zip = Arc::new(Mutex::new(ZipW::new(fs::File::create(&dest_file)?)));
             Ok(ModPacker {
                 current_root: source_dir.clone(),
+                current_zip_root: PathBuf::new(),
                 source_dir,
                 endian,
                 zip,
@@ -295,6 +429,8 @@ impl ModPacker {
                 compressor: Arc::new(Mutex::new(
                     zstd::bulk::Compressor::with_dictionary(8, super::DICTIONARY)?,
                 )),
+                hashes: Default::default(),
+                deterministic: false,
                 _zip_opts: FileOptions::default()
                     .compression_method(zip::CompressionMethod::Stored),
                 _out_file: dest_file,
@@ -303,31 +439,65 @@ impl ModPacker {
         inner(source.as_ref(), dest.as_ref(), meta, masters)
     }
 
+    /// Makes the package reproducible: source files are processed one at a
+    /// time in sorted order, and the finished ZIP is rewritten in sorted order
+    /// with fixed timestamps, so the same source always gives an identical ZIP.
+    pub fn deterministic(mut self) -> Self {
+        self.deterministic = true;
+        self._zip_opts = self
+            ._zip_opts
+            .last_modified_time(zip::DateTime::default())
+            .unix_permissions(0o644);
+        self
+    }
+
+    /// Adds archive extensions to merge or replace whole, usually from the
+    /// global settings. These are saved in the package meta along with the
+    /// mod's own.
+    pub fn with_archives(mut self, archives: &ArchiveExts) -> Self {
+        self.meta.archives.extend(archives);
+        self
+    }
+
+    /// Adds a file to the ZIP. If the file was already written, the first
+    /// copy is kept.
+    fn write_entry(&self, path: std::string::String, data: Vec<u8>) -> zip::result::ZipResult<()> {
+        let digest: [u8; 32] = Sha256::digest(&data).into();
+        {
+            let mut zip = self.zip.lock();
+            zip.start_file(path.as_str(), self._zip_opts)?;
+            zip.write_all(&data)?;
+        }
+        self.hashes.lock().insert(path, digest);
+        Ok(())
+    }
+
+    /// Hash of every file in the package except the meta, independent of the
+    /// order they were written in.
+    fn content_hash(&self) -> std::string::String {
+        hash_digests(self.hashes.lock().iter())
+    }
+
     fn write_resource(&self, canon: &str, resource: &ResourceData) -> Result<()> {
         let data = minicbor_ser::to_vec(&resource)
             .map_err(|e| anyhow::format_err!("{:?}", e))
             .with_context(|| jstr!("Failed to serialize {canon}"))?;
-        let zip_path = self
-            .current_root
-            .strip_prefix(&self.source_dir)?
-            .join(canon);
-        {
-            log::trace!("Writing {} to ZIP", canon);
-            let mut zip = self.zip.lock();
-            match zip.start_file(zip_path.to_slash_lossy(), self._zip_opts) {
-                Ok(_) => zip.write_all(&self.compressor.lock().compress(&data)?)?,
-                Err(zip::result::ZipError::InvalidArchive("Duplicate filename")) => {
-                    log::warn!("Attempted to duplicate resource {}, skipping", canon);
-                }
-                e => return Err(e.unwrap_err().into()),
+        let zip_path = self.current_zip_root.join(canon);
+        log::trace!("Writing {} to ZIP", canon);
+        let data = self.compressor.lock().compress(&data)?;
+        match self.write_entry(zip_path.to_slash_lossy().into(), data) {
+            Ok(_) => (),
+            Err(zip::result::ZipError::InvalidArchive("Duplicate filename")) => {
+                log::warn!("Attempted to duplicate resource {}, skipping", canon);
             }
+            Err(e) => return Err(e.into()),
         }
         self.built_resources.insert(canon.into());
         Ok(())
     }
 
     fn collect_resources(&self, root: PathBuf) -> Result<BTreeSet<String>> {
-        let files = WalkDir::new(&root)
+        let mut files = WalkDir::new(&root)
             .into_iter()
             .filter_map(|f| {
                 f.ok()
@@ -337,92 +507,117 @@ impl ModPacker {
         let total_files = files.len();
         let current_file = AtomicUsize::new(0);
         log::debug!("Resources found in root {}:\n{:#?}", root.display(), &files);
-        Ok(files
-            .into_par_iter()
-            .map(|path| -> Result<Option<String>> {
-                log::trace!("Processing resource at {}", path.display());
-                let name: String = path
-                    .strip_prefix(&self.current_root)
-                    .unwrap()
-                    .to_slash_lossy()
-                    .into();
-                // We know this is sound because we got `path` by iterating the contents of `root`.
-                let canon = canonicalize(name.as_str());
-                let file_data = fs::read(&path)?;
-                let file_data = decompress_if(&file_data);
-
-                if path
-                    .file_name()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s == "AocMainField.pack")
-                    .unwrap_or(false)
-                    && file_data.is_empty()
-                {
-                    self.write_resource(
-                        "Aoc/0010/Pack/AocMainField.pack",
-                        &ResourceData::Sarc(Default::default()),
-                    )?;
-                    return Ok(Some("Pack/AocMainField.pack".into()));
-                }
+        let process = |path: PathBuf| -> Result<Option<String>> {
+            let file = self.process_file(&root, &path)?;
 
-                if name.ends_with("sizetable") || file_data.len() < 4 {
-                    return Ok(None);
-                }
+            let progress = current_file.load(std::sync::atomic::Ordering::Relaxed) + 1;
+            current_file.store(progress, std::sync::atomic::Ordering::Relaxed);
+            let percent = (progress as f64 / total_files as f64) * 100.0;
+            let fract = percent.fract();
+            if fract <= 0.1 || fract >= 0.95 {
+                log::trace!(
+                    "PROGRESSBuilding {} files: {}%",
+                    total_files,
+                    percent as usize
+                );
+            }
 
-                if !self.hash_table.is_file_modded(&canon, &*file_data, true) {
-                    log::trace!("Resource {} not modded, ignoring", &canon);
-                    return Ok(None);
-                }
+            Ok(file)
+        };
+        // Processing in order means the same copy of a duplicated resource is
+        // always the one kept
+        let files = if self.deterministic {
+            files.sort();
+            files
+                .into_iter()
+                .map(process)
+                .collect::<Result<Vec<Option<_>>>>()?
+        } else {
+            files
+                .into_par_iter()
+                .map(process)
+                .collect::<Result<Vec<Option<_>>>>()?
+        };
+        Ok(files.into_iter().flatten().collect())
+    }
 
-                let resource = ResourceData::from_binary(name.as_str(), &*file_data)
-                    .with_context(|| jstr!("Failed to parse resource {&name}"))?;
-                let is_mergeable = matches!(resource, ResourceData::Mergeable(_));
-                if let ResourceData::Mergeable(
-                    uk_content::resource::MergeableResource::BinaryOverride(v),
-                ) = &resource
-                {
-                    log::error!(
-                        "There was an error processing {name}. It will not be processed but will \
-                         be stored as-is, overriding anything else. Error details:\n{}",
-                        v.1
-                    );
-                }
-                self.process_resource(name.clone(), canon.clone(), resource, false)
-                    .with_context(|| jstr!("Failed to process resource {&canon}"))?;
-                if !is_mergeable && is_mergeable_sarc(canon.as_str(), file_data.as_ref()) {
-                    log::trace!(
-                        "Resource {} is a mergeable SARC, processing contents",
-                        &canon
-                    );
-                    self.process_sarc(
-                        Sarc::new(file_data.as_ref())?,
-                        name.as_str().as_ref(),
-                        self.hash_table.is_file_new(&canon),
-                        canon.starts_with("Aoc"),
-                    )
-                    .with_context(|| jstr!("Failed to process SARC file {&canon}"))?;
-                }
+    /// Processes a single source file from the given content or DLC folder,
+    /// returning its manifest entry if it is part of the mod.
+    fn process_file(&self, root: &Path, path: &Path) -> Result<Option<String>> {
+        log::trace!("Processing resource at {}", path.display());
+        let name: String = path
+            .strip_prefix(&self.current_root)
+            .unwrap()
+            .to_slash_lossy()
+            .into();
+        // Callers only pass files inside `root`, which is inside the current root.
+        let canon = canonicalize(name.as_str());
+        let file_data = fs::read(path)?;
+        let file_data = decompress_if(&file_data);
 
-                let progress = current_file.load(std::sync::atomic::Ordering::Relaxed) + 1;
-                current_file.store(progress, std::sync::atomic::Ordering::Relaxed);
-                let percent = (progress as f64 / total_files as f64) * 100.0;
-                let fract = percent.fract();
-                if fract <= 0.1 || fract >= 0.95 {
-                    log::trace!(
-                        "PROGRESSBuilding {} files: {}%",
-                        total_files,
-                        percent as usize
-                    );
-                }
+        if path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .map(|s| s == "AocMainField.pack")
+            .unwrap_or(false)
+            && file_data.is_empty()
+        {
+            self.write_resource(
+                "Aoc/0010/Pack/AocMainField.pack",
+                &ResourceData::Sarc(Default::default()),
+            )?;
+            return Ok(Some("Pack/AocMainField.pack".into()));
+        }
 
-                Ok(Some(
-                    path.strip_prefix(&root).unwrap().to_slash_lossy().into(),
-                ))
-            })
-            .collect::<Result<Vec<Option<_>>>>()?
-            .into_par_iter()
-            .filter_map(|x| x)
-            .collect())
+        if name.ends_with("sizetable") || file_data.len() < 4 {
+            return Ok(None);
+        }
+
+        if !self.hash_table.is_file_modded(&canon, &*file_data, true) {
+            log::trace!("Resource {} not modded, ignoring", &canon);
+            return Ok(None);
+        }
+
+        let resource = ResourceData::from_binary_with_archives(
+            name.as_str(),
+            &*file_data,
+            &self.meta.archives,
+        )
+        .with_context(|| jstr!("Failed to parse resource {&name}"))?;
+        let is_mergeable = matches!(resource, ResourceData::Mergeable(_));
+        if let ResourceData::Mergeable(uk_content::resource::MergeableResource::BinaryOverride(v)) =
+            &resource
+        {
+            log::error!(
+                "There was an error processing {name}. It will not be processed but will be \
+                 stored as-is, overriding anything else. Error details:\n{}",
+                v.1
+            );
+        }
+        self.process_resource(name.clone(), canon.clone(), resource, false)
+            .with_context(|| jstr!("Failed to process resource {&canon}"))?;
+        if !is_mergeable
+            && self
+                .meta
+                .archives
+                .is_mergeable_sarc(canon.as_str(), file_data.as_ref())
+        {
+            log::trace!(
+                "Resource {} is a mergeable SARC, processing contents",
+                &canon
+            );
+            self.process_sarc(
+                Sarc::new(file_data.as_ref())?,
+                name.as_str().as_ref(),
+                self.hash_table.is_file_new(&canon),
+                canon.starts_with("Aoc"),
+            )
+            .with_context(|| jstr!("Failed to process SARC file {&canon}"))?;
+        }
+
+        Ok(Some(
+            path.strip_prefix(root).unwrap().to_slash_lossy().into(),
+        ))
     }
 
     fn process_resource(
@@ -454,12 +649,13 @@ impl ModPacker {
             .trim_start_matches(prefixes.0)
             .trim_start_matches(prefixes.1)
             .trim_start_matches('/');
+        // Packaged masters only hold their changes, so they are applied on
+        // top of the earlier masters instead of replacing them
         let reference = self
             .masters
             .iter()
-            .rev()
-            .find_map(|master| {
-                master
+            .fold(None::<Arc<ResourceData>>, |base, master| {
+                let data = master
                     .get_data(name.as_str())
                     .or_else(|err| {
                         log::trace!("{err}");
@@ -477,7 +673,13 @@ impl ModPacker {
                             Err(err)
                         }
                     })
-                    .ok()
+                    .ok();
+                match (base, data) {
+                    (Some(base), Some(data)) if master.is_mod_package() => {
+                        Some(apply_package_master(&base, &data))
+                    }
+                    (base, data) => data.or(base),
+                }
             });
         log::trace!("Resource {} has a master: {}", &canon, reference.is_some());
         if let (Some(res), Some(ref_res)) = (
@@ -492,9 +694,21 @@ impl ModPacker {
             resource = ResourceData::Mergeable(ref_res.diff(res));
         } else if let (Some(sarc), Some(ref_sarc)) = (
             resource.as_sarc(),
-            reference.as_ref().and_then(|rrd| rrd.as_sarc()),
+            reference.as_ref().and_then(|rrd| {
+                match &**rrd {
+                    ResourceData::Sarc(ref_sarc) => Some(Cow::Borrowed(ref_sarc)),
+                    // Archives only merged through the archive settings are
+                    // read as binary from the masters
+                    ResourceData::Binary(data) => {
+                        SarcMap::from_binary(decompress_if(data))
+                            .ok()
+                            .map(Cow::Owned)
+                    }
+                    _ => None,
+                }
+            }),
         ) {
-            if ref_sarc == sarc && !in_new_sarc {
+            if *ref_sarc == *sarc && !in_new_sarc {
                 log::trace!("{} not modded, skipping", &canon);
                 return Ok(());
             }
@@ -535,9 +749,14 @@ impl ModPacker {
                 continue;
             }
 
-            let resource = ResourceData::from_binary(&name, &*file_data).with_context(|| {
-                jstr!("Failed to parse resource {&canon} in SARC {&path.display().to_string()}")
-            })?;
+            let resource =
+                ResourceData::from_binary_with_archives(&name, &*file_data, &self.meta.archives)
+                    .with_context(|| {
+                        jstr!(
+                            "Failed to parse resource {&canon} in SARC \
+                             {&path.display().to_string()}"
+                        )
+                    })?;
             if let ResourceData::Mergeable(
                 uk_content::resource::MergeableResource::BinaryOverride(v),
             ) = &resource
@@ -549,7 +768,11 @@ impl ModPacker {
                 );
             }
             self.process_resource((&name).into(), canon.clone(), resource, is_new_sarc)?;
-            if is_mergeable_sarc(canon.as_str(), file_data.as_ref()) {
+            if self
+                .meta
+                .archives
+                .is_mergeable_sarc(canon.as_str(), file_data.as_ref())
+            {
                 log::trace!(
                     "Resource {} in SARC {} is a mergeable SARC, processing contents",
                     &canon,
@@ -626,25 +849,35 @@ impl ModPacker {
             }
             let manifest = serde_yaml::to_string(&manifest)?;
             log::info!("Writing manifest");
-            let mut zip = self_.zip.lock();
-            zip.start_file(
-                root.strip_prefix(&self_.source_dir)
-                    .unwrap()
+            self_.write_entry(
+                self_
+                    .current_zip_root
                     .join("manifest.yml")
-                    .to_slash_lossy(),
-                self_._zip_opts,
+                    .to_slash_lossy()
+                    .into(),
+                manifest.into_bytes(),
             )?;
-            zip.write_all(manifest.as_bytes())?;
             Ok(())
         }
         inner(self, root.as_ref())
     }
 
-    fn collect_roots(&self) -> Vec<PathBuf> {
+    /// Returns the source folder and ZIP folder for each option. Options
+    /// normally live under `options`, but graphic packs converted from
+    /// `rules.txt` presets keep them directly in the source folder.
+    fn collect_roots(&self) -> Vec<(PathBuf, PathBuf)> {
         let opt_root = self.source_dir.join("options");
         let mut roots = HashSet::new();
         for group in &self.meta.options {
-            roots.extend(group.options().iter().map(|opt| opt_root.join(&opt.path)))
+            roots.extend(group.options().iter().map(|opt| {
+                let zip_root = Path::new("options").join(&opt.path);
+                let root = opt_root.join(&opt.path);
+                if !root.exists() && self.source_dir.join(&opt.path).exists() {
+                    (self.source_dir.join(&opt.path), zip_root)
+                } else {
+                    (root, zip_root)
+                }
+            }))
         }
         log::debug!("Detected options:\n{:#?}", &roots);
         roots.into_iter().collect()
@@ -655,9 +888,7 @@ impl ModPacker {
             for ext in ["jpg", "jpeg", "png", "svg"] {
                 let path = self.source_dir.join(name).with_extension(ext);
                 if path.exists() {
-                    let mut zip = self.zip.lock();
-                    zip.start_file(format!("thumb.{}", ext), self._zip_opts)?;
-                    zip.write_all(&fs::read(path)?)?;
+                    self.write_entry(format!("thumb.{}", ext), fs::read(path)?)?;
                     return Ok(());
                 }
             }
@@ -673,15 +904,17 @@ impl ModPacker {
                 self.meta.name
             )
         })?;
-        if self.source_dir.join("options").exists() {
+        let roots = self.collect_roots();
+        if !roots.is_empty() {
             log::debug!("Mod contains options");
             self.masters
                 .push(Arc::new(uk_reader::ResourceReader::from_unpacked_mod(
                     &self.source_dir,
                 )?));
             log::info!("Collecting resources for options");
-            for root in self.collect_roots() {
+            for (root, zip_root) in roots {
                 self.current_root.clone_from(&root);
+                self.current_zip_root = zip_root;
                 self.pack_root(root).with_context(|| {
                     format!(
                         "Failed to package mod root at {} for mod {}",
@@ -692,8 +925,161 @@ impl ModPacker {
             }
         }
         self.pack_thumbnail()?;
+        self.finish()
+    }
+
+    /// Repackages only the given changed source files, copying everything
+    /// else from `previous`, an earlier build of the same source. Changed
+    /// files which no longer exist are dropped from the package. Returns the
+    /// new package along with a manifest of the files that changed.
+    pub fn pack_changed(
+        mut self,
+        previous: &Path,
+        changed: &[PathBuf],
+    ) -> Result<(PathBuf, Manifest)> {
+        let old = ParallelZipReader::open(previous, false)
+            .with_context(|| format!("Failed to open previous build at {}", previous.display()))?;
+        let (content, aoc) = platform_prefixes(self.endian);
+        let mut roots = vec![(self.source_dir.clone(), PathBuf::new())];
+        roots.extend(self.collect_roots());
+        if roots.len() > 1 {
+            self.masters
+                .push(Arc::new(uk_reader::ResourceReader::from_unpacked_mod(
+                    &self.source_dir,
+                )?));
+        }
+        let mut dirty = Manifest::default();
+        let mut stale = HashSet::new();
+        for (root, zip_root) in roots {
+            let manifest_path = zip_root.join("manifest.yml");
+            let mut manifest: Manifest = match old.get_file(&manifest_path) {
+                Ok(data) => serde_yaml::from_slice(&data)?,
+                Err(_) => Default::default(),
+            };
+            self.current_root.clone_from(&root);
+            self.current_zip_root.clone_from(&zip_root);
+            self.built_resources.clear();
+            for file in changed {
+                let Ok(rel) = file.strip_prefix(&root) else {
+                    continue;
+                };
+                let (dir, files, dirty_files) = if rel.starts_with(content) {
+                    (
+                        root.join(content),
+                        &mut manifest.content_files,
+                        &mut dirty.content_files,
+                    )
+                } else if rel.starts_with(aoc) {
+                    (
+                        root.join(aoc),
+                        &mut manifest.aoc_files,
+                        &mut dirty.aoc_files,
+                    )
+                } else {
+                    continue;
+                };
+                log::debug!("Repackaging changed file {}", file.display());
+                let name: String = file.strip_prefix(&dir).unwrap().to_slash_lossy().into();
+                let canon = canonicalize(rel);
+                stale.insert(zip_root.join(canon.as_str()).to_slash_lossy().into_owned());
+                stale.extend(self.nested_entries(&old, &zip_root, &canon));
+                files.remove(&name);
+                dirty_files.insert(name);
+                if file.is_file() {
+                    if let Some(name) = self
+                        .process_file(&dir, file)
+                        .with_context(|| format!("Failed to process {}", file.display()))?
+                    {
+                        files.insert(name);
+                    }
+                }
+            }
+            self.write_entry(
+                manifest_path.to_slash_lossy().into(),
+                serde_yaml::to_string(&manifest)?.into_bytes(),
+            )?;
+        }
+        self.pack_thumbnail()?;
+        let written: HashSet<std::string::String> = self.hashes.lock().keys().cloned().collect();
+        for path in old.iter() {
+            let name = path.to_slash_lossy();
+            if name == "meta.yml" || written.contains(&*name) || stale.contains(&*name) {
+                continue;
+            }
+            self.write_entry(name.into_owned(), old.get_file(path)?)?;
+        }
+        Ok((self.finish()?, dirty))
+    }
+
+    /// The ZIP entries of the files nested in an archive, as it is in the
+    /// game files or the previous build. Stock files come from the masters,
+    /// and files the mod added are listed in the stored archive.
+    fn nested_entries(
+        &self,
+        old: &ParallelZipReader,
+        zip_root: &Path,
+        canon: &str,
+    ) -> HashSet<std::string::String> {
+        let is_aoc = canon.starts_with("Aoc/0010/");
+        let mut entries = HashSet::new();
+        let mut archives = vec![String::from(canon)];
+        while let Some(archive) = archives.pop() {
+            let mut names = self
+                .masters
+                .iter()
+                .filter(|master| !master.is_mod_package())
+                .filter_map(|master| master.get_bytes_uncached(archive.as_str()).ok())
+                .filter_map(|data| SarcMap::from_binary(decompress_if(&data)).ok())
+                .flat_map(|sarc| sarc.files)
+                .collect::<BTreeSet<String>>();
+            if let Some(ResourceData::Sarc(sarc)) =
+                read_stored(old, &zip_root.join(archive.as_str()))
+            {
+                names.extend(sarc.files);
+            }
+            for name in names {
+                let name = if is_aoc {
+                    jstr!("Aoc/0010/{&name}")
+                } else {
+                    name.to_string()
+                };
+                let nested = canonicalize(&name);
+                let entry = zip_root.join(nested.as_str()).to_slash_lossy().into_owned();
+                let is_archive = Path::new(&name)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| botw_utils::extensions::SARC_EXTS.contains(&ext));
+                if entries.insert(entry) && is_archive {
+                    archives.push(nested);
+                }
+            }
+        }
+        entries
+    }
+
+    /// Writes the meta last and closes the ZIP.
+    fn finish(mut self) -> Result<PathBuf> {
+        self.meta.content_hash = Some(self.content_hash());
         match Arc::try_unwrap(self.zip).map(|z| z.into_inner()) {
             Ok(mut zip) => {
+                if self.deterministic {
+                    log::info!("Writing files in sorted order");
+                    zip.finish()?;
+                    let unsorted = self._out_file.with_extension("unsorted");
+                    fs::rename(&self._out_file, &unsorted)?;
+                    let mut archive = zip::ZipArchive::new(fs::File::open(&unsorted)?)?;
+                    let mut names = archive
+                        .file_names()
+                        .map(|n| n.to_owned())
+                        .collect::<Vec<_>>();
+                    names.sort();
+                    zip = ZipW::new(fs::File::create(&self._out_file)?);
+                    for name in names {
+                        zip.raw_copy_file(archive.by_name(&name)?)?;
+                    }
+                    drop(archive);
+                    fs::remove_file(unsorted)?;
+                }
                 log::info!("Writing meta");
                 zip.start_file("meta.yml", self._zip_opts)?;
                 zip.write_all(serde_yaml::to_string(&self.meta)?.as_bytes())?;
@@ -708,6 +1094,46 @@ impl ModPacker {
     }
 }
 
+fn hash_digests<'a>(
+    digests: impl Iterator<Item = (&'a std::string::String, &'a [u8; 32])>,
+) -> std::string::String {
+    let mut hasher = Sha256::new();
+    for (path, digest) in digests {
+        hasher.update(path.as_bytes());
+        hasher.update([0]);
+        hasher.update(digest);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Computes the content hash of a packaged mod, for packages built before it
+/// was stored in the meta
+pub fn package_hash(path: &Path) -> Result<std::string::String> {
+    let mut zip = zip::ZipArchive::new(std::io::BufReader::new(fs::File::open(path)?))?;
+    let mut digests = BTreeMap::new();
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i)?;
+        if file.is_dir() || file.name() == "meta.yml" {
+            continue;
+        }
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher)?;
+        digests.insert(file.name().to_owned(), hasher.finalize().into());
+    }
+    Ok(hash_digests(digests.iter()))
+}
+
+/// Decodes a resource stored in a built package
+fn read_stored(zip: &ParallelZipReader, entry: &Path) -> Option<ResourceData> {
+    let data = zip.get_file(entry).ok()?;
+    let size = zstd::bulk::Decompressor::upper_bound(&data).unwrap_or(data.len() * 1024);
+    let data = zstd::bulk::Decompressor::with_dictionary(super::DICTIONARY)
+        .ok()?
+        .decompress(&data, size)
+        .ok()?;
+    minicbor_ser::from_slice(&data).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use indexmap::IndexMap;
@@ -736,6 +1162,8 @@ mod tests {
                 author: "Lord Caleb".into(),
                 description: "A test mod".into(),
                 masters: IndexMap::default(),
+                content_hash: None,
+                archives: Default::default(),
                 url: None,
                 options: vec![OptionGroup::Multiple(MultipleOptionGroup {
                     name: "Test Option Group".into(),
@@ -757,4 +1185,141 @@ mod tests {
         .unwrap();
         builder.pack().unwrap();
     }
+
+    fn dev_meta() -> Meta {
+        Meta {
+            api: env!("CARGO_PKG_VERSION").into(),
+            platform: ModPlatform::Specific(Endian::Little),
+            name: "Dev Test".into(),
+            version: "0.1.0".into(),
+            category: crate::ModCategory::Other,
+            author: "Test".into(),
+            description: "".into(),
+            masters: IndexMap::default(),
+            content_hash: None,
+            archives: Default::default(),
+            url: None,
+            options: vec![],
+        }
+    }
+
+    #[test]
+    fn pack_changed_nested() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        let pack_dir = source.join("01007EF00011E000/romfs/Actor/Pack");
+        fs::create_dir_all(&pack_dir).unwrap();
+        let pack = pack_dir.join("Dev_Test.sbactorpack");
+        let write_pack = |files: &[(&str, &[u8])]| {
+            let mut writer = roead::sarc::SarcWriter::new(Endian::Little.into());
+            for (name, data) in files {
+                writer.add_file(*name, *data);
+            }
+            fs::write(&pack, writer.to_binary()).unwrap();
+        };
+        let meta = dev_meta();
+        write_pack(&[
+            ("Actor/Dev/A.txt", b"edited".as_slice()),
+            ("Actor/Dev/B.txt", b"added".as_slice()),
+        ]);
+        let first = ModPacker::new(
+            &source,
+            dir.path().join("first.zip"),
+            Some(meta.clone()),
+            vec![],
+        )
+        .unwrap()
+        .pack()
+        .unwrap();
+        // Removing B from the pack should drop it from the dev build too
+        write_pack(&[("Actor/Dev/A.txt", b"edited again".as_slice())]);
+        let (second, _) =
+            ModPacker::new(&source, dir.path().join("second.zip"), Some(meta), vec![])
+                .unwrap()
+                .pack_changed(&first, &[pack.clone()])
+                .unwrap();
+        let zip = ParallelZipReader::open(&second, false).unwrap();
+        let names = zip
+            .iter()
+            .map(|path| path.to_slash_lossy().into_owned())
+            .collect::<HashSet<_>>();
+        assert!(names.contains("Actor/Dev/A.txt"));
+        assert!(!names.contains("Actor/Dev/B.txt"));
+    }
+
+    #[test]
+    fn deterministic_pack() {
+        let dir = tempfile::tempdir().unwrap();
+        let romfs = dir.path().join("source/01007EF00011E000/romfs");
+        fs::create_dir_all(romfs.join("Actor/Pack")).unwrap();
+        fs::create_dir_all(romfs.join("System")).unwrap();
+        let mut writer = roead::sarc::SarcWriter::new(Endian::Little.into());
+        for i in 0..16u8 {
+            writer.add_file(format!("Actor/Dev/{i}.txt"), vec![i; 0x20]);
+        }
+        let actor_pack = romfs.join("Actor/Pack/Dev_Test.sbactorpack");
+        fs::write(actor_pack, writer.to_binary()).unwrap();
+        let byml = roead::byml::Byml::Map(
+            (0..16)
+                .map(|i| (format!("Key{i}").into(), roead::byml::Byml::I32(i)))
+                .collect(),
+        );
+        let byml_path = romfs.join("System/Dev.byml");
+        fs::write(byml_path, byml.to_binary(Endian::Little)).unwrap();
+        let build = |name: &str| {
+            let path = ModPacker::new(
+                dir.path().join("source"),
+                dir.path().join(name),
+                Some(dev_meta()),
+                vec![],
+            )
+            .unwrap()
+            .deterministic()
+            .pack()
+            .unwrap();
+            fs::read(path).unwrap()
+        };
+        assert_eq!(build("first.zip"), build("second.zip"));
+    }
+
+    #[test]
+    fn rules_presets() {
+        let presets = parse_rules_presets(
+            r#"[Definition]
+titleIds = 00050000101C9300,00050000101C9400,00050000101C9500
+name = "Test Pack"
+version = 7
+
+[Preset]
+category = "Difficulty"
+name = "Normal"
+default = 1
+
+[Preset]
+category = "Difficulty"
+name = "Hard"
+
+# Trailing comment
+[Preset]
+name = "Ungrouped"
+"#,
+        );
+        assert_eq!(presets, vec![
+            RulesPreset {
+                category: Some("Difficulty".into()),
+                name:     "Normal".into(),
+                default:  true,
+            },
+            RulesPreset {
+                category: Some("Difficulty".into()),
+                name:     "Hard".into(),
+                default:  false,
+            },
+            RulesPreset {
+                category: None,
+                name:     "Ungrouped".into(),
+                default:  false,
+            },
+        ]);
+    }
 }
@@ -1,6 +1,6 @@
 mod de;
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeMap, BTreeSet},
     io::{BufReader, Read, Write},
     ops::Deref,
     path::{Path, PathBuf},
@@ -23,23 +23,34 @@ use parking_lot::Mutex;
 use path_slash::PathExt;
 use rayon::prelude::*;
 use roead::{
-    sarc::SarcWriter,
-    yaz0::{compress, compress_if},
+    byml::Map,
+    sarc::{Sarc, SarcWriter},
+    yaz0::{compress, compress_if, decompress_if},
 };
 use serde::Serialize;
 use smartstring::alias::String;
 use uk_content::{
+    actor::Actor,
     canonicalize,
+    conflict::{
+        apply_resolutions, ConflictReport, ConflictTracker, FieldConflict, MergeWarning,
+        Resolution,
+    },
     constants::Language,
+    map::{
+        compound::StaticCompound,
+        unit::{MapUnit, UnitIds},
+    },
     platform_content, platform_prefixes,
     prelude::{Endian, Mergeable, Resource},
-    resource::{MergeableResource, ResourceData, SarcMap},
-    util::{HashMap, IndexSet},
+    resource::{ActorInfo, MergeableResource, ResourceData, SarcMap},
+    terrain::is_tile_archive,
+    util::{HashMap, HashSet, IndexSet},
 };
 use uk_reader::{ResourceLoader, ResourceReader};
 use uk_util::PathExt as UkPathExt;
 
-use crate::{Manifest, Meta, ModOption};
+use crate::{Manifest, Meta, ModOption, ModOptionGroup, OptionConflict};
 
 pub enum ZipData {
     Owned(Vec<u8>),
@@ -426,6 +437,85 @@ impl ModReader {
         }
         Ok(versions)
     }
+
+    fn option_files(&self, opt: &ModOption) -> Vec<String> {
+        let root = Path::new("options").join(&opt.path);
+        let files: Vec<String> = match self.zip.as_ref() {
+            Some(zip) => {
+                zip.iter()
+                    .filter_map(|file| file.strip_prefix(&root).ok())
+                    .map(|file| file.to_slash_lossy().into())
+                    .collect()
+            }
+            None => {
+                let root = self.path.join(root);
+                WalkDir::new(&root)
+                    .into_iter()
+                    .filter_map(std::result::Result::ok)
+                    .filter(|e| e.file_type().is_file())
+                    .filter_map(|e| {
+                        e.path()
+                            .strip_prefix(&root)
+                            .ok()
+                            .map(|file| file.to_slash_lossy().into())
+                    })
+                    .collect()
+            }
+        };
+        files
+            .into_iter()
+            .filter(|file| file.as_str() != "manifest.yml")
+            .collect()
+    }
+
+    fn option_resource(&self, opt: &ModOption, file: &str) -> Result<ResourceData> {
+        let path = Path::new("options").join(&opt.path).join(file);
+        let data = match self.zip.as_ref() {
+            Some(zip) => self.decompress(&zip.get_file(path)?)?,
+            None => fs::read(self.path.join(path))?,
+        };
+        minicbor_ser::from_slice(&data)
+            .map_err(|e| anyhow::format_err!("{:?}", e))
+            .with_context(|| jstr!("Failed to parse {file} from option {&opt.name}"))
+    }
+
+    /// Finds resources which more than one of the mod's options replace
+    /// as unmergeable files. Options in the same exclusive group are
+    /// included, so callers should only consider the selected ones.
+    pub fn option_conflicts(&self) -> Result<Vec<OptionConflict>> {
+        let mut owners: BTreeMap<String, Vec<&ModOption>> = BTreeMap::new();
+        for opt in self.meta.options.iter().flat_map(|g| g.options().iter()) {
+            for file in self.option_files(opt) {
+                owners.entry(file).or_default().push(opt);
+            }
+        }
+        owners
+            .into_iter()
+            .filter(|(_, opts)| opts.len() > 1)
+            .filter_map(|(file, opts)| {
+                let binary = opts.iter().try_fold(false, |binary, opt| {
+                    self.option_resource(opt, &file).map(|res| {
+                        binary
+                            || matches!(
+                                res,
+                                ResourceData::Binary(_)
+                                    | ResourceData::Mergeable(MergeableResource::BinaryOverride(_))
+                            )
+                    })
+                });
+                match binary {
+                    Ok(true) => {
+                        Some(Ok(OptionConflict {
+                            file,
+                            options: opts.into_iter().map(|opt| opt.path.clone()).collect(),
+                        }))
+                    }
+                    Ok(false) => None,
+                    Err(e) => Some(Err(e)),
+                }
+            })
+            .collect()
+    }
 }
 
 static RSTB_EXCLUDE_EXTS: &[&str] = &[
@@ -433,16 +523,68 @@ static RSTB_EXCLUDE_EXTS: &[&str] = &[
 ];
 static RSTB_EXCLUDE_NAMES: &[&str] = &["ActorInfo.product.byml"];
 
+/// Checks for the SARC magic, whether or not the data is Yaz0 compressed
+fn is_sarc(data: &[u8]) -> bool {
+    [0, 0x11]
+        .into_iter()
+        .any(|pos| data.get(pos..pos + 4) == Some(b"SARC".as_slice()))
+}
+
+/// The static compounds holding collision for a map unit's objects: one per
+/// shrine, and four per main field section
+fn compound_paths(unit: &str) -> Vec<std::string::String> {
+    let Some(section) = Path::new(unit)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.rsplit_once('_'))
+        .map(|(section, _)| section)
+    else {
+        return vec![];
+    };
+    if unit.contains("CDungeon") {
+        vec![format!("Physics/StaticCompound/CDungeon/{section}.shksc")]
+    } else if unit.contains("MainField") {
+        (0..4)
+            .map(|i| format!("Physics/StaticCompound/MainField/{section}-{i}.shksc"))
+            .collect()
+    } else {
+        vec![]
+    }
+}
+
+/// The HashIds of every object in the map units of a pack
+fn unit_objects(sarc: &Sarc) -> Result<BTreeSet<u32>> {
+    Ok(sarc
+        .files()
+        .filter(|file| file.name.is_some_and(|n| n.ends_with("mubin")))
+        .map(|file| MapUnit::from_binary(decompress_if(file.data)))
+        .collect::<uk_content::Result<Vec<_>>>()?
+        .into_iter()
+        .flat_map(|unit| unit.objects.keys().copied().collect::<Vec<_>>())
+        .collect())
+}
+
+pub const ACTOR_INFO_PATH: &str = "Actor/ActorInfo.product.sbyml";
+
 // #[derive(Debug)]
 pub struct ModUnpacker {
-    dump:     Arc<ResourceReader>,
-    manifest: Option<Manifest>,
-    mods:     Vec<ModReader>,
-    endian:   Endian,
-    lang:     Language,
-    rstb:     DashMap<String, Option<u32>>,
-    hashes:   StockHashTable,
-    out_dir:  PathBuf,
+    dump:            Arc<ResourceReader>,
+    manifest:        Option<Manifest>,
+    mods:            Vec<ModReader>,
+    endian:          Endian,
+    lang:            Language,
+    rstb:            DashMap<String, Option<u32>>,
+    conflicts:       DashMap<String, Vec<FieldConflict>>,
+    /// Problems found checking merged resources, such as actor packs
+    warnings:        DashMap<String, Vec<MergeWarning>>,
+    resolutions:     HashMap<String, Vec<Resolution>>,
+    sync_actor_info: bool,
+    /// Derived actor info fields for each merged actor, by name
+    info_updates:    DashMap<String, Map>,
+    /// Modded archives, and whether they were merged or replaced whole
+    archives:        DashMap<String, bool>,
+    hashes:          StockHashTable,
+    out_dir:         PathBuf,
 }
 
 impl ModUnpacker {
@@ -460,6 +602,12 @@ impl ModUnpacker {
             lang,
             endian,
             rstb: DashMap::new(),
+            conflicts: DashMap::new(),
+            warnings: DashMap::new(),
+            resolutions: HashMap::default(),
+            sync_actor_info: false,
+            info_updates: DashMap::new(),
+            archives: DashMap::new(),
             hashes: StockHashTable::new(&match endian {
                 Endian::Little => botw_utils::hashes::Platform::Switch,
                 Endian::Big => botw_utils::hashes::Platform::WiiU,
@@ -473,7 +621,35 @@ impl ModUnpacker {
         self
     }
 
+    /// Sets chosen values for conflicting fields, which are applied over
+    /// mergeable resources after all mods are merged.
+    pub fn with_resolutions(mut self, resolutions: impl IntoIterator<Item = Resolution>) -> Self {
+        for resolution in resolutions {
+            self.resolutions
+                .entry(resolution.resource.clone())
+                .or_default()
+                .push(resolution);
+        }
+        self
+    }
+
+    /// Recomputes the actor info fields derived from each merged actor pack,
+    /// so the actor info matches the packs even if no mod updated it.
+    pub fn sync_actor_info(mut self, sync: bool) -> Self {
+        self.sync_actor_info = sync;
+        self
+    }
+
     pub fn unpack(self) -> Result<DashMap<String, Option<u32>>> {
+        self.unpack_with_report().map(|(rstb, _)| rstb)
+    }
+
+    /// Unpacks the mods like [`Self::unpack`], also returning the field
+    /// conflicts found in each mergeable resource that was rebuilt, and any
+    /// problems found checking merged actor packs. Checked resources without
+    /// any are included with none, so an older report can be updated from a
+    /// partial remerge.
+    pub fn unpack_with_report(self) -> Result<(DashMap<String, Option<u32>>, ConflictReport)> {
         if !self.out_dir.exists() {
             fs::create_dir_all(&self.out_dir)?;
         }
@@ -549,7 +725,79 @@ impl ModUnpacker {
             log::trace!("CLEARPROGRESS");
             Ok(())
         })?;
-        Ok(self.rstb)
+        self.update_actor_info()
+            .context("Failed to update actor info")?;
+        self.report_archives();
+        Ok((self.rstb, ConflictReport {
+            fields:   self.conflicts.into_iter().collect(),
+            warnings: self.warnings.into_iter().collect(),
+        }))
+    }
+
+    /// Logs which modded archives were merged file by file and which were
+    /// replaced whole
+    fn report_archives(&self) {
+        let mut merged = vec![];
+        let mut replaced = vec![];
+        for entry in self.archives.iter() {
+            match entry.value() {
+                true => merged.push(entry.key().clone()),
+                false => replaced.push(entry.key().clone()),
+            }
+        }
+        for (mut names, what) in [
+            (merged, "merged file by file"),
+            (replaced, "replaced whole"),
+        ] {
+            if !names.is_empty() {
+                names.sort();
+                log::info!(
+                    "{} modded archives were {what}:\n{}",
+                    names.len(),
+                    names.join("\n")
+                );
+            }
+        }
+    }
+
+    fn update_actor_info(&self) -> Result<()> {
+        if self.info_updates.is_empty() {
+            return Ok(());
+        }
+        log::info!("Updating actor info");
+        let out = self
+            .out_dir
+            .join(platform_content(self.endian))
+            .join(ACTOR_INFO_PATH);
+        let mut info = if out.exists() {
+            ActorInfo::from_binary(decompress_if(&fs::read(&out)?))?
+        } else {
+            let Some(MergeableResource::ActorInfo(info)) =
+                ResourceData::clone(self.dump.get_data(ACTOR_INFO_PATH)?.deref()).take_mergeable()
+            else {
+                bail!("Broken stock actor info");
+            };
+            *info
+        };
+        let mut updated = 0;
+        for entry in self.info_updates.iter() {
+            let (name, fields) = entry.pair();
+            match info
+                .0
+                .get_mut(roead::aamp::hash_name(name))
+                .and_then(|actor| actor.as_mut_map().ok())
+            {
+                Some(actor) => {
+                    actor.extend(fields.iter().map(|(k, v)| (k.clone(), v.clone())));
+                    updated += 1;
+                }
+                None => log::debug!("{name} has no actor info entry, skipping"),
+            }
+        }
+        out.parent().map(fs::create_dir_all).transpose()?;
+        fs::write(&out, compress(info.into_binary(self.endian)))?;
+        log::info!("Updated actor info for {updated} actors");
+        Ok(())
     }
 
     fn unpack_texts(&self, mut langs: IndexSet<Language>) -> Result<()> {
@@ -609,7 +857,7 @@ impl ModUnpacker {
         aoc: bool,
     ) -> Result<()> {
         files.into_par_iter().try_for_each(|file| -> Result<()> {
-            let data = self.build_file(file.as_str(), aoc)?;
+            let data = self.build_file(file.as_str(), aoc, None, 0)?;
             let out_file = dir.join(file.as_str());
             out_file.parent().map(fs::create_dir_all).transpose()?;
             let mut writer = std::io::BufWriter::new(fs::File::create(&out_file)?);
@@ -629,7 +877,16 @@ impl ModUnpacker {
         })
     }
 
-    fn build_file(&self, file: &str, aoc: bool) -> Result<Vec<u8>> {
+    /// Merges a file from every mod. Inside an archive a mod replaced whole,
+    /// `replaced` is the file's copy in that archive, and the first `skip`
+    /// mods, whose changes the replacement discards, are left out.
+    fn build_file(
+        &self,
+        file: &str,
+        aoc: bool,
+        replaced: Option<&[u8]>,
+        skip: usize,
+    ) -> Result<Vec<u8>> {
         let mut versions = std::collections::VecDeque::with_capacity(
             (self.mods.len() as f32 / 2.).ceil() as usize,
         );
@@ -651,18 +908,30 @@ impl ModUnpacker {
                 .and_then(|n| n.to_str())
                 .unwrap_or_default(),
         );
+        let mut sources = std::collections::VecDeque::with_capacity(versions.capacity());
         let mut dump_error: Vec<anyhow_ext::Error> = vec![];
-        let res_result = self.dump.get_data(&filepath);
+        let res_result: Result<Arc<ResourceData>> = match replaced {
+            Some(data) => {
+                ResourceData::from_binary(canon.as_str(), decompress_if(data).as_ref())
+                    .map(Arc::new)
+                    .map_err(Into::into)
+            }
+            None => self.dump.get_data(&filepath).map_err(Into::into),
+        };
         match res_result {
-            Ok(ref_res) => versions.push_back(ref_res),
+            Ok(ref_res) => {
+                versions.push_back(ref_res);
+                sources.push_back(None);
+            }
             Err(e) => {
                 log::trace!("{e}");
-                dump_error.push(e.into());
+                dump_error.push(e);
             }
         }
         for (data, mod_) in self
             .mods
             .iter()
+            .skip(skip)
             .filter_map(|mod_| {
                 mod_.get_versions(filepath.as_ref())
                     .ok()
@@ -672,7 +941,10 @@ impl ModUnpacker {
         {
             let res = minicbor_ser::from_slice(&data);
             match res {
-                Ok(res) => versions.push_back(Arc::new(res)),
+                Ok(res) => {
+                    versions.push_back(Arc::new(res));
+                    sources.push_back(Some(mod_.as_str()));
+                }
                 Err(e) => {
                     let msg = format!("{}", e);
                     if msg.contains("unknown variant") {
@@ -689,7 +961,7 @@ impl ModUnpacker {
                 }
             }
         }
-        let base_version = versions
+        let mut base_version = versions
             .pop_front()
             .with_context(|| {
                 let mut err = anyhow_ext::anyhow!(
@@ -703,7 +975,49 @@ impl ModUnpacker {
                 }
                 err
             })?;
+        sources.pop_front();
         let is_modded = !versions.is_empty() || self.hashes.is_file_new(&canon);
+        let mut base_archive = None;
+        let mut archive_skip = skip;
+        if base_version.as_sarc().is_some() || versions.iter().any(|v| v.as_sarc().is_some()) {
+            // A mod replacing the archive whole discards the mods before it
+            if let Some(last) = versions.iter().rposition(|v| v.as_binary().is_some()) {
+                let rest = versions.split_off(last + 1);
+                let rest_sources = sources.split_off(last + 1);
+                base_version = versions
+                    .pop_back()
+                    .with_context(|| format!("Missing replacement for {canon}"))?;
+                let source = sources.pop_back().flatten().unwrap_or_default();
+                if let Some(index) = self.mods.iter().rposition(|mod_| mod_.meta.name == source) {
+                    archive_skip = index + 1;
+                }
+                if !versions.is_empty() {
+                    log::warn!(
+                        "{source} replaces {canon} whole, discarding changes to it from {}",
+                        sources
+                            .iter()
+                            .flatten()
+                            .copied()
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                }
+                versions = rest;
+                sources = rest_sources;
+            }
+            // Mods after the replacement, or mods which merge an archive
+            // replaced whole by default, are merged over it
+            if !versions.is_empty() {
+                if let Some(data) = base_version.as_binary() {
+                    let sarc = SarcMap::from_binary(decompress_if(data))
+                        .with_context(|| jstr!("{&canon} is merged by a mod but is not a SARC"))?;
+                    base_archive = Some(std::mem::replace(
+                        &mut base_version,
+                        Arc::new(ResourceData::Sarc(sarc)),
+                    ));
+                }
+            }
+        }
         let data = match base_version.as_ref() {
             ResourceData::Binary(_) => {
                 let res = versions.pop_back().unwrap_or(base_version);
@@ -714,22 +1028,56 @@ impl ModUnpacker {
                         self.endian.into(),
                     ));
                 }
-                match Arc::try_unwrap(res) {
+                let data = match Arc::try_unwrap(res) {
                     Ok(res) => res.take_binary().context("No binary resource?")?,
                     Err(res) => res.as_binary()
                         .map(|b| b.to_vec())
                         .context("No binary resource?")?,
+                };
+                if is_modded && is_sarc(&data) {
+                    self.archives.insert(canon.clone(), false);
                 }
+                data
             }
             ResourceData::Mergeable(base_res) => {
-                let merged = versions
-                    .into_iter()
-                    .fold(base_res.clone(), |mut res, version| {
+                // Only resources changed by more than one mod can conflict
+                let mut tracker = (sources.iter().flatten().collect::<HashSet<_>>().len() > 1)
+                    .then(|| ConflictTracker::new(canon.clone()));
+                let unit_ids = self.unit_ids(&filepath);
+                let no_ids = UnitIds::default();
+                let merged = versions.iter().zip(sources.iter()).fold(
+                    base_res.clone(),
+                    |mut res, (version, source)| {
                         if let Some(mergeable) = version.as_mergeable() {
-                            res = res.merge(mergeable);
+                            let source = source.unwrap_or_default();
+                            let ids = unit_ids.get(source).unwrap_or(&no_ids);
+                            res = match tracker.as_mut() {
+                                Some(tracker) => {
+                                    res.merge_with_report(base_res, mergeable, ids, source, tracker)
+                                }
+                                None => res.merge_with_stock(base_res, mergeable, ids),
+                            };
                         }
                         res
-                    });
+                    },
+                );
+                let mut conflicts = tracker
+                    .map(|tracker| tracker.finish(&merged))
+                    .unwrap_or_default();
+                let merged = match self.resolutions.get(&canon) {
+                    Some(resolutions) => {
+                        let diffs: Vec<_> = versions
+                            .iter()
+                            .zip(sources.iter())
+                            .filter_map(|(version, source)| {
+                                Some((source.unwrap_or_default(), version.as_mergeable()?))
+                            })
+                            .collect();
+                        apply_resolutions(merged, resolutions, &diffs, &mut conflicts)
+                    }
+                    None => merged,
+                };
+                self.conflicts.insert(canon.clone(), conflicts);
                 let data = merged.into_binary(self.endian);
                 if can_rstb && (is_modded || self.hashes.is_file_modded(&canon, &data, true)) {
                     rstb_val = Some(rstb::calc::estimate_from_slice_and_name(
@@ -744,6 +1092,9 @@ impl ModUnpacker {
                 data
             }
             ResourceData::Sarc(base_sarc) => {
+                if is_modded {
+                    self.archives.insert(canon.clone(), true);
+                }
                 let merged = versions
                     .into_iter()
                     .fold(base_sarc.clone(), |mut res, version| {
@@ -753,7 +1104,15 @@ impl ModUnpacker {
                         res
                     });
                 let data = self
-                    .build_sarc(merged, aoc)
+                    .build_sarc(
+                        merged,
+                        &canon,
+                        aoc,
+                        base_archive
+                            .as_ref()
+                            .and_then(|archive| archive.as_binary()),
+                        archive_skip,
+                    )
                     .with_context(|| jstr!("Failed to build SARC file {&file}"))?;
                 if can_rstb {
                     rstb_val = Some(rstb::calc::calc_from_size_and_name(
@@ -771,18 +1130,353 @@ impl ModUnpacker {
         Ok(data)
     }
 
-    fn build_sarc(&self, sarc: SarcMap, aoc: bool) -> Result<Vec<u8>> {
+    /// Builds a merged archive. If given, `base` is the unmerged archive,
+    /// which files start from, and the first `skip` mods don't apply to it.
+    fn build_sarc(
+        &self,
+        sarc: SarcMap,
+        canon: &str,
+        aoc: bool,
+        base: Option<&[u8]>,
+        skip: usize,
+    ) -> Result<Vec<u8>> {
         let mut writer = SarcWriter::new(self.endian.into()).with_min_alignment(sarc.alignment);
+        // Terrain tiles, and the contents of archives only some mods merge,
+        // may not be indexed in the dump
+        let stock_files = match base {
+            Some(data) => Some(decompress_if(data).to_vec()),
+            None => {
+                is_tile_archive(canon)
+                    .then(|| self.dump.get_bytes_uncached(canon).ok())
+                    .flatten()
+                    .map(|data| decompress_if(&data).to_vec())
+            }
+        }
+        .and_then(|data| Sarc::new(data).ok());
         for file in sarc.files.into_iter() {
-            let data = self
-                .build_file(&file, aoc)
-                .with_context(|| jstr!("Failed to build file {&file} for SARC"))?;
+            let stock_file = stock_files
+                .as_ref()
+                .and_then(|stock| stock.get_data(file.as_str()));
+            let unchanged = || {
+                self.mods.iter().skip(skip).all(|mod_| {
+                    mod_.get_versions(file.as_str().as_ref())
+                        .map(|v| v.is_empty())
+                        .unwrap_or(true)
+                })
+            };
+            let data = match stock_file {
+                Some(stock) if unchanged() => decompress_if(stock).to_vec(),
+                _ => {
+                    self.build_file(&file, aoc, base.and(stock_file), skip)
+                        .with_context(|| jstr!("Failed to build file {&file} for SARC"))?
+                }
+            };
             writer.add_file(
                 file.as_str(),
                 compress_if(data.as_ref(), file.as_str()).as_ref(),
             );
         }
-        Ok(writer.to_binary())
+        let data = writer.to_binary();
+        if canon.ends_with(".bactorpack") {
+            self.check_actor(canon, &data);
+        } else if canon.ends_with(".pack") {
+            self.check_collision(canon, &data);
+        }
+        Ok(data)
+    }
+
+    /// For a map unit, collects the HashIds each mod's diff has to keep, from
+    /// its static compounds, and those its new objects can't use, from the
+    /// other unit of the map section. Other files get none.
+    fn unit_ids(&self, file: &str) -> HashMap<String, UnitIds> {
+        let Some(sibling) = MapUnit::path_matches(file)
+            .then(|| MapUnit::sibling_path(file))
+            .flatten()
+        else {
+            return HashMap::default();
+        };
+        let hash_ids = |res: &ResourceData| -> Vec<u32> {
+            match res.as_mergeable() {
+                Some(MergeableResource::MapUnit(unit)) => unit.hash_ids().collect(),
+                _ => vec![],
+            }
+        };
+        let stock: HashSet<u32> = self
+            .dump
+            .get_data(&sibling)
+            .map(|res| hash_ids(&res).into_iter().collect())
+            .unwrap_or_default();
+        let added = self
+            .mods
+            .iter()
+            .map(|mod_| {
+                mod_.get_versions(sibling.as_ref())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|data| minicbor_ser::from_slice::<ResourceData>(&data).ok())
+                    .flat_map(|res| hash_ids(&res))
+                    .filter(|id| !stock.contains(id))
+                    .collect::<HashSet<u32>>()
+            })
+            .collect::<Vec<_>>();
+        let reserved: HashSet<u32> = stock
+            .iter()
+            .chain(added.iter().flatten())
+            .copied()
+            .collect();
+        let mut taken = HashSet::default();
+        let mut ids = HashMap::default();
+        for (mod_, added) in self.mods.iter().zip(added) {
+            ids.insert(mod_.meta.name.clone(), UnitIds {
+                fixed:    self.compound_ids(file, mod_),
+                reserved: reserved.clone(),
+                taken:    taken.clone(),
+            });
+            taken.extend(added);
+        }
+        ids
+    }
+
+    /// The HashIds a mod's static compounds for a map unit have collision for
+    fn compound_ids(&self, unit: &str, mod_: &ModReader) -> HashSet<u32> {
+        compound_paths(unit)
+            .into_iter()
+            .filter_map(|path| mod_.get_versions(path.as_ref()).ok()?.pop())
+            .filter_map(|data| {
+                let res: ResourceData = minicbor_ser::from_slice(&data).ok()?;
+                StaticCompound::from_binary(decompress_if(res.as_binary()?)).ok()
+            })
+            .flat_map(|compound| compound.actors.into_iter().map(|actor| actor.hash_id))
+            .collect()
+    }
+
+    /// Havok files can only be replaced whole, so this warns when more than
+    /// one mod replaces the same one, and when the static compound which won
+    /// doesn't match the merged map units.
+    fn check_collision(&self, canon: &str, data: &[u8]) {
+        let Ok(sarc) = Sarc::new(data) else {
+            return;
+        };
+        let units_modded = sarc
+            .files()
+            .filter_map(|file| file.name.filter(|n| n.ends_with("mubin")))
+            .any(|name| !self.mods_changing(name).is_empty());
+        let mut warnings = vec![];
+        for file in sarc.files() {
+            let Some(name) = file.name else {
+                continue;
+            };
+            if !StaticCompound::path_matches(name) && !name.ends_with("hknm2") {
+                continue;
+            }
+            let mods = self.mods_changing(name);
+            if mods.len() > 1 {
+                let message = format!(
+                    "{} is replaced by {}. Havok files cannot be merged, so only the version from \
+                     {} is used.",
+                    name,
+                    mods.join(", "),
+                    mods[mods.len() - 1]
+                );
+                log::warn!("{message} ({canon})");
+                warnings.push(MergeWarning {
+                    resource: canon.into(),
+                    mods:     mods.clone(),
+                    message,
+                });
+            }
+            // Stock collision matches the stock units, so there's nothing to check
+            if !StaticCompound::path_matches(name) || (mods.is_empty() && !units_modded) {
+                continue;
+            }
+            match self.check_compound(canon, name, file.data, &sarc) {
+                Ok(messages) => {
+                    let mods = if mods.is_empty() {
+                        self.mods_changing(canon)
+                    } else {
+                        mods
+                    };
+                    warnings.extend(messages.into_iter().map(|message| {
+                        log::warn!("{message} ({canon})");
+                        MergeWarning {
+                            resource: canon.into(),
+                            mods:     mods.clone(),
+                            message,
+                        }
+                    }));
+                }
+                Err(e) => log::debug!("Could not check static compound {name} in {canon}: {e}"),
+            }
+        }
+        self.warnings.insert(canon.into(), warnings);
+    }
+
+    /// Compares a static compound with the map units in its pack and the
+    /// stock copy of both, returning a message for each problem found
+    fn check_compound(
+        &self,
+        canon: &str,
+        name: &str,
+        data: &[u8],
+        sarc: &Sarc,
+    ) -> Result<Vec<String>> {
+        let compound = StaticCompound::from_binary(decompress_if(data))?;
+        let objects = unit_objects(sarc)?;
+        // Some packs hold collision for map units stored elsewhere
+        if objects.is_empty() {
+            return Ok(vec![]);
+        }
+        let stock_sarc = Sarc::new(decompress_if(&self.dump.get_bytes_uncached(canon)?).to_vec())?;
+        let stock_objects = unit_objects(&stock_sarc)?;
+        let stock = StaticCompound::from_binary(decompress_if(
+            stock_sarc
+                .get_data(name)
+                .with_context(|| format!("Stock {canon} has no {name}"))?,
+        ))?;
+        let mut messages = vec![];
+        // Stock compounds list some actors which aren't in any unit, so only
+        // those the stock units have, or which are new, count
+        let orphans = compound
+            .actors
+            .iter()
+            .filter(|actor| {
+                !objects.contains(&actor.hash_id)
+                    && (stock_objects.contains(&actor.hash_id) || !stock.contains(actor.hash_id))
+            })
+            .collect::<Vec<_>>();
+        if !orphans.is_empty() {
+            messages.push(format!(
+                "{} has collision for {} objects which are not in the merged map, e.g. hash ID \
+                 {}. The game will likely crash loading this area.",
+                name,
+                orphans.len(),
+                orphans[0].hash_id
+            ));
+        }
+        let missing = objects
+            .iter()
+            .filter(|hash| stock.contains(**hash) && !compound.contains(**hash))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            messages.push(format!(
+                "{} map objects have collision in the stock {} but not in the merged one, e.g. \
+                 hash ID {}. Their collision may be missing in game.",
+                missing.len(),
+                name,
+                missing[0]
+            ));
+        }
+        Ok(messages)
+    }
+
+    /// The names of the mods which change a file, in load order
+    fn mods_changing(&self, file: &str) -> Vec<String> {
+        self.mods
+            .iter()
+            .filter(|mod_| {
+                mod_.get_versions(file.as_ref())
+                    .map(|v| !v.is_empty())
+                    .unwrap_or(false)
+            })
+            .map(|mod_| mod_.meta.name.clone())
+            .collect()
+    }
+
+    /// Reports links in a merged actor pack to files it doesn't contain
+    fn check_actor(&self, canon: &str, data: &[u8]) {
+        let Ok(sarc) = Sarc::new(data) else {
+            return;
+        };
+        match Actor::from_sarc(&sarc) {
+            Ok(actor) => {
+                if self.sync_actor_info {
+                    let mut fields = Map::default();
+                    match actor.update_info(&sarc, &mut fields) {
+                        Ok(()) => {
+                            self.info_updates.insert(actor.name.clone(), fields);
+                        }
+                        Err(e) => log::warn!("Could not read actor info from {canon}: {e}"),
+                    }
+                }
+                let links = actor.dangling_links();
+                let mods = if links.is_empty() {
+                    vec![]
+                } else {
+                    self.mods_changing(canon)
+                };
+                let warnings = links
+                    .into_iter()
+                    .map(|link| {
+                        let message = format!(
+                            "{} has {} set to {}, which is missing from the merged pack. The game \
+                             will likely crash loading this actor.",
+                            actor.name, link.user, link.path
+                        );
+                        log::warn!("{message} ({canon})");
+                        MergeWarning {
+                            resource: canon.into(),
+                            mods:     mods.clone(),
+                            message,
+                        }
+                    })
+                    .collect();
+                self.warnings.insert(canon.into(), warnings);
+            }
+            Err(e) => log::debug!("Could not check actor pack {canon}: {e}"),
+        }
+    }
+
+    /// Merges a single resource from every mod over the stock copy without
+    /// building it, following the same rules as a deploy. Returns the stock
+    /// resource, if the dump has one, alongside the merged result. The file
+    /// is a full resource path, with the `Aoc/0010/` prefix for DLC files.
+    pub fn resolve(&self, file: &str) -> Result<(Option<Arc<ResourceData>>, ResourceData)> {
+        let stock = self.dump.get_data(file).ok();
+        let mut merged = stock.as_deref().cloned();
+        let unit_ids = self.unit_ids(file);
+        let no_ids = UnitIds::default();
+        for mod_ in &self.mods {
+            let Ok(versions) = mod_.get_versions(file.as_ref()) else {
+                continue;
+            };
+            for data in versions {
+                let version: ResourceData = minicbor_ser::from_slice(&data).with_context(|| {
+                    format!(
+                        "Error deserializing resource {file} from mod {}",
+                        mod_.meta.name
+                    )
+                })?;
+                merged = Some(match (merged, version.as_sarc()) {
+                    (Some(ResourceData::Mergeable(base)), _) => {
+                        ResourceData::Mergeable(match (version.as_mergeable(), stock.as_deref()) {
+                            (Some(mergeable), Some(ResourceData::Mergeable(stock))) => {
+                                let ids = unit_ids.get(mod_.meta.name.as_str()).unwrap_or(&no_ids);
+                                base.merge_with_stock(stock, mergeable, ids)
+                            }
+                            (Some(mergeable), _) => base.merge(mergeable),
+                            (None, _) => base,
+                        })
+                    }
+                    (Some(ResourceData::Sarc(base)), Some(sarc)) => {
+                        ResourceData::Sarc(base.merge(sarc))
+                    }
+                    // As in a deploy, mods after an archive replaced whole
+                    // are merged over the replacement
+                    (Some(ResourceData::Binary(base)), Some(sarc)) => {
+                        let base =
+                            SarcMap::from_binary(decompress_if(&base)).with_context(|| {
+                                jstr!("{file} is merged by a mod but is not a SARC")
+                            })?;
+                        ResourceData::Sarc(base.merge(sarc))
+                    }
+                    // Otherwise the version replaces everything before it
+                    _ => version,
+                });
+            }
+        }
+        let merged =
+            merged.with_context(|| jstr!("No copy of {file} found in game dump or any mod"))?;
+        Ok((stock, merged))
     }
 }
 
@@ -235,6 +235,26 @@ impl ResourceReader {
         inner(mod_dir.as_ref())
     }
 
+    /// Reads resources from a packaged mod. Only the files the mod changes
+    /// are available, stored as diffs against its own masters.
+    pub fn from_mod_package(source: Box<dyn ResourceLoader>) -> Self {
+        Self {
+            file_map: match source.file_exists(Path::new("Movie/Demo101_0.mp4")) {
+                false => FILE_MAP_NX.clone(),
+                true => FILE_MAP_U.clone(),
+            },
+            source,
+            cache: construct_res_cache(),
+            sarc_cache: construct_sarc_cache(),
+            bin_type: BinType::MiniCbor,
+        }
+    }
+
+    #[inline]
+    pub fn is_mod_package(&self) -> bool {
+        self.bin_type == BinType::MiniCbor
+    }
+
     pub fn get_data(&self, path: impl AsRef<Path>) -> Result<Arc<ResourceData>> {
         let canon = canonicalize(path.as_ref());
         log::trace!("Loading resource {}", &canon);
@@ -262,6 +282,9 @@ impl ResourceReader {
 
     pub fn get_bytes_uncached(&self, path: impl AsRef<Path>) -> Result<Vec<u8>> {
         let canon = canonicalize(path.as_ref());
+        if self.is_mod_package() {
+            return self.source.get_base_file_data(Path::new(canon.as_str()));
+        }
         self.file_map.get(&canon)
             .with_context(|| jstr!("File {&canon} not in vanilla files"))?
             .iter()
@@ -6,8 +6,18 @@ use std::{
 
 use anyhow_ext::{Context, Result};
 use smartstring::alias::String;
-use uk_manager::{core, mods::LookupMod, settings::Platform};
-use uk_mod::{unpack::ModReader, Manifest, Meta};
+use uk_manager::{
+    core,
+    load_order::LoadOrderFile,
+    modpack,
+    mods::LookupMod,
+    presets::{validate_options, OptionPreset},
+    settings::Platform,
+    watch::WatchState,
+};
+use uk_mod::{
+    data::DiffFormat, unpack::ModReader, Manifest, Meta, ModOption, ModOptionGroup, OptionGroup,
+};
 
 use crate::gui::{package, tasks};
 
@@ -28,6 +38,12 @@ xflags::xflags! {
             required path: PathBuf
             /// The profile to install the mod in
             optional profile: String
+            /// Select a mod option, as <group>=<option folder or name>
+            repeated --option option: String
+            /// Select options from a preset file, or a preset saved for the mod
+            optional --preset preset: String
+            /// Start from the mod's default options
+            optional --defaults
         }
         /// Package a mod
         cmd package {
@@ -37,6 +53,60 @@ xflags::xflags! {
             required output: PathBuf
             /// Path to the meta file for the mod
             required meta: PathBuf
+            /// Sort files and fix timestamps so the package is reproducible
+            optional --deterministic
+        }
+        /// Package all mods listed in a workspace file, in dependency order
+        cmd package-all {
+            /// Path to the workspace file
+            required workspace: PathBuf
+            /// Sort files and fix timestamps so the packages are reproducible
+            optional --deterministic
+        }
+        /// Export the resources of a packaged mod as readable YAML or JSON diffs
+        cmd export-diff {
+            /// Path to the mod package
+            required path: PathBuf
+            /// Folder to write the diffs to
+            required output: PathBuf
+            /// Text format for the diffs: yaml (default) or json
+            optional --format format: DiffFormat
+        }
+        /// Build a mod package from a folder written by export-diff
+        cmd import-diff {
+            /// Folder containing the exported diffs
+            required path: PathBuf
+            /// Path to the output mod archive
+            required output: PathBuf
+        }
+        /// Check a mod or mod source folder for common problems, printing a JSON report
+        cmd lint {
+            /// Path to the mod package or source folder
+            required path: PathBuf
+        }
+        /// Export a profile and its mods as a single modpack archive
+        cmd export-modpack {
+            /// Path to the output modpack
+            required output: PathBuf
+            /// The profile to export (defaults to the current profile)
+            optional profile: String
+        }
+        /// Import a modpack as a new profile and switch to it
+        cmd import-modpack {
+            /// Path to the modpack
+            required path: PathBuf
+            /// Name for the new profile (defaults to the name in the modpack)
+            optional profile: String
+        }
+        /// Save the current profile's load order, mod states, and options to a shareable file
+        cmd export-load-order {
+            /// Path to the output load order file
+            required output: PathBuf
+        }
+        /// Apply a load order file to the installed mods in the current profile
+        cmd import-load-order {
+            /// Path to the load order file
+            required path: PathBuf
         }
         /// Uninstall a mod
         cmd uninstall {
@@ -49,11 +119,15 @@ xflags::xflags! {
         cmd remerge {}
         /// Deploy mods
         cmd deploy {}
+        /// List fields which more than one mod set to different values in the last merge
+        cmd conflicts {}
         /// Change current mode (Switch or Wii U)
         cmd mode {
             /// Mode to activate (Switch or Wii U)
             required platform: Platform
         }
+        /// Watch the folder set in settings and install new mods automatically
+        cmd watch {}
     }
 }
 // generated start
@@ -75,15 +149,29 @@ pub enum UkmmCmd {
     Install(Install),
     Uninstall(Uninstall),
     Package(Package),
+    PackageAll(PackageAll),
+    ExportDiff(ExportDiff),
+    ImportDiff(ImportDiff),
+    Lint(Lint),
+    ExportModpack(ExportModpack),
+    ImportModpack(ImportModpack),
+    ExportLoadOrder(ExportLoadOrder),
+    ImportLoadOrder(ImportLoadOrder),
     Remerge(Remerge),
     Deploy(Deploy),
+    Conflicts(Conflicts),
     Mode(Mode),
+    Watch(Watch),
 }
 
 #[derive(Debug)]
 pub struct Install {
     pub path:    PathBuf,
     pub profile: Option<String>,
+
+    pub option:   Vec<String>,
+    pub preset:   Option<String>,
+    pub defaults: bool,
 }
 
 #[derive(Debug)]
@@ -91,6 +179,56 @@ pub struct Package {
     pub path:   PathBuf,
     pub output: PathBuf,
     pub meta:   PathBuf,
+
+    pub deterministic: bool,
+}
+
+#[derive(Debug)]
+pub struct PackageAll {
+    pub workspace: PathBuf,
+
+    pub deterministic: bool,
+}
+
+#[derive(Debug)]
+pub struct ExportDiff {
+    pub path:   PathBuf,
+    pub output: PathBuf,
+
+    pub format: Option<DiffFormat>,
+}
+
+#[derive(Debug)]
+pub struct ImportDiff {
+    pub path:   PathBuf,
+    pub output: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct Lint {
+    pub path: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct ExportModpack {
+    pub output:  PathBuf,
+    pub profile: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ImportModpack {
+    pub path:    PathBuf,
+    pub profile: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ExportLoadOrder {
+    pub output: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct ImportLoadOrder {
+    pub path: PathBuf,
 }
 
 #[derive(Debug)]
@@ -105,11 +243,17 @@ pub struct Remerge;
 #[derive(Debug)]
 pub struct Deploy;
 
+#[derive(Debug)]
+pub struct Conflicts;
+
 #[derive(Debug)]
 pub struct Mode {
     pub platform: Platform,
 }
 
+#[derive(Debug)]
+pub struct Watch;
+
 impl Ukmm {
     #[allow(dead_code)]
     pub fn from_env_or_exit() -> Self {
@@ -151,7 +295,7 @@ impl Runner {
         }
     }
 
-    fn check_mod(&self, path: &Path) -> Result<Option<PathBuf>> {
+    fn check_mod(&self, path: &Path) -> Result<Option<(PathBuf, Meta)>> {
         let (mod_, path) = match ModReader::open(path, vec![]) {
             Ok(mod_) => (mod_, path.to_path_buf()),
             Err(e) => {
@@ -175,13 +319,61 @@ impl Runner {
                 }
             }
         };
-        if !mod_.meta.options.is_empty() {
+        println!("Installing {}...", mod_.meta.name);
+        Ok(Some((path, mod_.meta)))
+    }
+
+    /// Chooses options for a mod from the install flags, starting from a
+    /// preset or the mod's defaults and then applying each `--option`.
+    fn select_options(&self, meta: &Meta, install: &Install) -> Result<Vec<ModOption>> {
+        let mut selected = if let Some(preset) = install.preset.as_ref() {
+            let path = Path::new(preset.as_str());
+            let preset = if path.is_file() {
+                OptionPreset::open(path)?
+            } else {
+                let profile_dir = self
+                    .core
+                    .mod_manager()
+                    .profile_path(install.profile.as_ref());
+                OptionPreset::find(&profile_dir, meta, preset).with_context(|| {
+                    format!("No option preset named {} for {}", preset, meta.name)
+                })?
+            };
+            preset.resolve(meta)?
+        } else if install.defaults {
+            meta.default_options()
+        } else if install.option.is_empty() {
             anyhow_ext::bail!(
-                "This mod contains configuration options and should be installed via the GUI."
+                "This mod contains configuration options. Choose them with --option, --preset, or \
+                 --defaults, or install it via the GUI."
             );
+        } else {
+            vec![]
+        };
+        for arg in &install.option {
+            let (group, option) = arg
+                .split_once('=')
+                .with_context(|| format!("Invalid option {arg}, expected <group>=<option>"))?;
+            let group = meta
+                .options
+                .iter()
+                .find(|g| g.name().eq_ignore_ascii_case(group.trim()))
+                .with_context(|| format!("{} has no option group {}", meta.name, group))?;
+            let option = group
+                .options()
+                .iter()
+                .find(|o| {
+                    o.path == Path::new(option.trim()) || o.name.eq_ignore_ascii_case(option.trim())
+                })
+                .with_context(|| format!("{} has no option {}", group.name(), option))?;
+            if let OptionGroup::Exclusive(group) = group {
+                selected.retain(|o| !group.options.iter().any(|opt| opt.path == o.path));
+            }
+            if !selected.iter().any(|o| o.path == option.path) {
+                selected.push(option.clone());
+            }
         }
-        println!("Installing {}...", mod_.meta.name);
-        Ok(Some(path))
+        validate_options(meta, selected)
     }
 
     fn deploy(&self) -> Result<()> {
@@ -213,15 +405,31 @@ impl Runner {
                 }
                 println!("Done!");
             }
-            UkmmCmd::Install(Install { path, profile }) => {
-                if let Some(path) = self.check_mod(path)? {
+            UkmmCmd::Install(install) => {
+                let profile = install.profile.as_ref();
+                if let Some((path, meta)) = self.check_mod(&install.path)? {
+                    let options = match meta.options.is_empty() {
+                        true => vec![],
+                        false => self.select_options(&meta, install)?,
+                    };
                     let mods = self.core.mod_manager();
-                    let mod_ = mods.add(&path, profile.as_ref())?;
-                    mods.set_enabled(mod_.as_map_id(), true, profile.as_ref())?;
+                    let mod_ = mods.add(&path, profile)?;
+                    if !options.is_empty() {
+                        println!(
+                            "Enabling options: {}",
+                            options
+                                .iter()
+                                .map(|o| o.name.as_str())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        );
+                        mods.set_enabled_options(mod_.as_map_id(), options, profile)?;
+                    }
+                    let manifest = mods.set_enabled(mod_.as_map_id(), true, profile)?;
                     mods.save()?;
                     println!("Applying mod to load order...");
                     let deployer = self.core.deploy_manager();
-                    deployer.apply(Some(mod_.manifest()?.as_ref().clone()))?;
+                    deployer.apply(Some(manifest.as_ref().clone()))?;
                     if self.cli.deploy {
                         self.deploy()?;
                     }
@@ -234,10 +442,112 @@ impl Runner {
                     source: pkg.path.clone(),
                     dest:   pkg.output.clone(),
                     meta:   Meta::parse(&pkg.meta)?,
+                    deterministic: pkg.deterministic,
                 };
                 tasks::package_mod(&self.core, builder)?;
                 println!("Done!");
             }
+            UkmmCmd::PackageAll(PackageAll {
+                workspace,
+                deterministic,
+            }) => {
+                let workspace = tasks::Workspace::open(workspace)?;
+                println!("Packaging {} mods...", workspace.mods.len());
+                let results = tasks::package_workspace(&self.core, &workspace, *deterministic)?;
+                let mut failed = 0;
+                println!("Summary:");
+                for (id, result) in results {
+                    match result {
+                        tasks::WorkspaceResult::Built(path) => {
+                            println!("  {id}: built {}", path.display())
+                        }
+                        tasks::WorkspaceResult::Failed(e) => {
+                            failed += 1;
+                            println!("  {id}: FAILED: {e:?}")
+                        }
+                        tasks::WorkspaceResult::Skipped(dep) => {
+                            failed += 1;
+                            println!("  {id}: skipped, dependency {dep} failed")
+                        }
+                    }
+                }
+                if failed > 0 {
+                    anyhow_ext::bail!(
+                        "{} of {} mods were not packaged",
+                        failed,
+                        workspace.mods.len()
+                    );
+                }
+                println!("Done!");
+            }
+            UkmmCmd::ExportDiff(ExportDiff {
+                path,
+                output,
+                format,
+            }) => {
+                println!("Exporting diffs...");
+                uk_mod::data::export_diff(path, output, format.unwrap_or_default())?;
+                println!("Done!");
+            }
+            UkmmCmd::ImportDiff(ImportDiff { path, output }) => {
+                println!("Building mod from diffs...");
+                let output = uk_mod::data::import_diff(path, output)?;
+                println!("Saved mod to {}", output.display());
+            }
+            UkmmCmd::Lint(Lint { path }) => {
+                let report = uk_mod::lint::lint(path)?;
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                if report.errors > 0 {
+                    anyhow_ext::bail!("Found {} error(s) in {}", report.errors, report.name);
+                }
+            }
+            UkmmCmd::ExportModpack(ExportModpack { output, profile }) => {
+                println!("Exporting modpack...");
+                let manifest = modpack::export(&self.core, profile.as_ref(), output)?;
+                println!(
+                    "Saved profile {} with {} mods to {}",
+                    manifest.profile,
+                    manifest.mods.len(),
+                    output.display()
+                );
+            }
+            UkmmCmd::ImportModpack(ImportModpack { path, profile }) => {
+                println!("Importing modpack and remerging...");
+                let profile = modpack::import(&self.core, path, profile.clone())?;
+                println!("Created and switched to profile {}", profile);
+                if self.cli.deploy {
+                    self.deploy()?;
+                }
+                println!("Done!");
+            }
+            UkmmCmd::ExportLoadOrder(ExportLoadOrder { output }) => {
+                let mods = self.core.mod_manager().all_mods().collect::<Vec<_>>();
+                LoadOrderFile::new(&mods).save_to(output)?;
+                println!(
+                    "Saved load order of {} mods to {}",
+                    mods.len(),
+                    output.display()
+                );
+            }
+            UkmmCmd::ImportLoadOrder(ImportLoadOrder { path }) => {
+                let installed = self.core.mod_manager().all_mods().collect::<Vec<_>>();
+                let import = LoadOrderFile::open(path)?.apply_to(&installed)?;
+                if !import.missing.is_empty() {
+                    println!("These mods are not installed:");
+                    for entry in &import.missing {
+                        match entry.url.as_ref() {
+                            Some(url) => println!("  {} (v{}): {}", entry.name, entry.version, url),
+                            None => println!("  {} (v{})", entry.name, entry.version),
+                        }
+                    }
+                }
+                println!("Applying load order...");
+                tasks::apply_changes(&self.core, import.mods, Some(import.dirty))?;
+                if self.cli.deploy {
+                    self.deploy()?;
+                }
+                println!("Done!");
+            }
             UkmmCmd::Remerge(_) => {
                 println!("Remerging...");
                 tasks::apply_changes(&self.core, vec![], None)?;
@@ -288,6 +598,79 @@ impl Runner {
                 println!("Done!");
             }
             UkmmCmd::Deploy(_) => self.deploy()?,
+            UkmmCmd::Conflicts(_) => {
+                let report = self.core.deploy_manager().conflicts()?;
+                if report.is_empty() {
+                    println!("No conflicts found in the last merge");
+                }
+                for (resource, conflicts) in report.fields {
+                    println!("{resource}:");
+                    for conflict in conflicts {
+                        println!(
+                            "  {}: {} set {}, {} set {} (using {})",
+                            conflict.path,
+                            conflict.mod_a,
+                            conflict.value_a,
+                            conflict.mod_b,
+                            conflict.value_b,
+                            conflict.winner
+                        );
+                    }
+                }
+                for (resource, warnings) in report.warnings {
+                    println!("{resource}:");
+                    for warning in warnings {
+                        println!(
+                            "  {} (changed by {})",
+                            warning.message,
+                            warning.mods.join(", ")
+                        );
+                    }
+                }
+            }
+            UkmmCmd::Watch(_) => {
+                let config = self
+                    .core
+                    .settings()
+                    .watch_config
+                    .clone()
+                    .context("No watch folder has been set in settings")?;
+                println!(
+                    "Watching {} for new mods (profile: {})...",
+                    config.folder.display(),
+                    config.profile
+                );
+                let mut state = WatchState::load();
+                loop {
+                    let (installed, deferred) = tasks::check_watch_folder(&self.core, &mut state)?;
+                    if !installed.is_empty() {
+                        let mut manifests = Manifest::default();
+                        for mod_ in installed.iter() {
+                            println!("Installed {}", &mod_.meta.name);
+                            manifests.extend(mod_.manifest()?.as_ref());
+                        }
+                        println!("Applying changes to merge...");
+                        self.core.deploy_manager().apply(Some(manifests))?;
+                        if self.cli.deploy {
+                            self.deploy()?;
+                        }
+                    }
+                    if !deferred.is_empty() {
+                        for path in deferred.iter() {
+                            println!(
+                                "{} needs to be installed via the GUI. It will be opened the \
+                                 next time UKMM is started.",
+                                path.display()
+                            );
+                        }
+                        for path in deferred {
+                            state.defer(path);
+                        }
+                        state.save()?;
+                    }
+                    std::thread::sleep(tasks::WATCH_INTERVAL);
+                }
+            }
         };
         Ok(())
     }
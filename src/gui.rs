@@ -39,15 +39,22 @@ use parking_lot::{
 use picker::FilePickerState;
 use rustc_hash::FxHashSet;
 use serde::{Deserialize, Serialize};
-use uk_content::util::HashMap;
+use uk_content::{
+    conflict::{ConflictReport, Resolution, ResolutionChoice},
+    util::HashMap,
+};
 use uk_localization::*;
 use uk_localization::string_ext::LocString;
 use uk_manager::{
     core::Manager,
+    dev::{DevBinding, DevState},
+    load_order::LoadOrderFile,
     mods::{LookupMod, Mod},
+    projects::Project,
     settings::{Platform, Settings},
+    watch::WatchState,
 };
-use uk_mod::{pack::sanitise, Manifest, Meta, ModPlatform};
+use uk_mod::{pack::sanitise, Manifest, Meta, ModPlatform, OptionConflict};
 pub use uk_ui::visuals;
 use uk_ui::{
     egui::{
@@ -153,6 +160,7 @@ pub enum Message {
     ClearSelect,
     CloseAbout,
     CloseConfirm,
+    CloseConflicts,
     CloseError,
     CloseChangelog,
     ClosePackagingOptions,
@@ -163,24 +171,32 @@ pub enum Message {
     Deploy,
     Deselect(usize),
     DoUpdate,
+    DownloadModUpdate(Mod, tasks::ModUpdate),
     DuplicateProfile(String),
     Error(anyhow_ext::Error),
+    ExportLoadOrder,
+    ExportModpack(String),
     Extract,
     FilePickerBack,
     FilePickerSet(Option<PathBuf>),
     FilePickerUp,
     GetPackagingOptions,
+    HandleMigration(Vec<String>),
     HandleMod(Mod),
     HandleSettings,
     ImportCemu,
+    ImportLoadOrder,
+    ImportModpack,
     InstallMod(Mod),
     MigrateBcml,
     ModUpdate,
+    ModUpdatesFound(Vec<tasks::ModUpdate>),
     MoveSelected(usize),
     NewProfile,
     Noop,
     OfferUpdate(VersionResponse),
     OpenMod(PathBuf),
+    OptionConflicts(usize, Vec<OptionConflict>),
     PackageMod,
     RefreshModsDisplay,
     Remerge,
@@ -193,6 +209,7 @@ pub enum Message {
     ResetPacker,
     ResetPending,
     ResetSettings,
+    ResolveConflict(Resolution),
     Restart,
     SaveSettings,
     SelectAlso(usize),
@@ -206,15 +223,30 @@ pub enum Message {
     SetLanguage(LocLang),
     SetTheme(uk_ui::visuals::Theme),
     ShowAbout,
+    ShowConflicts,
     ShowPackagingOptions(FxHashSet<PathBuf>),
     ShowPackagingDependencies,
     StartDrag(usize),
     Toast(String),
     ToggleMods(Option<Vec<Mod>>, bool),
     DevUpdate,
+    DevBind(Mod),
+    DevUnbind(Mod),
+    DevChanged(DevBinding, Vec<PathBuf>),
+    DevReloaded(Mod),
+    DevReloadFailed(PathBuf, Vec<PathBuf>, anyhow_ext::Error),
     UpdatePackageMeta(Meta),
     UninstallMods(Option<Vec<Mod>>),
     UpdateOptions(Mod),
+    WatchFound(Vec<PathBuf>),
+    WatchedMods(Vec<Mod>, Vec<PathBuf>),
+    RefreshProjects,
+    NewProject,
+    EditProject(Project),
+    RepackageProject(Project),
+    ProjectPackaged(Option<Manifest>, PathBuf),
+    InspectResource(Mod, PathBuf, bool),
+    ShowResource(info::ResourceView),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -243,6 +275,10 @@ pub struct App {
     displayed_mods: Vec<Mod>,
     selected: Vec<Mod>,
     install_queue: VecDeque<PathBuf>,
+    /// Mods found in the watched folder, waiting to be installed
+    watch_queue: Vec<PathBuf>,
+    /// Source changes of mods bound in dev mode, waiting to be reloaded
+    dev_queue: Vec<(DevBinding, Vec<PathBuf>)>,
     update_mod: Option<Mod>,
     error_queue: VecDeque<anyhow_ext::Error>,
     drag_index: Option<usize>,
@@ -258,12 +294,19 @@ pub struct App {
     confirm: Option<(Message, String)>,
     busy: Cell<bool>,
     show_about: bool,
+    conflicts: Option<ConflictReport>,
     package_builder: RefCell<ModPackerBuilder>,
     show_package_deps: bool,
     opt_folders: Option<Mutex<FxHashSet<PathBuf>>>,
+    projects: Vec<Project>,
+    inspected: Option<info::ResourceView>,
+    dev: Arc<Mutex<DevState>>,
+    watch: Arc<Mutex<WatchState>>,
     dirty: RwLock<HashMap<String, Manifest>>,
     sort: (Sort, bool),
     options_mod: Option<(Mod, bool)>,
+    mod_updates: HashMap<usize, tasks::ModUpdate>,
+    option_picker: options::OptionPickerState,
     temp_settings: Settings,
     toasts: egui_notify::Toasts,
     theme: uk_ui::visuals::Theme,
@@ -294,6 +337,13 @@ impl App {
         let mods: Vec<_> = core.mod_manager().all_mods().collect();
         let (send, recv) = flume::unbounded();
         tasks::ONECLICK_SENDER.set(send.clone()).unwrap_or(());
+        let watch = Arc::new(Mutex::new(WatchState::load()));
+        tasks::start_watcher(core.clone(), watch.clone(), send.clone());
+        let dev = Arc::new(Mutex::new(DevState::load()));
+        tasks::start_dev_watcher(dev.clone(), send.clone());
+        if core.settings().check_mod_updates {
+            tasks::check_mod_updates(core.clone(), send.clone());
+        }
         let temp_settings = core.settings().clone();
         let platform = core.settings().current_mode;
         LOCALIZATION.write().update_language(&temp_settings.lang);
@@ -329,8 +379,13 @@ impl App {
             new_profile: None,
             confirm: None,
             show_about: false,
+            conflicts: None,
             show_package_deps: false,
             opt_folders: None,
+            projects: Project::list(&core.settings()),
+            inspected: None,
+            dev,
+            watch,
             busy: Cell::new(false),
             dirty: {
                 let settings = core.settings();
@@ -343,11 +398,15 @@ impl App {
             },
             sort: (Sort::Priority, false),
             options_mod: None,
+            mod_updates: Default::default(),
+            option_picker: Default::default(),
             tree: Rc::new(RefCell::new(ui_state.tree)),
             toasts: egui_notify::Toasts::new().with_anchor(egui_notify::Anchor::BottomRight),
             theme: ui_state.theme,
             dock_style: uk_ui::visuals::style_dock(&cc.egui_ctx.style()),
             install_queue: Default::default(),
+            watch_queue: Default::default(),
+            dev_queue: Default::default(),
             update_mod: Default::default(),
             error_queue: Default::default(),
             new_version: None,
@@ -389,6 +448,7 @@ impl App {
             || self.options_mod.is_some()
             || self.confirm.is_some()
             || self.show_about
+            || self.conflicts.is_some()
             || self.new_profile.is_some()
             || self.show_package_deps
             || self.opt_folders.is_some()
@@ -435,6 +495,47 @@ impl App {
         });
     }
 
+    /// Opens the next mod waiting to be installed, if any
+    fn open_next_mod(&mut self) -> bool {
+        let Some(path) = self.install_queue.pop_front() else {
+            return false;
+        };
+        let mut watch = self.watch.lock();
+        if watch.resolve(&path) {
+            if let Err(e) = watch.save() {
+                log::warn!("{e:?}");
+            }
+        }
+        drop(watch);
+        self.do_task(move |core| tasks::open_mod(&core, &path, None));
+        true
+    }
+
+    /// Installs mods found in the watched folder once nothing else is running
+    fn install_watched(&mut self) {
+        if self.watch_queue.is_empty() || self.modal_open() {
+            return;
+        }
+        let paths = std::mem::take(&mut self.watch_queue);
+        // Only lists of paths are guarded, which a panic can't leave unusable
+        let watch = std::panic::AssertUnwindSafe(self.watch.clone());
+        self.do_task(move |core| tasks::install_watched_task(&core, &watch, paths));
+    }
+
+    /// Reloads mods bound in dev mode once nothing else is running
+    fn reload_dev(&mut self) {
+        if self.dev_queue.is_empty() || self.modal_open() {
+            return;
+        }
+        let (binding, changed) = self.dev_queue.remove(0);
+        self.do_task(move |core| {
+            match tasks::dev_reload(&core, &binding, &changed) {
+                Ok(mod_) => Ok(Message::DevReloaded(mod_)),
+                Err(e) => Ok(Message::DevReloadFailed(binding.mod_path, changed, e)),
+            }
+        });
+    }
+
     fn handle_drops(&mut self, ctx: &eframe::egui::Context) {
         let files = ctx.input(|i| i.raw.dropped_files.clone());
         if !(self.modal_open() || files.is_empty()) {
@@ -450,11 +551,14 @@ impl App {
 impl eframe::App for App {
     fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
         self.handle_update(ctx, frame);
+        self.install_watched();
+        self.reload_dev();
         self.render_menu(ctx, frame);
         self.render_error(ctx);
         self.render_confirm(ctx);
         self.render_new_profile(ctx);
         self.render_about(ctx);
+        self.render_conflicts(ctx);
         self.render_option_picker(ctx);
         self.profiles_state.borrow_mut().render(self, ctx);
         self.render_changelog(ctx);
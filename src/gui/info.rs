@@ -1,6 +1,8 @@
 use std::{
+    cell::Cell,
     hash::{Hash, Hasher},
     io::{BufReader, Read},
+    path::{Path, PathBuf},
     sync::{Arc, LazyLock},
 };
 
@@ -14,7 +16,7 @@ use uk_mod::Manifest;
 use uk_ui::egui_extras::RetainedImage;
 use uk_ui::{
     egui::{self, Align, Label, Layout, RichText, Ui},
-    icons::IconButtonExt,
+    icons::{Icon, IconButtonExt},
     PathNode,
 };
 
@@ -22,10 +24,22 @@ use super::Component;
 
 pub enum Message {
     RequestOptions,
+    Inspect(PathBuf, bool),
+    CloseInspector,
 }
 
-#[repr(transparent)]
-pub struct ModInfo<'a>(pub &'a Mod);
+/// A decoded resource from a mod, shown below its manifest. When `merged` is
+/// set, the panes hold the merged result for the current profile and the
+/// stock copy, otherwise the mod's own stored diff.
+#[derive(Debug, Clone)]
+pub struct ResourceView {
+    pub mod_hash: usize,
+    pub file:     PathBuf,
+    pub merged:   bool,
+    pub panes:    Vec<(String, String)>,
+}
+
+pub struct ModInfo<'a>(pub &'a Mod, pub Option<&'a ResourceView>);
 
 impl ModInfo<'_> {
     #[allow(deprecated)]
@@ -136,13 +150,52 @@ impl Component for ModInfo<'_> {
             }
             ui.label(RichText::new("Info_Manifest".localize())
                 .family(egui::FontFamily::Name("Bold".into())));
+            let inspected = self.1.filter(|view| view.mod_hash == mod_.hash());
             match mod_.manifest() {
-                Ok(manifest) => render_manifest(&manifest, ui),
+                Ok(manifest) => {
+                    if let Some(file) = render_manifest_selectable(
+                        &manifest,
+                        ui,
+                        inspected.map(|view| view.file.as_path()),
+                    ) {
+                        msg = Some(Message::Inspect(file, false));
+                    }
+                }
                 Err(e) => {
                     log::error!("{:#?}", e);
                     ui.label(RichText::new("FAILED TO LOAD MANIFEST").strong());
                 }
             }
+            if let Some(view) = inspected {
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(view.file.display().to_string())
+                            .family(egui::FontFamily::Name("Bold".into())),
+                    );
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        if ui.icon_button(Icon::Cancel).clicked() {
+                            msg = Some(Message::CloseInspector);
+                        }
+                        let mut merged = view.merged;
+                        if ui
+                            .checkbox(&mut merged, "Info_Inspect_Merged".localize())
+                            .changed()
+                        {
+                            msg = Some(Message::Inspect(view.file.clone(), merged));
+                        }
+                    });
+                });
+                ui.columns(view.panes.len().max(1), |columns| {
+                    for (ui, (title, text)) in columns.iter_mut().zip(view.panes.iter()) {
+                        ui.label(RichText::new(title).strong());
+                        egui::ScrollArea::both()
+                            .id_source(title)
+                            .max_height(480.0)
+                            .show(ui, |ui| uk_ui::syntect::code_view_ui(ui, text));
+                    }
+                });
+            }
             ui.add_space(8.0);
             msg
         })
@@ -153,6 +206,26 @@ pub static ROOTS: LazyLock<RwLock<FxHashMap<u64, PathNode>>> =
     LazyLock::new(|| RwLock::new(FxHashMap::default()));
 
 pub fn render_manifest(manifest: &Manifest, ui: &mut Ui) {
+    render_manifest_inner(manifest, ui, None);
+}
+
+/// Renders the manifest with selectable files, returning the file that was
+/// double clicked, if any. DLC files are given with their `Aoc/0010` prefix.
+pub fn render_manifest_selectable(
+    manifest: &Manifest,
+    ui: &mut Ui,
+    selected: Option<&Path>,
+) -> Option<PathBuf> {
+    let clicked = Cell::new(None);
+    render_manifest_inner(manifest, ui, Some((selected, &clicked)));
+    clicked.into_inner()
+}
+
+const AOC_PREFIX: &str = "Aoc/0010";
+
+type Selection<'a> = Option<(Option<&'a Path>, &'a Cell<Option<PathBuf>>)>;
+
+fn render_manifest_inner(manifest: &Manifest, ui: &mut Ui, selection: Selection) {
     ui.scope(|ui| {
         ui.style_mut().override_text_style = Some(egui::TextStyle::Body);
         ui.spacing_mut().item_spacing.y = 4.;
@@ -168,7 +241,12 @@ pub fn render_manifest(manifest: &Manifest, ui: &mut Ui) {
                 });
                 root
             });
-            content_root.render_dir(ui);
+            match selection {
+                Some((selected, clicked)) => {
+                    content_root.render_dir_selectable(ui, selected, |file| clicked.set(Some(file)))
+                }
+                None => content_root.render_dir(ui),
+            }
         }
         if !manifest.aoc_files.is_empty() {
             let mut hasher = FxHasher::default();
@@ -182,7 +260,16 @@ pub fn render_manifest(manifest: &Manifest, ui: &mut Ui) {
                 });
                 root
             });
-            aoc_root.render_dir(ui);
+            match selection {
+                Some((selected, clicked)) => {
+                    aoc_root.render_dir_selectable(
+                        ui,
+                        selected.and_then(|file| file.strip_prefix(AOC_PREFIX).ok()),
+                        |file| clicked.set(Some(Path::new(AOC_PREFIX).join(file))),
+                    )
+                }
+                None => aoc_root.render_dir(ui),
+            }
         }
     });
 }
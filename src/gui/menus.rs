@@ -27,6 +27,14 @@ impl App {
             ui.close_menu();
             self.do_update(Message::SelectFile);
         }
+        if ui.button("Menu_File_ImportLoadOrder".localize()).clicked() {
+            ui.close_menu();
+            self.do_update(Message::ImportLoadOrder);
+        }
+        if ui.button("Menu_File_ExportLoadOrder".localize()).clicked() {
+            ui.close_menu();
+            self.do_update(Message::ExportLoadOrder);
+        }
         if ui.button("Generic_Exit".localize()).clicked() {
             ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
         }
@@ -37,6 +45,10 @@ impl App {
             ui.close_menu();
             self.do_update(Message::Remerge);
         }
+        if ui.button("Menu_Tools_Conflicts".localize()).clicked() {
+            ui.close_menu();
+            self.do_update(Message::ShowConflicts);
+        }
         if ui.button("Menu_Tools_ResetPending".localize()).clicked() {
             ui.close_menu();
             self.do_update(Message::ResetPending);
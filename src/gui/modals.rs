@@ -43,6 +43,8 @@ impl MetaInputModal {
             category: ModCategory::Other,
             author: Default::default(),
             masters: Default::default(),
+            content_hash: None,
+            archives: Default::default(),
             options: Default::default(),
             platform: uk_mod::ModPlatform::Specific(platform.into()),
             url: Default::default(),
@@ -510,4 +512,108 @@ impl App {
                 });
         }
     }
+
+    pub fn render_conflicts(&self, ctx: &egui::Context) {
+        if let Some(ref conflicts) = self.conflicts {
+            egui::Window::new("Conflicts_Title".localize())
+                .collapsible(false)
+                .anchor(Align2::CENTER_CENTER, Vec2::default())
+                .frame(Frame::window(&ctx.style()).inner_margin(6.))
+                .default_width(600.0)
+                .default_height(400.0)
+                .show(ctx, |ui| {
+                    ui.spacing_mut().item_spacing.y = 6.0;
+                    if conflicts.is_empty() {
+                        ui.label("Conflicts_None".localize());
+                    } else {
+                        ui.label("Conflicts_Description".localize());
+                    }
+                    egui::ScrollArea::both()
+                        .max_height(ctx.available_rect().height() * 0.6)
+                        .show(ui, |ui| {
+                            for (resource, found) in &conflicts.fields {
+                                egui::CollapsingHeader::new(format!(
+                                    "{} ({})",
+                                    resource,
+                                    found.len()
+                                ))
+                                .id_source(resource.as_str())
+                                .show(ui, |ui| {
+                                    egui::Grid::new(resource.as_str())
+                                        .num_columns(4)
+                                        .striped(true)
+                                        .show(ui, |ui| {
+                                            ui.strong("Conflicts_Field".localize());
+                                            ui.strong("Conflicts_Earlier".localize());
+                                            ui.strong("Conflicts_Later".localize());
+                                            ui.strong("Conflicts_Winner".localize());
+                                            ui.end_row();
+                                            for conflict in found {
+                                                ui.monospace(&conflict.path);
+                                                for (mod_name, value) in [
+                                                    (&conflict.mod_a, &conflict.value_a),
+                                                    (&conflict.mod_b, &conflict.value_b),
+                                                ] {
+                                                    if ui
+                                                        .selectable_label(
+                                                            conflict.winner == *mod_name,
+                                                            format!("{mod_name}: {value}"),
+                                                        )
+                                                        .on_hover_text(
+                                                            "Conflicts_Choose".localize(),
+                                                        )
+                                                        .clicked()
+                                                    {
+                                                        self.do_update(Message::ResolveConflict(
+                                                            Resolution {
+                                                                resource: resource.clone(),
+                                                                path:     conflict.path.clone(),
+                                                                choice:   ResolutionChoice::Mod(
+                                                                    mod_name.clone(),
+                                                                ),
+                                                            },
+                                                        ));
+                                                    }
+                                                }
+                                                ui.label(conflict.winner.as_str());
+                                                ui.end_row();
+                                            }
+                                        });
+                                });
+                            }
+                            if !conflicts.warnings.is_empty() {
+                                ui.strong("Conflicts_Warnings".localize());
+                            }
+                            for (resource, warnings) in &conflicts.warnings {
+                                egui::CollapsingHeader::new(format!(
+                                    "{} ({})",
+                                    resource,
+                                    warnings.len()
+                                ))
+                                .id_source(format!("{resource}-warnings"))
+                                .show(ui, |ui| {
+                                    for warning in warnings {
+                                        ui.label(warning.message.as_str())
+                                            .on_hover_text(warning.mods.join(", "));
+                                    }
+                                });
+                            }
+                        });
+                    ui.separator();
+                    let width = ui.min_size().x;
+                    ui.horizontal(|ui| {
+                        ui.allocate_ui_with_layout(
+                            Vec2::new(width, ui.min_size().y),
+                            Layout::right_to_left(Align::Center),
+                            |ui| {
+                                if ui.button("Generic_OK".localize()).clicked() {
+                                    self.do_update(Message::CloseConflicts);
+                                }
+                                ui.shrink_width_to_current();
+                            },
+                        );
+                    });
+                });
+        }
+    }
 }
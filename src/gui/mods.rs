@@ -10,6 +10,7 @@ use uk_ui::{
     },
     egui_extras::{Column, TableBuilder, TableRow},
     ext::UiExt,
+    icons::{Icon, IconButtonExt},
 };
 
 use super::{App, FocusedPane, Message, Sort};
@@ -19,6 +20,7 @@ enum ContextMenuMessage {
     Extract,
     Update,
     DevUpdate,
+    DevWatch(bool),
     Uninstall,
     Toggle(bool),
     Move(usize),
@@ -347,6 +349,9 @@ impl App {
             let mut toggled = false;
             let mut ctx_action = None;
             let menu_mod = mod_.clone();
+            let dev_bound = self.dev.lock().is_bound(&mod_.path);
+            let update = self.mod_updates.get(&mod_.hash()).cloned();
+            let mut download_update = false;
 
             let mut process_col_res = |res: Response| {
                 clicked = clicked || res.clicked();
@@ -354,7 +359,7 @@ impl App {
                 drag_started = drag_started || res.drag_started();
                 res.context_menu(|ui| {
                     if let Some(action) =
-                        Self::render_mod_context_menu(&self.core, menu_mod.clone(), ui)
+                        Self::render_mod_context_menu(&self.core, menu_mod.clone(), dev_bound, ui)
                     {
                         ctx_action.replace(action);
                     }
@@ -375,6 +380,13 @@ impl App {
             );
             process_col_res(
                 row.col(|ui| {
+                    if let Some(update) = update.as_ref() {
+                        let vars = HashMap::from([("version".to_string(), update.version.clone())]);
+                        download_update = ui
+                            .icon_button(Icon::ArrowUp)
+                            .on_hover_text("Mod_Update_Available".localize().format(&vars).unwrap())
+                            .clicked();
+                    }
                     ui.clipped_label(mod_.meta.name.as_str());
                 })
                 .1,
@@ -405,6 +417,12 @@ impl App {
                     ContextMenuMessage::DevUpdate => {
                         self.do_update(Message::DevUpdate);
                     }
+                    ContextMenuMessage::DevWatch(true) => {
+                        self.do_update(Message::DevBind(mod_.clone()));
+                    }
+                    ContextMenuMessage::DevWatch(false) => {
+                        self.do_update(Message::DevUnbind(mod_.clone()));
+                    }
                     ContextMenuMessage::Uninstall => {
                         let message = "Mod_Uninstall_Confirmation".localize();
                         let vars = HashMap::from(
@@ -424,7 +442,9 @@ impl App {
                     }
                 }
             }
-            if toggled {
+            if let Some(update) = update.filter(|_| download_update) {
+                self.do_update(Message::DownloadModUpdate(menu_mod.clone(), update));
+            } else if toggled {
                 self.do_update(Message::ToggleMods(Some(vec![menu_mod.clone()]), enabled));
             } else if clicked {
                 self.do_update(Message::SetFocus(FocusedPane::ModList));
@@ -450,6 +470,7 @@ impl App {
     fn render_mod_context_menu(
         core: &uk_manager::core::Manager,
         mod_: Mod,
+        dev_bound: bool,
         ui: &mut Ui,
     ) -> Option<ContextMenuMessage> {
         let mut result = None;
@@ -473,6 +494,17 @@ impl App {
             ui.close_menu();
             result = Some(ContextMenuMessage::DevUpdate);
         }
+        if ui
+            .button(if dev_bound {
+                "Mod_Dev_Unwatch".localize()
+            } else {
+                "Mod_Dev_Watch".localize()
+            })
+            .clicked()
+        {
+            ui.close_menu();
+            result = Some(ContextMenuMessage::DevWatch(!dev_bound));
+        }
         if ui.button("Mod_Uninstall".localize()).clicked() {
             ui.close_menu();
             result = Some(ContextMenuMessage::Uninstall);
@@ -1,26 +1,143 @@
+use std::path::PathBuf;
+
+use anyhow_ext::Result;
 use eframe::egui::Button;
+use rustc_hash::FxHashSet;
+use strfmt::Format;
 use uk_localization::string_ext::LocString;
-use uk_mod::ModOptionGroup;
+use uk_manager::{
+    core::Manager,
+    mods::Mod,
+    presets::{validate_options, OptionPreset},
+};
+use uk_mod::{pack::sanitise, ModOptionGroup, OptionConflict, OptionProblem};
 use uk_ui::{
-    egui::{self, Align, Checkbox, Context, Layout, Vec2},
+    egui::{self, Align, Checkbox, Context, Layout, RadioButton, Ui, Vec2},
     visuals,
 };
 
 use super::{App, Message};
 
+/// Extra state for the option picker, loaded when it opens.
+#[derive(Debug, Default)]
+pub struct OptionPickerState {
+    conflicts:   Vec<OptionConflict>,
+    presets:     Vec<OptionPreset>,
+    preset_name: String,
+    profile_dir: PathBuf,
+}
+
+impl OptionPickerState {
+    pub fn set_conflicts(&mut self, conflicts: Vec<OptionConflict>) {
+        self.conflicts = conflicts;
+    }
+
+    /// Conflicts are checked in the background and filled in by
+    /// [`Message::OptionConflicts`] once ready.
+    pub fn load(core: &Manager, mod_: &Mod) -> Self {
+        let profile_dir = core.mod_manager().path();
+        Self {
+            conflicts: vec![],
+            presets: OptionPreset::list(&profile_dir, &mod_.meta),
+            preset_name: Default::default(),
+            profile_dir,
+        }
+    }
+}
+
+fn localize_with<const N: usize>(key: &'static str, vars: [(&str, String); N]) -> String {
+    let vars = std::collections::HashMap::from(vars.map(|(k, v)| (k.to_string(), v)));
+    key.localize().format(&vars).unwrap()
+}
+
+fn render_presets(state: &mut OptionPickerState, mod_: &mut Mod, ui: &mut Ui) -> Result<()> {
+    let mut loaded = None;
+    ui.horizontal(|ui| -> Result<()> {
+        egui::ComboBox::new("option-presets", "")
+            .selected_text("Options_Preset_Load".localize())
+            .show_ui(ui, |ui| {
+                for preset in &state.presets {
+                    if ui.selectable_label(false, preset.name.as_str()).clicked() {
+                        loaded = Some(preset.clone());
+                    }
+                }
+            });
+        if ui.button("Options_Preset_Import".localize()).clicked() {
+            if let Some(file) = rfd::FileDialog::new()
+                .add_filter("Option Preset (*.yml)", &["yml", "yaml"])
+                .pick_file()
+            {
+                loaded = Some(OptionPreset::open(file)?);
+            }
+        }
+        Ok(())
+    })
+    .inner?;
+    if let Some(preset) = loaded {
+        mod_.enabled_options = preset.resolve(&mod_.meta)?;
+        state.preset_name = preset.name.to_string();
+    }
+    ui.horizontal(|ui| -> Result<()> {
+        ui.text_edit_singleline(&mut state.preset_name);
+        let name = state.preset_name.trim();
+        let preset = OptionPreset::new(name, &mod_.meta, &mod_.enabled_options);
+        if ui
+            .add_enabled(
+                !name.is_empty(),
+                Button::new("Options_Preset_Save".localize()),
+            )
+            .clicked()
+        {
+            preset.save(&state.profile_dir)?;
+            state.presets.retain(|p| p.name != preset.name);
+            state.presets.push(preset.clone());
+            state.presets.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        if ui
+            .add_enabled(
+                !name.is_empty(),
+                Button::new("Options_Preset_Export".localize()),
+            )
+            .clicked()
+        {
+            if let Some(dest) = rfd::FileDialog::new()
+                .add_filter("Option Preset (*.yml)", &["yml"])
+                .set_file_name(format!("{}.yml", sanitise(name)))
+                .save_file()
+            {
+                preset.save_to(dest)?;
+            }
+        }
+        Ok(())
+    })
+    .inner
+}
+
 impl App {
     pub fn render_option_picker(&mut self, ctx: &Context) {
         let is_opt_mod = self.options_mod.is_some();
         if !is_opt_mod {
             return;
         }
+        let mut error = None;
         egui::Window::new("Options_Select".localize())
             .collapsible(false)
             .scroll([false, true])
             .anchor(egui::Align2::CENTER_CENTER, Vec2::default())
             .show(ctx, |ui| {
                 let mod_ = unsafe { &mut self.options_mod.as_mut().unwrap_unchecked().0 };
+                if let Err(e) = render_presets(&mut self.option_picker, mod_, ui) {
+                    error = Some(e);
+                }
+                ui.separator();
                 let mut done = true;
+                let mut changed = false;
+                // Options required by a selected option can't be turned off
+                let locked: FxHashSet<PathBuf> = mod_
+                    .enabled_options
+                    .iter()
+                    .flat_map(|opt| opt.requires.iter().cloned())
+                    .collect();
                 mod_.meta.options.iter().for_each(|group| {
                     egui::CollapsingHeader::new(group.name())
                         .default_open(true)
@@ -32,30 +149,41 @@ impl App {
                                 }
                                 match group {
                                     uk_mod::OptionGroup::Exclusive(group) => {
+                                        let group_locked = group.options.iter().any(|opt| {
+                                            locked.contains(&opt.path)
+                                                && mod_.enabled_options.contains(opt)
+                                        });
                                         if !group.required
                                             && ui
-                                                .radio(
-                                                    !group.options.iter().any(|opt| {
-                                                        mod_.enabled_options.contains(opt)
-                                                    }),
-                                                    "Options_None".localize(),
+                                                .add_enabled(
+                                                    !group_locked,
+                                                    RadioButton::new(
+                                                        !group.options.iter().any(|opt| {
+                                                            mod_.enabled_options.contains(opt)
+                                                        }),
+                                                        "Options_None".localize(),
+                                                    ),
                                                 )
                                                 .clicked()
                                         {
                                             mod_.enabled_options
                                                 .retain(|opt| !group.options.contains(opt));
+                                            changed = true;
                                         }
                                         group.options.iter().for_each(|opt| {
+                                            let checked = mod_.enabled_options.contains(opt);
                                             if ui
-                                                .radio(
-                                                    mod_.enabled_options.contains(opt),
-                                                    opt.name.as_str(),
+                                                .add_enabled(
+                                                    checked || !group_locked,
+                                                    RadioButton::new(checked, opt.name.as_str()),
                                                 )
+                                                .on_disabled_hover_text("Options_Locked".localize())
                                                 .clicked()
                                             {
                                                 mod_.enabled_options
                                                     .retain(|o| !group.options.contains(o));
                                                 mod_.enabled_options.push(opt.clone());
+                                                changed = true;
                                             }
                                             if !opt.description.is_empty() {
                                                 ui.small(opt.description.as_str());
@@ -66,7 +194,11 @@ impl App {
                                         group.options.iter().for_each(|opt| {
                                             let mut checked = mod_.enabled_options.contains(opt);
                                             if ui
-                                                .add(Checkbox::new(&mut checked, opt.name.as_str()))
+                                                .add_enabled(
+                                                    !(checked && locked.contains(&opt.path)),
+                                                    Checkbox::new(&mut checked, opt.name.as_str()),
+                                                )
+                                                .on_disabled_hover_text("Options_Locked".localize())
                                                 .clicked()
                                             {
                                                 if checked {
@@ -74,6 +206,7 @@ impl App {
                                                 } else {
                                                     mod_.enabled_options.retain(|o| o != opt);
                                                 }
+                                                changed = true;
                                             }
                                             if !opt.description.is_empty() {
                                                 ui.small(opt.description.as_str());
@@ -92,18 +225,71 @@ impl App {
                         done = false;
                     }
                 });
+                if changed {
+                    mod_.enabled_options = mod_.meta.with_requirements(&mod_.enabled_options);
+                }
                 if !done {
                     ui.colored_label(visuals::RED, "Options_Required".localize());
                 }
+                let problems = mod_.meta.option_problems(&mod_.enabled_options);
+                for problem in &problems {
+                    ui.colored_label(visuals::RED, match problem {
+                        OptionProblem::MissingRequirement { option, requires } => {
+                            localize_with("Options_Problem_Missing", [
+                                ("option", option.to_string()),
+                                ("requires", requires.display().to_string()),
+                            ])
+                        }
+                        OptionProblem::Unselected { option, requires } => {
+                            localize_with("Options_Problem_Requires", [
+                                ("option", option.to_string()),
+                                ("requires", requires.to_string()),
+                            ])
+                        }
+                        OptionProblem::ExclusiveConflict { group, options } => {
+                            localize_with("Options_Problem_Exclusive", [
+                                ("group", group.to_string()),
+                                ("options", options.join(", ")),
+                            ])
+                        }
+                    });
+                }
+                done &= problems.is_empty();
+                for conflict in &self.option_picker.conflicts {
+                    let names: Vec<&str> = conflict
+                        .options
+                        .iter()
+                        .filter(|path| mod_.enabled_options.iter().any(|o| o.path == **path))
+                        .filter_map(|path| mod_.meta.option(path).map(|o| o.name.as_str()))
+                        .collect();
+                    if names.len() > 1 {
+                        ui.colored_label(
+                            visuals::ORANGE,
+                            localize_with("Options_Conflict", [
+                                ("options", names.join(", ")),
+                                ("file", conflict.file.to_string()),
+                            ]),
+                        );
+                    }
+                }
                 ui.horizontal(|ui| {
                     ui.add_space(2.);
                     ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
                         if ui.add_enabled(done, Button::new("Generic_OK".localize())).clicked() {
-                            let (mod_, update) = self.options_mod.take().unwrap();
-                            if update {
-                                self.do_update(Message::UpdateOptions(mod_));
-                            } else {
-                                self.do_update(Message::InstallMod(mod_));
+                            let (mut mod_, update) = self.options_mod.take().unwrap();
+                            match validate_options(&mod_.meta, mod_.enabled_options.clone()) {
+                                Ok(options) => {
+                                    mod_.enabled_options = options;
+                                    if update {
+                                        self.do_update(Message::UpdateOptions(mod_));
+                                    } else {
+                                        self.do_update(Message::InstallMod(mod_));
+                                    }
+                                }
+                                Err(e) => {
+                                    self.options_mod = Some((mod_, update));
+                                    error = Some(e);
+                                }
                             }
                         }
                         if ui.button("Generic_Cancel".localize()).clicked() {
@@ -112,5 +298,8 @@ impl App {
                     });
                 });
             });
+        if let Some(e) = error {
+            self.do_update(Message::Error(e));
+        }
     }
 }
@@ -10,7 +10,7 @@ use uk_mod::{
     OptionGroup, ModCategory,
 };
 use uk_ui::{
-    egui::{self, Align2, Context, Id, Layout, Response, TextStyle, Ui},
+    egui::{self, Align2, Context, Id, Layout, Response, RichText, TextStyle, Ui},
     ext::UiExt,
     icons::{Icon, IconButtonExt},
 };
@@ -28,6 +28,8 @@ pub struct ModPackerBuilder {
     pub source: PathBuf,
     pub dest:   PathBuf,
     pub meta:   Meta,
+    /// Write a reproducible package, see [`ModPacker::deterministic`]
+    pub deterministic: bool,
 }
 
 impl ModPackerBuilder {
@@ -46,7 +48,10 @@ impl ModPackerBuilder {
                 url: Default::default(),
                 options: Default::default(),
                 masters: Default::default(),
+                content_hash: None,
+                archives: Default::default(),
             },
+            deterministic: false,
         }
     }
 
@@ -342,11 +347,73 @@ impl ModPackerBuilder {
         }
     }
 
+    fn render_projects(&self, app: &App, ui: &mut Ui) {
+        egui::CollapsingHeader::new("Package_Projects".localize())
+            .default_open(!app.projects.is_empty())
+            .show(ui, |ui| {
+                if app.projects.is_empty() {
+                    ui.label("Package_Projects_None".localize());
+                }
+                for project in app.projects.iter() {
+                    ui.horizontal(|ui| {
+                        let editing = self.source == project.path;
+                        ui.label(if editing {
+                            RichText::new(project.name.as_str()).strong()
+                        } else {
+                            RichText::new(project.name.as_str())
+                        })
+                        .on_hover_text(project.path.display().to_string());
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui
+                                .icon_button(Icon::Refresh)
+                                .on_hover_text("Package_Project_Repackage".localize())
+                                .clicked()
+                            {
+                                app.do_update(Message::RepackageProject(project.clone()));
+                            }
+                            if ui
+                                .icon_button(Icon::FolderOpen)
+                                .on_hover_text("Package_Project_Open".localize())
+                                .clicked()
+                            {
+                                open::that(&project.path).unwrap_or(());
+                            }
+                            if ui
+                                .add_enabled(
+                                    !editing,
+                                    egui::Button::new("Package_Project_Edit".localize()),
+                                )
+                                .clicked()
+                            {
+                                app.do_update(Message::EditProject(project.clone()));
+                            }
+                        });
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(!self.meta.name.is_empty(), |ui| {
+                        if ui
+                            .icon_text_button("Package_Project_New".localize(), Icon::Add)
+                            .on_hover_text("Package_Project_New_Desc".localize())
+                            .clicked()
+                        {
+                            app.do_update(Message::NewProject);
+                        }
+                    });
+                    if ui.icon_button(Icon::Refresh).clicked() {
+                        app.do_update(Message::RefreshProjects);
+                    }
+                });
+            });
+        ui.add_space(8.0);
+    }
+
     pub fn render(&mut self, app: &App, ui: &mut Ui) {
         egui::Frame::none().inner_margin(8.0).show(ui, |ui| {
             let id = Id::new("packer_data");
             self.render_package_deps(app, ui.ctx());
             self.render_package_opts(app, ui.ctx());
+            self.render_projects(app, ui);
             ui.horizontal(|ui| {
                 let source_set = self.source.exists();
                 ui.add_enabled_ui(source_set, |ui| {
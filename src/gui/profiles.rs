@@ -122,6 +122,9 @@ impl ProfileManagerState {
                         if ui.button("Profile_Duplicate".localize()).clicked() {
                             app.do_update(Message::DuplicateProfile(name.to_string()));
                         }
+                        if ui.button("Profile_Export_Modpack".localize()).clicked() {
+                            app.do_update(Message::ExportModpack(name.to_string()));
+                        }
                         if ui.button("Generic_Delete".localize()).clicked() {
                             let message = "Profile_Delete_Confirmation".localize();
                             let vars = std::collections::HashMap::from(
@@ -194,6 +197,9 @@ impl ProfileManagerState {
                             if ui.button("Generic_Close".localize()).clicked() {
                                 app.do_update(Message::CloseProfiles);
                             }
+                            if ui.button("Profile_Import_Modpack".localize()).clicked() {
+                                app.do_update(Message::ImportModpack);
+                            }
                         });
                     });
                 });
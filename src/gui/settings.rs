@@ -43,6 +43,28 @@ fn render_setting<R>(
     ui.horizontal(|ui| add_contents(ui))
 }
 
+/// Edits a list of archive extensions as comma separated text. The text is
+/// kept while editing, so it isn't rebuilt from the list as the user types.
+fn edit_exts(ui: &mut Ui, id: &str, exts: &mut Vec<smartstring::alias::String>) -> egui::Response {
+    let text = ui
+        .get_temp_string(id)
+        .unwrap_or_else(|| ui.create_temp_string(id, Some(exts.join(", "))));
+    let res = ui.text_edit_singleline(&mut *text.write());
+    if res.changed() {
+        *exts = text
+            .read()
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(Into::into)
+            .collect();
+    }
+    if res.lost_focus() {
+        ui.clear_temp_string(id);
+    }
+    res
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 #[serde(tag = "type")]
 pub enum DumpType {
@@ -658,6 +680,40 @@ impl App {
                             ui,
                             |ui| ui.checkbox(&mut settings.system_7z, ""),
                         );
+                        name = "Settings_Watch".localize();
+                        description = "Settings_Watch_Desc".localize();
+                        let profiles = self.core.settings().profiles().collect::<Vec<_>>();
+                        let current_profile = self.core.mod_manager().current_profile().clone();
+                        render_setting(
+                            &name,
+                            &description,
+                            ui,
+                            |ui| {
+                                let mut enabled = settings.watch_config.is_some();
+                                if ui.checkbox(&mut enabled, "").changed() {
+                                    settings.watch_config = enabled.then(|| {
+                                        uk_manager::settings::WatchConfig {
+                                            folder:  Default::default(),
+                                            profile: current_profile.clone(),
+                                        }
+                                    });
+                                }
+                                if let Some(config) = settings.watch_config.as_mut() {
+                                    ui.folder_picker(&mut config.folder);
+                                    egui::ComboBox::new("watch-profile", "Settings_Watch_Profile".localize())
+                                        .selected_text(config.profile.as_str())
+                                        .show_ui(ui, |ui| {
+                                            for profile in profiles.iter() {
+                                                ui.selectable_value(
+                                                    &mut config.profile,
+                                                    profile.clone(),
+                                                    profile.as_str(),
+                                                );
+                                            }
+                                        });
+                                }
+                            },
+                        );
                         name = "Settings_Changelog".localize();
                         description = "Settings_Changelog_Desc".localize();
                         render_setting(
@@ -666,6 +722,38 @@ impl App {
                             ui,
                             |ui| ui.add(Checkbox::new(&mut settings.show_changelog, "")),
                         );
+                        name = "Settings_ModUpdates".localize();
+                        description = "Settings_ModUpdates_Desc".localize();
+                        render_setting(
+                            &name,
+                            &description,
+                            ui,
+                            |ui| ui.add(Checkbox::new(&mut settings.check_mod_updates, "")),
+                        );
+                        name = "Settings_SyncActorInfo".localize();
+                        description = "Settings_SyncActorInfo_Desc".localize();
+                        render_setting(
+                            &name,
+                            &description,
+                            ui,
+                            |ui| ui.add(Checkbox::new(&mut settings.sync_actor_info, "")),
+                        );
+                        name = "Settings_ArchivesMerge".localize();
+                        description = "Settings_ArchivesMerge_Desc".localize();
+                        render_setting(
+                            &name,
+                            &description,
+                            ui,
+                            |ui| edit_exts(ui, "archives_merge", &mut settings.archives.merge),
+                        );
+                        name = "Settings_ArchivesExclude".localize();
+                        description = "Settings_ArchivesExclude_Desc".localize();
+                        render_setting(
+                            &name,
+                            &description,
+                            ui,
+                            |ui| edit_exts(ui, "archives_exclude", &mut settings.archives.exclude),
+                        );
                     });
                 egui::CollapsingHeader::new("Settings_Config_WiiU".localize()).show(ui, |ui| {
                     if ui
@@ -48,10 +48,19 @@ impl TabViewer for super::App {
             match tab {
                 Tabs::Info => {
                     if let Some(mod_) = self.selected.first() {
-                        if let Some(info::Message::RequestOptions) =
-                            info::ModInfo(mod_).show(ui).inner
-                        {
-                            self.do_update(super::Message::RequestOptions(mod_.clone(), true));
+                        match info::ModInfo(mod_, self.inspected.as_ref()).show(ui).inner {
+                            Some(info::Message::RequestOptions) => {
+                                self.do_update(super::Message::RequestOptions(mod_.clone(), true));
+                            }
+                            Some(info::Message::Inspect(file, merged)) => {
+                                self.do_update(super::Message::InspectResource(
+                                    mod_.clone(),
+                                    file,
+                                    merged,
+                                ));
+                            }
+                            Some(info::Message::CloseInspector) => self.inspected = None,
+                            None => (),
                         }
                     } else {
                         ui.centered_and_justified(|ui| {
@@ -10,30 +10,38 @@ use anyhow::{Context, Result};
 use fs_err as fs;
 use http_req::request::RedirectPolicy;
 use join_str::jstr;
+use path_slash::PathExt as _;
+use rustc_hash::{FxHashMap, FxHashSet};
 use serde::Deserialize;
 use strfmt::Format;
-use uk_content::constants::Language;
+use uk_content::{constants::Language, resource::ResourceData};
 use uk_manager::{
-    bnp::convert_bnp,
+    bnp::{convert_bnp, convert_bnp_with_meta},
     core::Manager,
+    dev::{DevBinding, DevState},
     mods::Mod,
+    projects::Project,
     settings::{DeployConfig, Platform, PlatformSettings, UpdatePreference},
     util::get_temp_file,
+    watch::WatchState,
 };
 use uk_mod::{
+    data::DiffFormat,
     pack::{sanitise, ModPacker},
     unpack::{ModReader, ModUnpacker},
-    Manifest, Meta,
+    Manifest, Meta, ModOptionGroup, ModPlatform, OptionGroup,
 };
 use uk_reader::ResourceReader;
 use uk_util::PathExt;
 
-use super::{package::ModPackerBuilder, util::response, Message};
+use super::{info::ResourceView, package::ModPackerBuilder, util::response, Message};
 use crate::INTERFACE;
 
 mod handlers;
+mod mod_updates;
 
 pub use handlers::register_handlers;
+pub use mod_updates::{check_mod_updates, download_mod_update, ModUpdate};
 use uk_localization::string_ext::LocString;
 
 fn is_probably_a_mod_and_has_meta(path: &Path) -> (bool, bool) {
@@ -185,6 +193,158 @@ pub fn open_mod(core: &Manager, path: &Path, meta: Option<Meta>) -> Result<Messa
     Ok(Message::HandleMod(mod_))
 }
 
+/// How often the watched folder is checked for new mods.
+pub const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The result of handling a new archive from the watched folder.
+pub enum WatchOutcome {
+    Installed(Mod),
+    /// The mod needs meta info or option choices from the user
+    Deferred,
+}
+
+pub fn install_watched(
+    core: &Manager,
+    path: &Path,
+    profile: &smartstring::alias::String,
+) -> Result<WatchOutcome> {
+    let mod_ = match open_mod(core, path, None)? {
+        Message::HandleMod(mod_) if mod_.meta.options.is_empty() => mod_,
+        _ => return Ok(WatchOutcome::Deferred),
+    };
+    let platform = core.settings().current_mode;
+    if let ModPlatform::Specific(endian) = mod_.meta.platform {
+        if Platform::from(endian) != platform {
+            anyhow::bail!(
+                "{} is for {}, current mode is {}",
+                mod_.meta.name,
+                mod_.meta.platform,
+                platform
+            );
+        }
+    }
+    let mods = core.mod_manager();
+    mods.create_profile_if(profile)?;
+    let mod_ = mods.add(&mod_.path, Some(profile))?;
+    mods.save_profile(profile)?;
+    Ok(WatchOutcome::Installed(mod_))
+}
+
+/// Checks the watched folder once. Returns the mods installed into the current
+/// profile, and the paths of any mods which need user input.
+pub fn check_watch_folder(
+    core: &Manager,
+    state: &mut WatchState,
+) -> Result<(Vec<Mod>, Vec<PathBuf>)> {
+    let Some(folder) = core
+        .settings()
+        .watch_config
+        .as_ref()
+        .map(|c| c.folder.clone())
+        .filter(|f| f.is_dir())
+    else {
+        return Ok(Default::default());
+    };
+    let found = state.scan(&folder)?;
+    state.save()?;
+    Ok(install_watched_mods(core, found))
+}
+
+/// Installs mods found in the watched folder into the watch profile. Returns
+/// the mods installed into the current profile, and the paths of any mods
+/// which need user input.
+pub fn install_watched_mods(core: &Manager, paths: Vec<PathBuf>) -> (Vec<Mod>, Vec<PathBuf>) {
+    let Some(config) = core.settings().watch_config.clone() else {
+        return Default::default();
+    };
+    let mut installed = vec![];
+    let mut deferred = vec![];
+    for path in paths {
+        log::info!("Found new mod in watched folder: {}", path.display());
+        match install_watched(core, &path, &config.profile) {
+            Ok(WatchOutcome::Installed(mod_)) => {
+                log::info!("Installed {} into profile {}", mod_.meta.name, config.profile);
+                if core.mod_manager().current_profile() == &config.profile {
+                    installed.push(mod_);
+                }
+            }
+            Ok(WatchOutcome::Deferred) => {
+                log::info!("{} needs more information to install", path.display());
+                deferred.push(path);
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to install {} from watched folder: {:?}",
+                    path.display(),
+                    e
+                )
+            }
+        }
+    }
+    (installed, deferred)
+}
+
+/// Installs mods found by the watcher as a GUI task. Mods which need user
+/// input stay pending until they are opened, so they survive a restart.
+pub fn install_watched_task(
+    core: &Manager,
+    state: &parking_lot::Mutex<WatchState>,
+    paths: Vec<PathBuf>,
+) -> Result<Message> {
+    let (installed, deferred) = install_watched_mods(core, paths);
+    let mut state = state.lock();
+    for path in deferred.iter() {
+        state.defer(path.clone());
+    }
+    state.save()?;
+    Ok(Message::WatchedMods(installed, deferred))
+}
+
+/// Scans the watched folder in the background. New mods are sent to the GUI,
+/// which installs them through its task queue.
+pub fn start_watcher(
+    core: Arc<Manager>,
+    state: Arc<parking_lot::Mutex<WatchState>>,
+    sender: flume::Sender<Message>,
+) {
+    std::thread::spawn(move || {
+        let pending = state.lock().pending.clone();
+        if !pending.is_empty() {
+            log::info!("Opening {} mod(s) deferred from watched folder", pending.len());
+            if sender.send(Message::WatchFound(pending)).is_err() {
+                return;
+            }
+        }
+        loop {
+            let folder = core
+                .settings()
+                .watch_config
+                .as_ref()
+                .map(|c| c.folder.clone())
+                .filter(|f| f.is_dir());
+            if let Some(folder) = folder {
+                let found = {
+                    let mut watch = state.lock();
+                    watch.scan(&folder).and_then(|found| {
+                        watch.save()?;
+                        Ok(found)
+                    })
+                };
+                match found {
+                    Ok(found) if !found.is_empty() => {
+                        if sender.send(Message::WatchFound(found)).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(_) => (),
+                    Err(e) => log::warn!("Failed to check watched folder: {:?}", e),
+                }
+            }
+            std::thread::sleep(WATCH_INTERVAL);
+        }
+    });
+}
+
 pub fn apply_changes(core: &Manager, mods: Vec<Mod>, dirty: Option<Manifest>) -> Result<Message> {
     let mod_manager = core.mod_manager();
     log::info!("Applying pending changes to mod configuration");
@@ -207,7 +367,7 @@ pub fn apply_changes(core: &Manager, mods: Vec<Mod>, dirty: Option<Manifest>) ->
                             )
                         })?;
                     mod_manager
-                        .set_enabled_options(m.hash(), m.enabled_options.clone())
+                        .set_enabled_options(m.hash(), m.enabled_options.clone(), None)
                         .with_context(|| {
                             format!("Failed to update options on {}", m.meta.name.as_str())
                         })?;
@@ -246,18 +406,264 @@ pub fn package_mod(core: &Manager, builder: ModPackerBuilder) -> Result<Message>
     let Some(dump) = core.settings().dump() else {
         anyhow::bail!("No dump for current platform")
     };
-    ModPacker::new(
+    let mut packer = ModPacker::new(
         builder.source,
         builder.dest,
         Some(builder.meta),
         [dump].into_iter().collect(),
     )
     .context("Failed to initialize mod packager")?
-    .pack()
-    .context("Failed to package mod")?;
+    .with_archives(&core.settings().archives);
+    if builder.deterministic {
+        packer = packer.deterministic();
+    }
+    packer.pack().context("Failed to package mod")?;
     Ok(Message::ResetPacker)
 }
 
+pub fn new_project(core: &Manager, builder: ModPackerBuilder) -> Result<Message> {
+    let source = (builder.source != PathBuf::default()).then_some(builder.source.as_path());
+    let project = Project::create(&core.settings(), &builder.meta, source)?;
+    log::info!(
+        "Created project {} at {}",
+        project.name,
+        project.path.display()
+    );
+    Ok(Message::EditProject(project))
+}
+
+/// Packages a project to its output path and installs it into the current
+/// profile. The mod installed from the project before, if any, is replaced in
+/// place, keeping its position, enabled state and options.
+pub fn repackage_project(core: &Manager, mut project: Project) -> Result<Message> {
+    let Some(dump) = core.settings().dump() else {
+        anyhow::bail!("No dump for current platform")
+    };
+    let meta = project.meta()?;
+    let output = project.output();
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    log::info!("Packaging project {} to {}", project.name, output.display());
+    ModPacker::new(&project.path, &output, Some(meta), vec![dump])
+        .context("Failed to initialize mod packager")?
+        .with_archives(&core.settings().archives)
+        .pack()
+        .context("Failed to package mod")?;
+    let mods = core.mod_manager();
+    let old_mod = project
+        .config
+        .installed
+        .as_ref()
+        .and_then(|path| mods.all_mods().find(|m| &m.path == path));
+    let mut dirty = Manifest::default();
+    let new_mod = match old_mod {
+        Some(old_mod) => {
+            log::info!("Replacing installed mod {}", old_mod.meta.name);
+            dirty.extend(&old_mod.manifest().unwrap_or_default());
+            mods.reinstall(&output, old_mod.hash())?
+        }
+        None => {
+            log::info!("Installing project {}", project.name);
+            let mut mod_ = mods.add(&output, None)?;
+            mod_.enable_default_options();
+            if !mod_.enabled_options.is_empty() {
+                mods.set_enabled_options(mod_.hash(), mod_.enabled_options.clone(), None)?;
+            }
+            mod_
+        }
+    };
+    mods.save()?;
+    dirty.extend(&new_mod.manifest()?);
+    project.config.installed = Some(new_mod.path.clone());
+    project.save()?;
+    Ok(Message::ProjectPackaged(Some(dirty), output))
+}
+
+/// A file listing several mods to package together, such as all the mods
+/// maintained in one repository.
+#[derive(Debug, Deserialize)]
+pub struct Workspace {
+    /// Folder for packaged mods, defaults to the workspace folder
+    #[serde(default)]
+    pub output: Option<PathBuf>,
+    pub mods:   Vec<WorkspaceMod>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WorkspaceMod {
+    /// Name used to refer to this mod in `depends`
+    pub id:      String,
+    /// Mod root directory
+    pub path:    PathBuf,
+    /// Meta file, defaults to `meta.yml` in the mod root
+    #[serde(default)]
+    pub meta:    Option<PathBuf>,
+    /// Output file, defaults to the mod name in the output folder
+    #[serde(default)]
+    pub output:  Option<PathBuf>,
+    /// IDs of other mods in the workspace this mod depends on
+    #[serde(default)]
+    pub depends: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum WorkspaceResult {
+    Built(PathBuf),
+    Failed(anyhow::Error),
+    /// A dependency failed, so this mod was not built
+    Skipped(String),
+}
+
+impl Workspace {
+    /// Loads a workspace file, resolving paths relative to its folder.
+    pub fn open(path: &Path) -> Result<Self> {
+        let root = path.parent().unwrap_or(Path::new(""));
+        let mut workspace: Workspace = serde_yaml::from_str(
+            &fs::read_to_string(path).context("Failed to read workspace file")?,
+        )
+        .context("Failed to parse workspace file")?;
+        let output = root.join(workspace.output.as_deref().unwrap_or(Path::new("")));
+        for mod_ in workspace.mods.iter_mut() {
+            mod_.path = root.join(&mod_.path);
+            mod_.meta = Some(match mod_.meta.as_ref() {
+                Some(meta) => root.join(meta),
+                None => mod_.path.join("meta.yml"),
+            });
+            mod_.output = mod_.output.as_ref().map(|out| root.join(out));
+        }
+        workspace.output = Some(output);
+        Ok(workspace)
+    }
+
+    /// Sorts the mods so that each comes after all of its dependencies.
+    pub fn build_order(&self) -> Result<Vec<usize>> {
+        fn visit(
+            idx: usize,
+            mods: &[WorkspaceMod],
+            state: &mut [u8],
+            order: &mut Vec<usize>,
+        ) -> Result<()> {
+            match state[idx] {
+                2 => return Ok(()),
+                1 => anyhow::bail!("Dependency cycle involving {}", mods[idx].id),
+                _ => state[idx] = 1,
+            }
+            for dep in mods[idx].depends.iter() {
+                let dep_idx = mods
+                    .iter()
+                    .position(|m| &m.id == dep)
+                    .with_context(|| format!("{} depends on unknown mod {}", mods[idx].id, dep))?;
+                visit(dep_idx, mods, state, order)?;
+            }
+            state[idx] = 2;
+            order.push(idx);
+            Ok(())
+        }
+        let mut state = vec![0; self.mods.len()];
+        let mut order = Vec::with_capacity(self.mods.len());
+        for idx in 0..self.mods.len() {
+            visit(idx, &self.mods, &mut state, &mut order)?;
+        }
+        Ok(order)
+    }
+}
+
+/// Packages every mod in a workspace in dependency order. Each mod gets the
+/// packages built for its dependencies, direct and transitive, as masters,
+/// so resources are diffed against them rather than only against the game
+/// dump.
+pub fn package_workspace(
+    core: &Manager,
+    workspace: &Workspace,
+    deterministic: bool,
+) -> Result<Vec<(String, WorkspaceResult)>> {
+    let Some(dump) = core.settings().dump() else {
+        anyhow::bail!("No dump for current platform")
+    };
+    let order = workspace.build_order()?;
+    let rank: FxHashMap<usize, usize> = order.iter().enumerate().map(|(i, m)| (*m, i)).collect();
+    let output = workspace.output.clone().unwrap_or_default();
+    fs::create_dir_all(&output)?;
+    let mut built: FxHashMap<usize, Mod> = FxHashMap::default();
+    let mut results = Vec::with_capacity(order.len());
+    for idx in order {
+        let ws_mod = &workspace.mods[idx];
+        let deps = ws_mod
+            .depends
+            .iter()
+            .filter_map(|dep| workspace.mods.iter().position(|m| &m.id == dep))
+            .collect::<Vec<_>>();
+        if let Some(failed) = deps.iter().find(|dep| !built.contains_key(dep)) {
+            results.push((
+                ws_mod.id.clone(),
+                WorkspaceResult::Skipped(workspace.mods[*failed].id.clone()),
+            ));
+            continue;
+        }
+        // Transitive dependencies, in build order, so later masters take
+        // precedence like they would in the load order
+        let mut master_ids = vec![];
+        let mut stack = deps.clone();
+        while let Some(dep) = stack.pop() {
+            if !master_ids.contains(&dep) {
+                master_ids.push(dep);
+                stack.extend(
+                    workspace.mods[dep]
+                        .depends
+                        .iter()
+                        .filter_map(|d| workspace.mods.iter().position(|m| &m.id == d)),
+                );
+            }
+        }
+        master_ids.sort_by_key(|id| rank[id]);
+        let result = (|| -> Result<Mod> {
+            let meta_path = ws_mod
+                .meta
+                .as_ref()
+                .with_context(|| format!("No meta file found for {}", ws_mod.id))?;
+            let mut meta = Meta::parse(meta_path)?;
+            meta.masters.extend(master_ids.iter().map(|dep| {
+                let master = &built[dep];
+                (
+                    master.hash(),
+                    (master.meta.name.clone(), master.meta.version.clone()),
+                )
+            }));
+            let mut masters = vec![dump.clone()];
+            for id in master_ids.iter() {
+                masters.push(Arc::new(ResourceReader::from_mod_package(Box::new(
+                    ModReader::open(&built[id].path, vec![])?,
+                ))));
+            }
+            let dest = ws_mod
+                .output
+                .clone()
+                .unwrap_or_else(|| output.join(sanitise(&meta.name)).with_extension("zip"));
+            log::info!("Packaging {} to {}", ws_mod.id, dest.display());
+            let mut packer = ModPacker::new(&ws_mod.path, dest, Some(meta), masters)
+                .context("Failed to initialize mod packager")?
+                .with_archives(&core.settings().archives);
+            if deterministic {
+                packer = packer.deterministic();
+            }
+            let path = packer.pack().context("Failed to package mod")?;
+            Ok(Mod::from_reader(ModReader::open_peek(path, vec![])?))
+        })();
+        match result {
+            Ok(mod_) => {
+                results.push((ws_mod.id.clone(), WorkspaceResult::Built(mod_.path.clone())));
+                built.insert(idx, mod_);
+            }
+            Err(e) => {
+                log::error!("Failed to package {}: {:?}", ws_mod.id, e);
+                results.push((ws_mod.id.clone(), WorkspaceResult::Failed(e)));
+            }
+        }
+    }
+    Ok(results)
+}
+
 pub fn dev_update_mods(core: &Manager, mods: Vec<Mod>) -> Result<Message> {
     let mut dirty = Manifest::default();
     for mod_ in mods {
@@ -279,6 +685,7 @@ pub fn dev_update_mods(core: &Manager, mods: Vec<Mod>) -> Result<Message> {
                 [core.settings().dump().unwrap()].into_iter().collect(),
             )
             .context("Failed to initialize mod packager")?
+            .with_archives(&core.settings().archives)
             .pack()
             .context("Failed to package mod")?;
             let new_mod = ModReader::open_peek(mod_.path, vec![])?;
@@ -292,6 +699,128 @@ pub fn dev_update_mods(core: &Manager, mods: Vec<Mod>) -> Result<Message> {
     Ok(Message::ResetMods(Some(dirty)))
 }
 
+/// How often source folders bound in dev mode are checked for changes.
+pub const DEV_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Repackages the changed source files of a mod bound in dev mode, swaps the
+/// new build in for the installed mod, and applies only the files which
+/// changed.
+pub fn dev_reload(core: &Manager, binding: &DevBinding, changed: &[PathBuf]) -> Result<Mod> {
+    let old_mod = core
+        .mod_manager()
+        .all_mods()
+        .find(|m| m.path == binding.mod_path)
+        .context("The bound mod is no longer installed")?;
+    log::info!(
+        "Reloading {} from {}",
+        old_mod.meta.name,
+        binding.source.display()
+    );
+    let Some(dump) = core.settings().dump() else {
+        anyhow::bail!("No dump for current platform")
+    };
+    let meta = Meta::parse(binding.source.join("meta.yml"))?;
+    let temp = get_temp_file().clone();
+    let (packed, dirty) = ModPacker::new(&binding.source, &temp, Some(meta), vec![dump])
+        .context("Failed to initialize mod packager")?
+        .with_archives(&core.settings().archives)
+        .pack_changed(&old_mod.path, changed)
+        .context("Failed to repackage mod")?;
+    // Copy next to the installed package first so it is swapped in whole
+    let staged = old_mod.path.with_extension("zip.tmp");
+    fs::copy(&packed, &staged)?;
+    fs::rename(&staged, &old_mod.path)?;
+    let mut new_mod = Mod::from_reader(ModReader::open_peek(&old_mod.path, vec![])?);
+    new_mod.enabled_options = old_mod.enabled_options.clone();
+    let mods = core.mod_manager();
+    let new_mod = mods.replace(new_mod, old_mod.hash())?;
+    mods.save()?;
+    drop(mods);
+    if new_mod.enabled {
+        log::info!("Applying {} changed file(s)", changed.len());
+        let deploy_manager = core.deploy_manager();
+        deploy_manager
+            .apply(Some(dirty))
+            .context("Failed to apply reloaded mod")?;
+        if core
+            .settings()
+            .platform_config()
+            .and_then(|c| c.deploy_config.as_ref().map(|c| c.auto))
+            .unwrap_or(false)
+        {
+            deploy_manager
+                .deploy()
+                .context("Failed to deploy reloaded mod")?;
+        }
+    }
+    Ok(new_mod)
+}
+
+/// Scans the sources of mods bound in dev mode in the background, sending
+/// their changes to be reloaded.
+pub fn start_dev_watcher(dev: Arc<parking_lot::Mutex<DevState>>, sender: flume::Sender<Message>) {
+    std::thread::spawn(move || {
+        loop {
+            // Scanning large sources takes a while, so the lock is only held
+            // to record the results
+            let sources: Vec<_> = dev
+                .lock()
+                .bindings
+                .iter()
+                .map(|b| (b.mod_path.clone(), b.source.clone()))
+                .collect();
+            let snapshots: Vec<_> = sources
+                .into_iter()
+                .map(|(mod_path, source)| (mod_path, DevBinding::snapshot(&source)))
+                .collect();
+            let changes: Vec<_> = {
+                let mut dev = dev.lock();
+                let first_scan = dev.bindings.iter().any(|b| b.is_new());
+                let changes: Vec<_> = snapshots
+                    .into_iter()
+                    .filter_map(|(mod_path, stamps)| {
+                        // The binding may have been removed during the scan
+                        let binding = dev.bindings.iter_mut().find(|b| b.mod_path == mod_path)?;
+                        let changed = binding.update(stamps);
+                        (!changed.is_empty()).then(|| (binding.clone(), changed))
+                    })
+                    .collect();
+                if first_scan || !changes.is_empty() {
+                    if let Err(e) = dev.save() {
+                        log::warn!("Failed to save dev mode state: {e:?}");
+                    }
+                }
+                changes
+            };
+            for (binding, changed) in changes {
+                if sender.send(Message::DevChanged(binding, changed)).is_err() {
+                    return;
+                }
+            }
+            std::thread::sleep(DEV_INTERVAL);
+        }
+    });
+}
+
+/// Checks which files a mod's options both change, in the background so the
+/// option picker opens right away.
+pub fn check_option_conflicts(mod_: Mod, sender: flume::Sender<Message>) {
+    std::thread::spawn(move || {
+        let conflicts = ModReader::open_peek(&mod_.path, vec![])
+            .and_then(|reader| reader.option_conflicts())
+            .unwrap_or_else(|e| {
+                log::warn!(
+                    "Failed to check {} for option conflicts: {e:?}",
+                    mod_.meta.name
+                );
+                vec![]
+            });
+        if !conflicts.is_empty() {
+            let _ = sender.send(Message::OptionConflicts(mod_.hash(), conflicts));
+        }
+    });
+}
+
 pub fn extract_mods(core: &Manager, mods: Vec<Mod>) -> Result<Message> {
     let mut errors = vec![];
     if let Some(folder) = rfd::FileDialog::new()
@@ -333,6 +862,71 @@ pub fn extract_mods(core: &Manager, mods: Vec<Mod>) -> Result<Message> {
     }
 }
 
+fn resource_text(resource: &ResourceData) -> Result<String> {
+    match resource {
+        ResourceData::Binary(data) => {
+            Ok(format!(
+                "# {} ({} bytes)",
+                "Info_Inspect_Binary".localize(),
+                data.len()
+            ))
+        }
+        resource => Ok(DiffFormat::Yaml.to_text(resource)?),
+    }
+}
+
+pub fn inspect_resource(core: &Manager, mod_: Mod, file: PathBuf, merged: bool) -> Result<Message> {
+    let name = file.to_slash_lossy();
+    let panes = if merged {
+        let settings = core.settings();
+        let config = settings
+            .platform_config()
+            .context("No config for current platform. Have you configured your settings?")?;
+        let mods = core
+            .mod_manager()
+            .mods()
+            .map(|m| {
+                ModReader::open_peek(&m.path, m.enabled_options.clone())
+                    .with_context(|| format!("Failed to open mod: {}", m.meta.name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let unpacker = ModUnpacker::new(
+            config.dump.clone(),
+            settings.current_mode.into(),
+            config.language,
+            mods,
+            settings.merged_dir(),
+        );
+        let (stock, merged) = unpacker.resolve(&name)?;
+        vec![
+            ("Info_Inspect_Merged".localize(), resource_text(&merged)?),
+            ("Info_Inspect_Stock".localize(), match stock {
+                Some(stock) => resource_text(&stock)?,
+                None => format!("# {}", "Info_Inspect_Stock_None".localize()),
+            }),
+        ]
+    } else {
+        let reader = ModReader::open_peek(&mod_.path, mod_.enabled_options.clone())?;
+        let diff = reader
+            .get_versions(&file)?
+            .iter()
+            .map(|data| {
+                let resource: ResourceData = minicbor_ser::from_slice(data)
+                    .with_context(|| format!("Failed to decode {name} from mod"))?;
+                resource_text(&resource)
+            })
+            .collect::<Result<Vec<_>>>()?
+            .join("---\n");
+        vec![("Info_Inspect_Diff".localize(), diff)]
+    };
+    Ok(Message::ShowResource(ResourceView {
+        mod_hash: mod_.hash(),
+        file,
+        merged,
+        panes,
+    }))
+}
+
 pub fn parse_meta(file: PathBuf) -> Result<Message> {
     match file.extension().and_then(|x| x.to_str()).unwrap() {
         "txt" => ModPacker::parse_rules(file),
@@ -664,8 +1258,24 @@ struct BcmlSettings {
     store_dir: PathBuf,
 }
 
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BcmlModInfo {
+    name: String,
+    id: String,
+    depends: Vec<String>,
+}
+
+#[derive(Debug)]
+struct BcmlMod {
+    dir: PathBuf,
+    priority: usize,
+    info: BcmlModInfo,
+}
+
 pub fn migrate_bcml(core: Arc<Manager>) -> Result<Message> {
     log::info!("Attempting to import BCML settings");
+    let mut report = vec![];
     let current_mode = core.settings().current_mode;
     let settings_path = if cfg!(windows) {
         dirs2::data_local_dir()
@@ -720,7 +1330,7 @@ pub fn migrate_bcml(core: Arc<Manager>) -> Result<Message> {
         }
         core.reload()?;
         log::info!("Attempting to import BCML Wii U mods");
-        import_mods(&core, bcml_settings.store_dir.join("mods"))?;
+        import_mods(&core, bcml_settings.store_dir.join("mods"), &mut report)?;
     }
     if let Some(game_dir) = bcml_settings
         .game_dir_nx
@@ -750,7 +1360,7 @@ pub fn migrate_bcml(core: Arc<Manager>) -> Result<Message> {
         }
         core.reload()?;
         log::info!("Attempting to import BCML Switch mods");
-        import_mods(&core, bcml_settings.store_dir.join("mods_nx"))?;
+        import_mods(&core, bcml_settings.store_dir.join("mods_nx"), &mut report)?;
     }
     let mode_changed = core.settings().current_mode != current_mode;
     if mode_changed {
@@ -761,14 +1371,18 @@ pub fn migrate_bcml(core: Arc<Manager>) -> Result<Message> {
         }
         core.reload()?;
     }
-    Ok(Message::HandleSettings)
+    for line in report.iter() {
+        log::warn!("{line}");
+    }
+    Ok(Message::HandleMigration(report))
 }
 
-fn import_mods(core: &Manager, mod_dir: PathBuf) -> Result<()> {
+fn import_mods(core: &Manager, mod_dir: PathBuf, report: &mut Vec<String>) -> Result<()> {
     if !mod_dir.exists() {
-        Ok(())
-    } else {
-        for dir in fs::read_dir(mod_dir)?.filter_map(|e| {
+        return Ok(());
+    }
+    let mut bcml_mods = fs::read_dir(mod_dir)?
+        .filter_map(|e| {
             e.ok().and_then(|e| {
                 e.file_type().ok().and_then(|t| {
                     (t.is_dir()
@@ -780,17 +1394,182 @@ fn import_mods(core: &Manager, mod_dir: PathBuf) -> Result<()> {
                     .then(|| e.path())
                 })
             })
-        }) {
-            match convert_bnp(core, &dir) {
-                Ok(path) => {
-                    core.mod_manager().add(&path, None)?;
+        })
+        .map(|dir| {
+            let folder = dir
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default()
+                .to_owned();
+            let priority = folder
+                .split_once('_')
+                .and_then(|(p, _)| p.parse().ok())
+                .unwrap_or_else(|| {
+                    report.push(format!("{folder}: no BCML priority, placed first in load order"));
+                    0
+                });
+            let info = fs::read_to_string(dir.join("info.json"))
+                .ok()
+                .and_then(|text| serde_json::from_str::<BcmlModInfo>(&text).ok())
+                .unwrap_or_else(|| {
+                    report.push(format!("{folder}: could not read info.json"));
+                    BcmlModInfo {
+                        name: folder,
+                        ..Default::default()
+                    }
+                });
+            BcmlMod {
+                dir,
+                priority,
+                info,
+            }
+        })
+        .collect::<Vec<_>>();
+    bcml_mods.sort_by_key(|m| m.priority);
+
+    // BCML refers to dependencies by mod ID, but older mods sometimes only
+    // list a name, so we accept both.
+    let lookup: FxHashMap<&str, usize> = bcml_mods
+        .iter()
+        .enumerate()
+        .flat_map(|(i, m)| [(m.info.id.as_str(), i), (m.info.name.as_str(), i)])
+        .filter(|(key, _)| !key.is_empty())
+        .collect();
+    // Dependencies are converted first so they can be recorded as masters
+    fn visit(
+        idx: usize,
+        mods: &[BcmlMod],
+        lookup: &FxHashMap<&str, usize>,
+        visited: &mut [bool],
+        order: &mut Vec<usize>,
+    ) {
+        if visited[idx] {
+            return;
+        }
+        visited[idx] = true;
+        for dep in mods[idx].info.depends.iter() {
+            if let Some(dep_idx) = lookup.get(dep.as_str()) {
+                visit(*dep_idx, mods, lookup, visited, order);
+            }
+        }
+        order.push(idx);
+    }
+    let mut visited = vec![false; bcml_mods.len()];
+    let mut order = Vec::with_capacity(bcml_mods.len());
+    for idx in 0..bcml_mods.len() {
+        visit(idx, &bcml_mods, &lookup, &mut visited, &mut order);
+    }
+
+    let mod_manager = core.mod_manager();
+    let mut converted: FxHashMap<usize, Mod> = FxHashMap::default();
+    for idx in order {
+        let bcml = &bcml_mods[idx];
+        let name = bcml.info.name.as_str();
+        let masters = bcml
+            .info
+            .depends
+            .iter()
+            .filter_map(|dep| {
+                match lookup.get(dep.as_str()).and_then(|i| converted.get(i)) {
+                    Some(master) => {
+                        Some((
+                            master.hash(),
+                            (master.meta.name.clone(), master.meta.version.clone()),
+                        ))
+                    }
+                    None => {
+                        report.push(format!(
+                            "{name}: dependency {dep} is not installed or could not be imported"
+                        ));
+                        None
+                    }
                 }
-                Err(e) => log::warn!("Failed to import BCML mod: {}", e),
+            })
+            .collect::<Vec<_>>();
+        // BCML deletes the folders of unselected options on install, so the
+        // remaining ones are the user's selections.
+        let selected: FxHashSet<PathBuf> = fs::read_dir(bcml.dir.join("options"))
+            .map(|entries| {
+                entries
+                    .filter_map(|e| {
+                        e.ok()
+                            .filter(|e| e.path().is_dir())
+                            .map(|e| PathBuf::from(e.file_name()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut dropped = vec![];
+        let result = convert_bnp_with_meta(core, &bcml.dir, |meta| {
+            meta.masters = masters.into_iter().collect();
+            for group in meta.options.iter_mut() {
+                group.options_mut().retain(|opt| {
+                    let keep = selected.contains(&opt.path);
+                    if !keep {
+                        dropped.push(opt.name.clone());
+                    }
+                    keep
+                });
+                match group {
+                    OptionGroup::Exclusive(group) => {
+                        group.default = group
+                            .default
+                            .take()
+                            .filter(|d| selected.contains(d))
+                            .or_else(|| group.options.first().map(|o| o.path.clone()));
+                    }
+                    OptionGroup::Multiple(group) => group.defaults.retain(|d| selected.contains(d)),
+                }
+            }
+            meta.options.retain(|group| !group.options().is_empty());
+        });
+        for option in dropped {
+            report.push(format!(
+                "{name}: option \"{option}\" was not installed in BCML and has been removed"
+            ));
+        }
+        let mod_ = match result.and_then(|path| mod_manager.add(&path, None)) {
+            Ok(mod_) => mod_,
+            Err(e) => {
+                log::warn!("Failed to import BCML mod: {:?}", e);
+                report.push(format!("{name}: could not be imported ({e})"));
+                continue;
+            }
+        };
+        let options = mod_
+            .meta
+            .options
+            .iter()
+            .flat_map(|group| group.options().iter().cloned())
+            .collect::<Vec<_>>();
+        // BCML doesn't enforce option groups, so a bad selection falls back
+        // to the mod's defaults instead of stopping the migration
+        if !options.is_empty() {
+            if let Err(e) = uk_manager::presets::validate_options(&mod_.meta, options)
+                .and_then(|options| mod_manager.set_enabled_options(mod_.hash(), options, None))
+            {
+                log::warn!("Failed to carry over BCML options: {:?}", e);
+                report.push(format!(
+                    "{name}: options could not be carried over and the defaults are used ({e})"
+                ));
             }
         }
-        core.mod_manager().save()?;
-        Ok(())
+        converted.insert(idx, mod_);
     }
+
+    log::info!("Applying BCML load order");
+    let imported = (0..bcml_mods.len())
+        .filter_map(|i| converted.get(&i).map(|m| m.hash()))
+        .collect::<Vec<_>>();
+    let mut load_order = mod_manager
+        .all_mods()
+        .map(|m| m.hash())
+        .filter(|h| !imported.contains(h))
+        .collect::<Vec<_>>();
+    load_order.extend(imported);
+    mod_manager.set_order(load_order);
+    mod_manager.save()?;
+    Ok(())
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -1060,4 +1839,34 @@ mod tests {
         let core = uk_manager::core::Manager::init().unwrap();
         super::apply_changes(&core, vec![], None).unwrap();
     }
+
+    #[test]
+    fn workspace_order() {
+        let workspace: super::Workspace = serde_yaml::from_str(
+            "mods:
+  - id: patch
+    path: patch
+    depends: [addon, base]
+  - id: addon
+    path: addon
+    depends: [base]
+  - id: base
+    path: base
+",
+        )
+        .unwrap();
+        assert_eq!(workspace.build_order().unwrap(), vec![2, 1, 0]);
+        let cyclic: super::Workspace = serde_yaml::from_str(
+            "mods:
+  - id: a
+    path: a
+    depends: [b]
+  - id: b
+    path: b
+    depends: [a]
+",
+        )
+        .unwrap();
+        assert!(cyclic.build_order().is_err());
+    }
 }
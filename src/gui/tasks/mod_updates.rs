@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use fs_err as fs;
+use serde::Deserialize;
+use serde_json::Value;
+use uk_manager::{core::Manager, mods::Mod, util::get_temp_file};
+use uk_mod::pack::sanitise;
+
+use super::{open_mod, response, Message};
+
+pub const GAMEBANANA_API: &str = "https://api.gamebanana.com";
+
+/// A newer version of an installed mod, found on its GameBanana page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModUpdate {
+    pub mod_hash:     usize,
+    pub version:      String,
+    pub download_url: String,
+    pub file_name:    String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GbFile {
+    #[serde(rename = "_sFile")]
+    name: String,
+    #[serde(rename = "_sDownloadUrl")]
+    download_url: String,
+    #[serde(rename = "_tsDateAdded")]
+    added: u64,
+}
+
+/// Gets the ID from a GameBanana mod page URL, like
+/// `https://gamebanana.com/mods/12345`.
+pub fn gamebanana_mod_id(url: &str) -> Option<u64> {
+    let url = url.split(['?', '#']).next()?;
+    let mut parts = url
+        .split_once("gamebanana.com/")?
+        .1
+        .split('/')
+        .filter(|p| !p.is_empty());
+    if parts.next()? != "mods" {
+        return None;
+    }
+    parts.find_map(|p| p.parse().ok())
+}
+
+/// Gets the version of a mod's latest update and its newest file. The API
+/// returns the requested fields in order, with PHP's habit of sending an
+/// empty list in place of an empty object.
+fn latest_release(api: &str, id: u64) -> Result<Option<(String, GbFile)>> {
+    let data = response(&format!(
+        "{api}/Core/Item/Data?itemtype=Mod&itemid={id}&fields=Updates().aGetLatestUpdates(),\
+         Files().aFiles()"
+    ))?;
+    let (updates, files): (Value, Value) =
+        serde_json::from_slice(&data).context("Failed to parse GameBanana response")?;
+    let version = updates
+        .get(0)
+        .and_then(|update| update.get("_sVersion"))
+        .and_then(Value::as_str)
+        .map(|v| v.trim().trim_start_matches('v'))
+        .filter(|v| !v.is_empty());
+    let file = files
+        .as_object()
+        .into_iter()
+        .flat_map(|files| files.values())
+        .filter_map(|file| serde_json::from_value::<GbFile>(file.clone()).ok())
+        .max_by_key(|file| file.added);
+    Ok(version
+        .zip(file)
+        .map(|(version, file)| (version.to_owned(), file)))
+}
+
+fn is_newer(current: &str, latest: &str) -> bool {
+    match (
+        lenient_semver::parse(current.trim_start_matches('v')),
+        lenient_semver::parse(latest),
+    ) {
+        (Ok(current), Ok(latest)) => latest > current,
+        _ => false,
+    }
+}
+
+/// Checks whether a mod's GameBanana page has a newer version than the one
+/// installed. Mods without a GameBanana URL are skipped.
+pub fn check_mod_update(api: &str, mod_: &Mod) -> Result<Option<ModUpdate>> {
+    let Some(id) = mod_.meta.url.as_deref().and_then(gamebanana_mod_id) else {
+        return Ok(None);
+    };
+    let Some((version, file)) = latest_release(api, id)? else {
+        return Ok(None);
+    };
+    Ok(is_newer(&mod_.meta.version, &version).then(|| {
+        ModUpdate {
+            mod_hash: mod_.hash(),
+            version,
+            download_url: file.download_url,
+            file_name: file.name,
+        }
+    }))
+}
+
+/// Checks all mods in the current profile for updates in the background.
+pub fn check_mod_updates(core: Arc<Manager>, sender: flume::Sender<Message>) {
+    std::thread::spawn(move || {
+        let mods: Vec<Mod> = core.mod_manager().all_mods().collect();
+        let updates: Vec<ModUpdate> = mods
+            .iter()
+            .filter_map(|mod_| {
+                check_mod_update(GAMEBANANA_API, mod_)
+                    .inspect_err(|e| {
+                        log::warn!("Failed to check {} for updates: {e:?}", mod_.meta.name)
+                    })
+                    .ok()
+                    .flatten()
+            })
+            .collect();
+        if !updates.is_empty() {
+            log::info!("Found updates for {} mod(s)", updates.len());
+            if sender.send(Message::ModUpdatesFound(updates)).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+/// Downloads a mod update and opens it, to replace the installed version.
+pub fn download_mod_update(core: &Manager, update: &ModUpdate) -> Result<Message> {
+    log::info!("Downloading version {} from GameBanana…", update.version);
+    let data = response(&update.download_url)
+        .with_context(|| format!("Failed to download mod from {}", update.download_url))?;
+    let tmp = get_temp_file().with_file_name(sanitise(&update.file_name));
+    fs::write(tmp.as_path(), data).context("Failed to save mod to temp file")?;
+    open_mod(core, &tmp, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+
+    /// Serves one JSON response on a local port, sending back the request
+    /// line it received.
+    fn mock_server(body: &'static str) -> (String, flume::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (send, recv) = flume::bounded(1);
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 4096];
+            let len = stream.read(&mut buf).unwrap();
+            let request = String::from_utf8_lossy(&buf[..len]);
+            send.send(request.lines().next().unwrap_or_default().to_owned())
+                .unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: \
+                 {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+        (url, recv)
+    }
+
+    #[test]
+    fn gamebanana_update() {
+        assert_eq!(
+            super::gamebanana_mod_id("https://gamebanana.com/mods/12345"),
+            Some(12345)
+        );
+        assert_eq!(
+            super::gamebanana_mod_id("https://gamebanana.com/mods/download/12345#FileInfo_1"),
+            Some(12345)
+        );
+        assert_eq!(
+            super::gamebanana_mod_id("https://github.com/a/mods/1"),
+            None
+        );
+
+        let (api, requests) = mock_server(
+            r#"[
+                [{"_sTitle": "Bug fixes", "_sVersion": "v1.2.0"}],
+                {
+                    "100": {"_sFile": "mod_old.zip", "_sDownloadUrl": "http://x/dl/100", "_tsDateAdded": 1000},
+                    "101": {"_sFile": "mod.zip", "_sDownloadUrl": "http://x/dl/101", "_tsDateAdded": 2000}
+                }
+            ]"#,
+        );
+        let (version, file) = super::latest_release(&api, 12345).unwrap().unwrap();
+        assert!(requests.recv().unwrap().contains("itemid=12345"));
+        assert_eq!(version, "1.2.0");
+        assert_eq!(file.name, "mod.zip");
+        assert_eq!(file.download_url, "http://x/dl/101");
+        assert!(super::is_newer("1.1.3", &version));
+        assert!(!super::is_newer("v1.2.0", &version));
+
+        let (api, _) = mock_server("[[], []]");
+        assert!(super::latest_release(&api, 1).unwrap().is_none());
+    }
+}
@@ -46,6 +46,55 @@ impl App {
                 Message::CloseError => self.error = None,
                 Message::CloseConfirm => self.confirm = None,
                 Message::ShowAbout => self.show_about = true,
+                Message::ShowConflicts => match self.core.deploy_manager().conflicts() {
+                    Ok(conflicts) => self.conflicts = Some(conflicts),
+                    Err(e) => self.do_update(Message::Error(e)),
+                },
+                Message::CloseConflicts => self.conflicts = None,
+                Message::ResolveConflict(resolution) => {
+                    match self.core.mod_manager().set_resolution(resolution.clone()) {
+                        Ok(()) => {
+                            // Rebuild the resource on the next apply. Manifests
+                            // don't list files inside archives, so those take
+                            // every file from the mods involved.
+                            let involved: Vec<_> = self
+                                .conflicts
+                                .as_ref()
+                                .and_then(|c| c.fields.get(&resolution.resource))
+                                .into_iter()
+                                .flatten()
+                                .flat_map(|c| [c.mod_a.clone(), c.mod_b.clone()])
+                                .collect();
+                            let mods = self.mods.iter().filter(|m| involved.contains(&m.meta.name));
+                            for mod_ in mods {
+                                match mod_.manifest() {
+                                    Ok(manifest) => {
+                                        let files = manifest.files_for(&resolution.resource);
+                                        if files.is_empty() {
+                                            self.dirty_mut().extend(&manifest);
+                                        } else {
+                                            self.dirty_mut().extend(&files);
+                                        }
+                                    }
+                                    Err(e) => self.do_update(Message::Error(e)),
+                                }
+                            }
+                            if let (ResolutionChoice::Mod(winner), Some(found)) = (
+                                &resolution.choice,
+                                self.conflicts
+                                    .as_mut()
+                                    .and_then(|c| c.fields.get_mut(&resolution.resource)),
+                            ) {
+                                found
+                                    .iter_mut()
+                                    .filter(|c| c.path == resolution.path)
+                                    .for_each(|c| c.winner = winner.clone());
+                            }
+                            self.do_update(Message::Toast("Conflicts_Resolved".localize()));
+                        }
+                        Err(e) => self.do_update(Message::Error(e)),
+                    }
+                }
                 Message::CloseAbout => self.show_about = false,
                 Message::CloseProfiles => self.profiles_state.borrow_mut().show = false,
                 Message::Confirm(msg, prompt) => {
@@ -205,6 +254,82 @@ impl App {
                         Ok(Message::ReloadProfiles)
                     });
                 }
+                Message::ExportLoadOrder => {
+                    if let Some(dest) = rfd::FileDialog::new()
+                        .add_filter("Load Order (*.yml)", &["yml"])
+                        .set_title("Menu_File_ExportLoadOrder".localize())
+                        .set_file_name("load_order.yml")
+                        .save_file()
+                    {
+                        if let Err(e) = LoadOrderFile::new(&self.mods).save_to(dest) {
+                            self.do_update(Message::Error(e));
+                        }
+                    }
+                }
+                Message::ImportLoadOrder => {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Load Order (*.yml)", &["yml"])
+                        .set_title("Menu_File_ImportLoadOrder".localize())
+                        .pick_file()
+                    {
+                        match LoadOrderFile::open(path).and_then(|file| file.apply_to(&self.mods)) {
+                            Ok(import) => {
+                                self.mods = import.mods;
+                                self.dirty_mut().extend(&import.dirty);
+                                self.do_update(Message::RefreshModsDisplay);
+                                if !import.missing.is_empty() {
+                                    self.do_update(Message::Confirm(
+                                        Box::new(Message::Noop),
+                                        format!(
+                                            "{}\n\n{}",
+                                            "Menu_File_ImportLoadOrder_Missing".localize(),
+                                            import
+                                                .missing
+                                                .iter()
+                                                .map(|entry| {
+                                                    match entry.url.as_ref() {
+                                                        Some(url) => {
+                                                            format!("{} ({})", entry.name, url)
+                                                        }
+                                                        None => entry.name.to_string(),
+                                                    }
+                                                })
+                                                .collect::<Vec<_>>()
+                                                .join("\n")
+                                        ),
+                                    ));
+                                }
+                            }
+                            Err(e) => self.do_update(Message::Error(e)),
+                        }
+                    }
+                }
+                Message::ExportModpack(profile) => {
+                    if let Some(dest) = rfd::FileDialog::new()
+                        .add_filter("UKMM Modpack (*.zip)", &["zip"])
+                        .set_title("Profile_Export_Modpack".localize())
+                        .set_file_name(format!("{}.zip", sanitise(&profile)))
+                        .save_file()
+                    {
+                        self.do_task(move |core| {
+                            let profile = smartstring::alias::String::from(profile);
+                            uk_manager::modpack::export(&core, Some(&profile), &dest)?;
+                            Ok(Message::Noop)
+                        });
+                    }
+                }
+                Message::ImportModpack => {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("UKMM Modpack (*.zip)", &["zip"])
+                        .set_title("Profile_Import_Modpack".localize())
+                        .pick_file()
+                    {
+                        self.do_task(move |core| {
+                            uk_manager::modpack::import(&core, &path, None)?;
+                            Ok(Message::ResetMods(None))
+                        });
+                    }
+                }
                 Message::RenameProfile(profile, rename) => {
                     self.do_task(move |core| {
                         let profiles_dir = core.settings().profiles_dir();
@@ -320,7 +445,7 @@ impl App {
                             let mod_ = mods.add(&tmp_mod_.path, None)?;
                             let hash = mod_.as_map_id();
                             if !tmp_mod_.enabled_options.is_empty() {
-                                mods.set_enabled_options(hash, tmp_mod_.enabled_options)?;
+                                mods.set_enabled_options(hash, tmp_mod_.enabled_options, None)?;
                             }
                             mods.save()?;
                             log::info!("Added mod {} to current profile", mod_.meta.name.as_str());
@@ -357,10 +482,83 @@ impl App {
                         self.do_task(move |core| tasks::open_mod(&core, &path, None));
                     }
                 }
+                Message::ModUpdatesFound(updates) => {
+                    self.mod_updates = updates.into_iter().map(|u| (u.mod_hash, u)).collect();
+                }
+                Message::DownloadModUpdate(mod_, update) => {
+                    self.mod_updates.remove(&mod_.hash());
+                    self.update_mod = Some(mod_);
+                    self.do_task(move |core| tasks::download_mod_update(&core, &update));
+                }
                 Message::DevUpdate => {
                     let mods = self.selected.clone();
                     self.do_task(move |core| tasks::dev_update_mods(&core, mods));
                 }
+                Message::DevBind(mod_) => {
+                    if let Some(folder) = rfd::FileDialog::new()
+                        .set_title("Mod_Dev_Watch_Folder".localize())
+                        .pick_folder()
+                    {
+                        match self.dev.lock().bind(mod_.path.clone(), folder) {
+                            Ok(()) => {
+                                self.do_update(Message::Toast(format!(
+                                    "{} {}",
+                                    mod_.meta.name,
+                                    "Mod_Dev_Watching".localize()
+                                )))
+                            }
+                            Err(e) => self.do_update(Message::Error(e)),
+                        }
+                    }
+                }
+                Message::DevUnbind(mod_) => {
+                    let mut dev = self.dev.lock();
+                    dev.unbind(&mod_.path);
+                    if let Err(e) = dev.save() {
+                        self.do_update(Message::Error(e));
+                    }
+                }
+                Message::DevChanged(binding, mut changed) => {
+                    match self
+                        .dev_queue
+                        .iter_mut()
+                        .find(|(b, _)| b.mod_path == binding.mod_path)
+                    {
+                        Some((_, queued)) => {
+                            queued.append(&mut changed);
+                            queued.sort();
+                            queued.dedup();
+                        }
+                        None => self.dev_queue.push((binding, changed)),
+                    }
+                }
+                Message::DevReloadFailed(mod_path, changed, e) => {
+                    if let Some(binding) = self
+                        .dev
+                        .lock()
+                        .bindings
+                        .iter_mut()
+                        .find(|b| b.mod_path == mod_path)
+                    {
+                        binding.retry(changed);
+                    }
+                    self.do_update(Message::Error(e));
+                }
+                Message::DevReloaded(mod_) => {
+                    self.busy.set(false);
+                    self.mods = self.core.mod_manager().all_mods().collect();
+                    self.selected
+                        .iter_mut()
+                        .filter(|m| m.path == mod_.path)
+                        .for_each(|m| *m = mod_.clone());
+                    self.do_update(Message::RefreshModsDisplay);
+                    info::ROOTS.write().clear();
+                    self.do_update(Message::Toast(format!(
+                        "{} {}",
+                        mod_.meta.name,
+                        "Mod_Dev_Reloaded".localize()
+                    )));
+                }
                 Message::ToggleMods(mods, enabled) => {
                     let mods = mods.as_ref().unwrap_or(&self.selected);
                     let dirty = mods.iter().try_fold(
@@ -389,9 +587,7 @@ impl App {
                     self.mods = self.core.mod_manager().all_mods().collect();
                     self.do_update(Message::RefreshModsDisplay);
                     self.busy.set(false);
-                    if let Some(path) = self.install_queue.pop_front() {
-                        self.do_task(move |core| tasks::open_mod(&core, &path, None));
-                    } else if !self.error_queue.is_empty() {
+                    if !self.open_next_mod() && !self.error_queue.is_empty() {
                         let msg = self
                             .error_queue
                             .drain(..)
@@ -405,6 +601,41 @@ impl App {
                         )));
                     }
                 }
+                Message::WatchFound(paths) => {
+                    for path in paths {
+                        if !self.watch_queue.contains(&path) {
+                            self.watch_queue.push(path);
+                        }
+                    }
+                }
+                Message::WatchedMods(installed, deferred) => {
+                    self.busy.set(false);
+                    if !installed.is_empty() {
+                        for mod_ in installed.iter() {
+                            if let Ok(manifest) = mod_.manifest() {
+                                self.dirty_mut().extend(&manifest);
+                            }
+                        }
+                        self.mods = self.core.mod_manager().all_mods().collect();
+                        self.do_update(Message::RefreshModsDisplay);
+                        let vars = std::collections::HashMap::from([(
+                            "mod_names".to_string(),
+                            installed
+                                .iter()
+                                .map(|m| m.meta.name.as_str())
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        )]);
+                        self.do_update(Message::Toast(
+                            "Settings_Watch_Installed"
+                                .localize()
+                                .format(&vars)
+                                .unwrap_or_default(),
+                        ));
+                    }
+                    self.install_queue.extend(deferred);
+                    self.open_next_mod();
+                }
                 Message::Extract => {
                     let mods = self.selected.clone();
                     self.do_task(move |core| tasks::extract_mods(&core, mods));
@@ -520,6 +751,19 @@ impl App {
                         Err(e) => self.do_update(Message::Error(e)),
                     };
                 }
+                Message::HandleMigration(report) => {
+                    self.do_update(Message::HandleSettings);
+                    if !report.is_empty() {
+                        self.do_update(Message::Confirm(
+                            Box::new(Message::Noop),
+                            format!(
+                                "{}\n\n{}",
+                                "Settings_Migrate_Report".localize(),
+                                report.join("\n")
+                            ),
+                        ));
+                    }
+                }
                 Message::HandleSettings => {
                     self.temp_settings = self.core.settings().clone();
                     self.toasts.add({
@@ -538,14 +782,26 @@ impl App {
                     if !update {
                         mod_.enable_default_options();
                     }
+                    self.option_picker = options::OptionPickerState::load(&self.core, &mod_);
+                    tasks::check_option_conflicts(mod_.clone(), self.channel.0.clone());
                     self.options_mod = Some((mod_, update));
                 }
+                Message::OptionConflicts(hash, conflicts) => {
+                    // Ignore results for a picker which has since closed
+                    if self
+                        .options_mod
+                        .as_ref()
+                        .is_some_and(|(mod_, _)| mod_.hash() == hash)
+                    {
+                        self.option_picker.set_conflicts(conflicts);
+                    }
+                }
                 Message::UpdateOptions(mod_) => {
                     let opts = mod_.enabled_options.clone();
                     match self
                         .core
                         .mod_manager()
-                        .set_enabled_options(mod_.hash(), opts)
+                        .set_enabled_options(mod_.hash(), opts, None)
                     {
                         Ok(manifest) => {
                             self.dirty_mut().extend(&manifest);
@@ -572,9 +828,7 @@ impl App {
                     } else {
                         log::warn!("More operations in queue, stashing error and continuing…");
                         self.error_queue.push_back(error);
-                        if let Some(path) = self.install_queue.pop_front() {
-                            self.do_task(move |core| tasks::open_mod(&core, &path, None));
-                        }
+                        self.open_next_mod();
                     }
                 }
 
@@ -621,9 +875,69 @@ impl App {
                         .save_file()
                     {
                         builder.dest = dest;
+                        if let Some(mut project) = self
+                            .projects
+                            .iter()
+                            .find(|p| p.path == builder.source)
+                            .cloned()
+                        {
+                            // Remember the output and meta for repackaging later
+                            project.config.output = Some(builder.dest.clone());
+                            if let Err(e) = project
+                                .save()
+                                .and_then(|_| project.save_meta(&builder.meta))
+                            {
+                                log::warn!("Failed to update project {}: {:?}", project.name, e);
+                            }
+                            self.do_update(Message::RefreshProjects);
+                        }
                         self.do_task(move |core| tasks::package_mod(&core, builder));
                     }
                 }
+                Message::RefreshProjects => {
+                    self.projects = Project::list(&self.core.settings());
+                }
+                Message::NewProject => {
+                    let builder = self.package_builder.borrow().clone();
+                    self.do_task(move |core| tasks::new_project(&core, builder));
+                }
+                Message::EditProject(project) => {
+                    self.busy.set(false);
+                    let mut builder = self.package_builder.borrow_mut();
+                    builder.source.clone_from(&project.path);
+                    match project.meta() {
+                        Ok(meta) => builder.meta = meta,
+                        Err(e) => log::warn!("{e:?}"),
+                    }
+                    drop(builder);
+                    self.do_update(Message::RefreshProjects);
+                }
+                Message::RepackageProject(project) => {
+                    self.do_task(move |core| tasks::repackage_project(&core, project));
+                }
+                Message::ProjectPackaged(dirty, output) => {
+                    self.busy.set(false);
+                    if let Some(dirty) = dirty {
+                        self.dirty_mut().extend(&dirty);
+                        self.mods = self.core.mod_manager().all_mods().collect();
+                        self.selected.retain(|m| self.mods.contains(m));
+                        self.do_update(Message::RefreshModsDisplay);
+                        info::ROOTS.write().clear();
+                    }
+                    self.do_update(Message::RefreshProjects);
+                    self.do_update(Message::Toast(format!(
+                        "{} {}",
+                        "Package_Project_Built".localize(),
+                        output.display()
+                    )));
+                }
+                Message::InspectResource(mod_, file, merged) => {
+                    self.do_task(move |core| tasks::inspect_resource(&core, mod_, file, merged));
+                }
+                Message::ShowResource(view) => {
+                    self.busy.set(false);
+                    self.inspected = Some(view);
+                }
                 Message::ResetPacker => {
                     self.package_builder.borrow_mut().reset(self.platform());
                     self.busy.set(false);